@@ -0,0 +1,193 @@
+use crate::bitboard::*;
+
+// ══════════════════════════════════════════════════════════════════════════════
+// KPK BITBASE
+// ══════════════════════════════════════════════════════════════════════════════
+//
+// Win/draw for every king + pawn vs king position with the pawn on files
+// a-d, built by retrograde analysis on first use. Indexed by
+// (white king, black king, side to move, pawn file, pawn rank).
+
+const MAX_INDEX: usize = 2 * 24 * 64 * 64;
+
+// Classification bits; a position's successors are OR-ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+const WHITE: usize = 0;
+const BLACK: usize = 1;
+
+lazy_static::lazy_static! {
+    static ref KPK_BITBASE: Vec<u64> = generate();
+}
+
+fn index(stm: usize, black_king: u8, white_king: u8, pawn: u8) -> usize {
+    white_king as usize
+        | (black_king as usize) << 6
+        | stm << 12
+        | ((pawn % 8) as usize) << 13
+        | ((6 - pawn / 8) as usize) << 15
+}
+
+struct KpkPosition {
+    stm: usize,
+    kings: [u8; 2],
+    pawn: u8,
+    result: u8,
+}
+
+impl KpkPosition {
+    fn new(idx: usize) -> Self {
+        let tables = &ATTACK_TABLES;
+        let white_king = (idx & 0x3F) as u8;
+        let black_king = ((idx >> 6) & 0x3F) as u8;
+        let stm = (idx >> 12) & 1;
+        let pawn = (6 - ((idx >> 15) & 0x7) as u8) * 8 + ((idx >> 13) & 0x3) as u8;
+        let push = pawn + 8;
+
+        let result = if distance(white_king, black_king) <= 1
+            || white_king == pawn
+            || black_king == pawn
+            || (stm == WHITE && tables.pawn_attacks[WHITE][pawn as usize] & (1u64 << black_king) != 0)
+        {
+            // Overlapping pieces or a king that can be captured
+            INVALID
+        } else if stm == WHITE
+            && pawn / 8 == 6
+            && white_king != push
+            && (distance(black_king, push) > 1 || distance(white_king, push) == 1)
+        {
+            // Pawn promotes and can't be taken
+            WIN
+        } else if stm == BLACK
+            && ((tables.king_attacks[black_king as usize]
+                & !(tables.king_attacks[white_king as usize] | tables.pawn_attacks[WHITE][pawn as usize]))
+                == 0
+                || tables.king_attacks[black_king as usize] & !tables.king_attacks[white_king as usize] & (1u64 << pawn) != 0)
+        {
+            // Stalemate, or the black king takes the pawn
+            DRAW
+        } else {
+            UNKNOWN
+        };
+
+        KpkPosition { stm, kings: [white_king, black_king], pawn, result }
+    }
+
+    // White needs one winning successor, black one drawing successor
+    fn classify(&mut self, db: &[u8]) -> u8 {
+        let tables = &ATTACK_TABLES;
+        let (good, bad) = if self.stm == WHITE { (WIN, DRAW) } else { (DRAW, WIN) };
+        let [white_king, black_king] = self.kings;
+
+        let mut r = INVALID;
+        let mut moves = tables.king_attacks[self.kings[self.stm] as usize];
+        while moves != 0 {
+            let (new_bb, sq) = pop_lsb(moves);
+            moves = new_bb;
+            let to = sq.unwrap();
+            r |= if self.stm == WHITE {
+                db[index(BLACK, black_king, to, self.pawn)]
+            } else {
+                db[index(WHITE, to, white_king, self.pawn)]
+            };
+        }
+
+        if self.stm == WHITE {
+            let push = self.pawn + 8;
+            if self.pawn / 8 < 6 {
+                r |= db[index(BLACK, black_king, white_king, push)];
+            }
+            if self.pawn / 8 == 1 && push != white_king && push != black_king {
+                r |= db[index(BLACK, black_king, white_king, push + 8)];
+            }
+        }
+
+        self.result = if r & good != 0 {
+            good
+        } else if r & UNKNOWN != 0 {
+            UNKNOWN
+        } else {
+            bad
+        };
+        self.result
+    }
+}
+
+fn generate() -> Vec<u64> {
+    let mut positions: Vec<KpkPosition> = (0..MAX_INDEX).map(KpkPosition::new).collect();
+    let mut db: Vec<u8> = positions.iter().map(|pos| pos.result).collect();
+
+    // Keep resolving unknowns until a full pass changes nothing
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (idx, pos) in positions.iter_mut().enumerate() {
+            if pos.result == UNKNOWN && pos.classify(&db) != UNKNOWN {
+                db[idx] = pos.result;
+                changed = true;
+            }
+        }
+    }
+
+    let mut bits = vec![0u64; MAX_INDEX / 64];
+    for (idx, &result) in db.iter().enumerate() {
+        if result == WIN {
+            bits[idx / 64] |= 1u64 << (idx % 64);
+        }
+    }
+    bits
+}
+
+/// Builds the bitbase now rather than on the first KPK probe mid-search.
+pub fn init() {
+    lazy_static::initialize(&KPK_BITBASE);
+}
+
+/// Whether the side with the pawn wins. Squares are given with the pawn
+/// side as white; `stm` is 0 when that side is to move.
+pub fn probe_kpk(white_king: u8, pawn: u8, black_king: u8, stm: usize) -> bool {
+    // Mirror onto files a-d
+    let (white_king, pawn, black_king) = if pawn % 8 > 3 {
+        (white_king ^ 7, pawn ^ 7, black_king ^ 7)
+    } else {
+        (white_king, pawn, black_king)
+    };
+
+    let idx = index(stm, black_king, white_king, pawn);
+    KPK_BITBASE[idx / 64] & (1u64 << (idx % 64)) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::parse_square;
+
+    fn sq(name: &str) -> u8 {
+        parse_square(name).unwrap()
+    }
+
+    #[test]
+    fn test_key_square_win() {
+        // King on a key square in front of the pawn wins whoever moves
+        assert!(probe_kpk(sq("d6"), sq("d4"), sq("d8"), WHITE));
+        assert!(probe_kpk(sq("d6"), sq("d4"), sq("d8"), BLACK));
+        assert!(probe_kpk(sq("f6"), sq("f4"), sq("f8"), BLACK));
+    }
+
+    #[test]
+    fn test_opposition_decides() {
+        // Kings in opposition in front of the pawn: whoever moves gives way
+        assert!(probe_kpk(sq("e5"), sq("e4"), sq("e7"), BLACK));
+        assert!(!probe_kpk(sq("e5"), sq("e4"), sq("e7"), WHITE));
+    }
+
+    #[test]
+    fn test_rook_pawn_corner_draw() {
+        assert!(!probe_kpk(sq("a6"), sq("a5"), sq("a8"), WHITE));
+        assert!(!probe_kpk(sq("b6"), sq("a6"), sq("a8"), WHITE));
+        assert!(!probe_kpk(sq("h6"), sq("h5"), sq("h8"), BLACK));
+    }
+}
@@ -60,6 +60,14 @@ pub fn lsb(bb: Bitboard) -> Option<u8> {
     }
 }
 
+// King moves between two squares (Chebyshev distance)
+#[inline(always)]
+pub fn distance(a: u8, b: u8) -> u8 {
+    let file_distance = (a % 8).abs_diff(b % 8);
+    let rank_distance = (a / 8).abs_diff(b / 8);
+    file_distance.max(rank_distance)
+}
+
 // **Parallel bit extraction using PEXT-like operations**
 // These are used for magic bitboards in sliding piece move generation
 #[inline(always)]
@@ -89,8 +97,15 @@ pub struct AttackTables {
     // Magic bitboard tables for sliding pieces
     pub rook_magics: [MagicEntry; 64],
     pub bishop_magics: [MagicEntry; 64],
+    rook_attacks: Vec<Bitboard>,
+    bishop_attacks: Vec<Bitboard>,
+    // Squares strictly between / full line through two aligned squares
+    between_bb: [[Bitboard; 64]; 64],
+    line_bb: [[Bitboard; 64]; 64],
 }
 
+/// A zero `magic` marks a square whose shipped constant collided during
+/// init; lookups for that square fall back to walking the rays.
 #[derive(Clone, Copy)]
 pub struct MagicEntry {
     pub mask: Bitboard,
@@ -99,6 +114,13 @@ pub struct MagicEntry {
     pub offset: usize,
 }
 
+impl MagicEntry {
+    #[inline(always)]
+    fn index(&self, occ: Bitboard) -> usize {
+        self.offset + ((occ & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
 impl AttackTables {
     pub fn new() -> Self {
         let mut tables = AttackTables {
@@ -117,12 +139,17 @@ impl AttackTables {
                 shift: 0,
                 offset: 0,
             }; 64],
+            rook_attacks: vec![0; 64 * 4096],
+            bishop_attacks: vec![0; 64 * 512],
+            between_bb: [[0; 64]; 64],
+            line_bb: [[0; 64]; 64],
         };
         
         tables.init_pawn_attacks();
         tables.init_knight_attacks();
         tables.init_king_attacks();
         tables.init_magics();
+        tables.init_lines();
         
         tables
     }
@@ -196,8 +223,6 @@ impl AttackTables {
 
     // **Initialize magic bitboards for faster sliding piece attacks**
     fn init_magics(&mut self) {
-        // Simplified magic initialization
-        // In production, these would be pre-computed optimal magic numbers
         for sq in 0..64 {
             self.rook_magics[sq] = MagicEntry {
                 mask: self.rook_mask(sq as u8),
@@ -205,6 +230,9 @@ impl AttackTables {
                 shift: 52, // 12-bit index
                 offset: sq * 4096,
             };
+            if !Self::fill_magic_table(&mut self.rook_attacks, &self.rook_magics[sq], sq as u8, slow_rook_attacks) {
+                self.rook_magics[sq].magic = 0;
+            }
             
             self.bishop_magics[sq] = MagicEntry {
                 mask: self.bishop_mask(sq as u8),
@@ -212,6 +240,61 @@ impl AttackTables {
                 shift: 55, // 9-bit index
                 offset: sq * 512,
             };
+            if !Self::fill_magic_table(&mut self.bishop_attacks, &self.bishop_magics[sq], sq as u8, slow_bishop_attacks) {
+                self.bishop_magics[sq].magic = 0;
+            }
+        }
+    }
+
+    // Enumerate every occupancy subset of the mask (Carry-Rippler) and store
+    // its attack set at the magic index. Returns false on a destructive
+    // collision, leaving the square's slice cleared.
+    fn fill_magic_table(table: &mut [Bitboard], entry: &MagicEntry, sq: u8, slow: fn(u8, Bitboard) -> Bitboard) -> bool {
+        let size = 1usize << (64 - entry.shift);
+        let mut subset: Bitboard = 0;
+        
+        loop {
+            let attacks = slow(sq, subset);
+            let index = entry.index(subset);
+            
+            // A slider always attacks at least one square, so 0 means unused
+            if table[index] != 0 && table[index] != attacks {
+                table[entry.offset..entry.offset + size].fill(0);
+                return false;
+            }
+            table[index] = attacks;
+            
+            subset = subset.wrapping_sub(entry.mask) & entry.mask;
+            if subset == 0 {
+                break;
+            }
+        }
+        
+        true
+    }
+
+    // Needs the slider tables, so runs after init_magics
+    fn init_lines(&mut self) {
+        for a in 0..64u8 {
+            for b in 0..64u8 {
+                if a == b {
+                    continue;
+                }
+                let a_bb = 1u64 << a;
+                let b_bb = 1u64 << b;
+                
+                if get_bit(self.get_rook_attacks(a, 0), b) {
+                    self.line_bb[a as usize][b as usize] =
+                        (self.get_rook_attacks(a, 0) & self.get_rook_attacks(b, 0)) | a_bb | b_bb;
+                    self.between_bb[a as usize][b as usize] =
+                        self.get_rook_attacks(a, b_bb) & self.get_rook_attacks(b, a_bb);
+                } else if get_bit(self.get_bishop_attacks(a, 0), b) {
+                    self.line_bb[a as usize][b as usize] =
+                        (self.get_bishop_attacks(a, 0) & self.get_bishop_attacks(b, 0)) | a_bb | b_bb;
+                    self.between_bb[a as usize][b as usize] =
+                        self.get_bishop_attacks(a, b_bb) & self.get_bishop_attacks(b, a_bb);
+                }
+            }
         }
     }
 
@@ -308,67 +391,128 @@ impl AttackTables {
     // **Fast sliding piece attack generation using magic bitboards**
     #[inline(always)]
     pub fn get_bishop_attacks(&self, sq: u8, occ: Bitboard) -> Bitboard {
-        // Use classical method for now (magic bitboards would be faster)
-        let mut attacks = 0;
-        let directions: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
-        
-        let rank = (sq / 8) as i8;
-        let file = (sq % 8) as i8;
-        
-        for (dr, df) in directions.iter() {
-            let mut r = rank + dr;
-            let mut f = file + df;
-            
-            while (0..8).contains(&r) && (0..8).contains(&f) {
-                let target = (r * 8 + f) as u8;
-                attacks = set_bit(attacks, target);
-                if get_bit(occ, target) {
-                    break;
-                }
-                r += dr;
-                f += df;
-            }
+        let entry = &self.bishop_magics[sq as usize];
+        if entry.magic == 0 {
+            return slow_bishop_attacks(sq, occ);
         }
-        
-        attacks
+        self.bishop_attacks[entry.index(occ)]
     }
 
     #[inline(always)]
     pub fn get_rook_attacks(&self, sq: u8, occ: Bitboard) -> Bitboard {
-        let mut attacks = 0;
-        let directions: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-        
-        let rank = (sq / 8) as i8;
-        let file = (sq % 8) as i8;
-        
-        for (dr, df) in directions.iter() {
-            let mut r = rank + dr;
-            let mut f = file + df;
-            
-            while (0..8).contains(&r) && (0..8).contains(&f) {
-                let target = (r * 8 + f) as u8;
-                attacks = set_bit(attacks, target);
-                if get_bit(occ, target) {
-                    break;
-                }
-                r += dr;
-                f += df;
-            }
+        let entry = &self.rook_magics[sq as usize];
+        if entry.magic == 0 {
+            return slow_rook_attacks(sq, occ);
         }
-        
-        attacks
+        self.rook_attacks[entry.index(occ)]
     }
 
     #[inline(always)]
     pub fn get_queen_attacks(&self, sq: u8, occ: Bitboard) -> Bitboard {
         self.get_rook_attacks(sq, occ) | self.get_bishop_attacks(sq, occ)
     }
+
+    /// Squares strictly between `a` and `b` if they share a rank, file or
+    /// diagonal, otherwise empty.
+    #[inline(always)]
+    pub fn between(&self, a: u8, b: u8) -> Bitboard {
+        self.between_bb[a as usize][b as usize]
+    }
+
+    /// The whole rank, file or diagonal through `a` and `b` (edge to edge),
+    /// or empty if they are not aligned.
+    #[inline(always)]
+    pub fn line(&self, a: u8, b: u8) -> Bitboard {
+        self.line_bb[a as usize][b as usize]
+    }
+}
+
+// Classical ray walk, used to build the magic tables and as the fallback
+// for squares without a working magic
+fn slow_bishop_attacks(sq: u8, occ: Bitboard) -> Bitboard {
+    slide_attacks(sq, occ, &[(-1, -1), (-1, 1), (1, -1), (1, 1)])
+}
+
+fn slow_rook_attacks(sq: u8, occ: Bitboard) -> Bitboard {
+    slide_attacks(sq, occ, &[(-1, 0), (1, 0), (0, -1), (0, 1)])
+}
+
+fn slide_attacks(sq: u8, occ: Bitboard, directions: &[(i8, i8); 4]) -> Bitboard {
+    let mut attacks = 0;
+    
+    let rank = (sq / 8) as i8;
+    let file = (sq % 8) as i8;
+    
+    for (dr, df) in directions.iter() {
+        let mut r = rank + dr;
+        let mut f = file + df;
+        
+        while (0..8).contains(&r) && (0..8).contains(&f) {
+            let target = (r * 8 + f) as u8;
+            attacks = set_bit(attacks, target);
+            if get_bit(occ, target) {
+                break;
+            }
+            r += dr;
+            f += df;
+        }
+    }
+    
+    attacks
 }
 
 lazy_static::lazy_static! {
     pub static ref ATTACK_TABLES: AttackTables = AttackTables::new();
 }
 
+// ══════════════════════════════════════════════════════════════════════════════
+// PAWN STRUCTURE MASKS
+// ══════════════════════════════════════════════════════════════════════════════
+
+lazy_static::lazy_static! {
+    /// Squares on the same and adjacent files ahead of a pawn, by
+    /// [color][square]; a pawn is passed when no enemy pawn is on them.
+    pub static ref PASSED_PAWN_MASK: [[Bitboard; 64]; 2] = square_masks(|color, sq| {
+        forward_ranks(color, sq) & (ISOLATED_MASK[(sq % 8) as usize] | FILE_A << (sq % 8))
+    });
+    /// Squares ahead of a pawn on its own file, by [color][square].
+    pub static ref FRONT_SPAN: [[Bitboard; 64]; 2] = square_masks(|color, sq| forward_ranks(color, sq) & FILE_A << (sq % 8));
+    /// The files either side of a file: a pawn with no friendly pawns there
+    /// is isolated.
+    pub static ref ISOLATED_MASK: [Bitboard; 8] = {
+        let mut masks = [0; 8];
+        for (file, mask) in masks.iter_mut().enumerate() {
+            if file > 0 {
+                *mask |= FILE_A << (file - 1);
+            }
+            if file < 7 {
+                *mask |= FILE_A << (file + 1);
+            }
+        }
+        masks
+    };
+}
+
+fn square_masks(mask: impl Fn(usize, u8) -> Bitboard) -> [[Bitboard; 64]; 2] {
+    let mut masks = [[0; 64]; 2];
+    for (color, color_masks) in masks.iter_mut().enumerate() {
+        for sq in 0..64u8 {
+            color_masks[sq as usize] = mask(color, sq);
+        }
+    }
+    masks
+}
+
+/// Every rank in front of `sq` from `color`'s side of the board.
+pub fn forward_ranks(color: usize, sq: u8) -> Bitboard {
+    let rank = sq / 8;
+    match (color, rank) {
+        (0, 7) | (1, 0) => 0,
+        (0, _) => FULL << (8 * (rank + 1)),
+        _ => FULL >> (8 * (8 - rank)),
+    }
+}
+
 // **Additional SIMD-friendly utility functions**
 
 // Shift entire bitboard (useful for pawn pushes)
@@ -407,4 +551,95 @@ pub fn fill_south(mut bb: Bitboard, empty: Bitboard) -> Bitboard {
     bb |= empty & (bb >> 16);
     bb |= empty & (bb >> 32);
     bb
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    // a1 = 0, rank-major
+    fn sq(name: &str) -> u8 {
+        let b = name.as_bytes();
+        (b[1] - b'1') * 8 + (b[0] - b'a')
+    }
+
+    #[test]
+    fn test_passed_pawn_mask_e5() {
+        let expected = ["d6", "e6", "f6", "d7", "e7", "f7", "d8", "e8", "f8"]
+            .iter()
+            .fold(0, |bb, name| set_bit(bb, sq(name)));
+        assert_eq!(PASSED_PAWN_MASK[0][sq("e5") as usize], expected);
+        assert_eq!(FRONT_SPAN[0][sq("e5") as usize], set_bit(set_bit(set_bit(0, sq("e6")), sq("e7")), sq("e8")));
+        assert_eq!(ISOLATED_MASK[0], FILE_B);
+        assert_eq!(ISOLATED_MASK[7], FILE_A << 6);
+    }
+
+    #[test]
+    fn test_passed_pawn_mask_matches_loop() {
+        // The nested loops the evaluator used to build the mask with
+        fn ahead_mask(color: usize, square: u8) -> Bitboard {
+            let (file, rank) = (square % 8, square / 8);
+            let ranks: Vec<u8> = if color == 0 { ((rank + 1)..8).collect() } else { (0..rank).collect() };
+            let mut mask = 0;
+            for r in ranks {
+                if file > 0 {
+                    mask = set_bit(mask, r * 8 + file - 1);
+                }
+                mask = set_bit(mask, r * 8 + file);
+                if file < 7 {
+                    mask = set_bit(mask, r * 8 + file + 1);
+                }
+            }
+            mask
+        }
+
+        for color in 0..2 {
+            for square in 0..64u8 {
+                assert_eq!(PASSED_PAWN_MASK[color][square as usize], ahead_mask(color, square), "{} {}", color, square);
+            }
+        }
+    }
+
+    #[test]
+    fn test_between() {
+        let tables = &ATTACK_TABLES;
+        assert_eq!(tables.between(sq("a1"), sq("a4")), (1u64 << sq("a2")) | (1u64 << sq("a3")));
+        assert_eq!(tables.between(sq("a4"), sq("a1")), (1u64 << sq("a2")) | (1u64 << sq("a3")));
+        assert_eq!(tables.between(sq("a1"), sq("b3")), 0);
+        assert_eq!(tables.between(sq("a1"), sq("b2")), 0);
+        assert_eq!(tables.between(sq("h8"), sq("e5")), (1u64 << sq("g7")) | (1u64 << sq("f6")));
+    }
+
+    #[test]
+    fn test_line() {
+        let tables = &ATTACK_TABLES;
+        let diagonal = ["c1", "d2", "e3", "f4", "g5", "h6"]
+            .iter()
+            .fold(0u64, |bb, s| bb | (1u64 << sq(s)));
+        assert_eq!(tables.line(sq("c1"), sq("f4")), diagonal);
+        assert_eq!(tables.line(sq("f4"), sq("c1")), diagonal);
+        assert_eq!(tables.line(sq("b2"), sq("b7")), FILE_B);
+        assert_eq!(tables.line(sq("a1"), sq("b3")), 0);
+    }
+
+    #[test]
+    fn test_magic_attacks_match_ray_walk() {
+        let tables = &ATTACK_TABLES;
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for sq in 0..64u8 {
+            for i in 0..200 {
+                // Mix dense and sparse boards
+                let occ: Bitboard = match i % 3 {
+                    0 => rng.gen(),
+                    1 => rng.gen::<u64>() & rng.gen::<u64>(),
+                    _ => rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>(),
+                };
+                assert_eq!(tables.get_rook_attacks(sq, occ), slow_rook_attacks(sq, occ), "rook sq {} occ {:#x}", sq, occ);
+                assert_eq!(tables.get_bishop_attacks(sq, occ), slow_bishop_attacks(sq, occ), "bishop sq {} occ {:#x}", sq, occ);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::bitboard::*;
 use crate::zobrist::ZOBRIST;
+use crate::eval;
 use crate::movegen::{Move, CAPTURE, EP_CAPTURE, DOUBLE_PAWN_PUSH, KING_CASTLE, QUEEN_CASTLE};
 use std::collections::VecDeque;
 
@@ -30,8 +31,31 @@ impl Color {
     }
 }
 
+/// How a game stands; `Checkmate` holds the winning side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    Ongoing,
+    Checkmate(Color),
+    Stalemate,
+    DrawSeventyFiveMove,
+    DrawRepetition,
+    DrawInsufficient,
+}
+
+/// What `make_null_move` changed, for `unmake_null_move` to put back.
+#[derive(Clone, Copy, Debug)]
+pub struct NullUndo {
+    ep_square: Option<u8>,
+    hash: u64,
+    halfmove_clock: u16,
+    plies_since_null: u16,
+}
+
 pub const PIECE_VALUES: [i32; 7] = [0, 100, 320, 330, 500, 900, 20000];
 
+// Rook home squares for the K, Q, k and q castling rights in standard chess
+const STANDARD_CASTLING_ROOKS: [u8; 4] = [7, 0, 63, 56];
+
 #[derive(Clone)]
 pub struct BoardState {
     pub pieces: [[Bitboard; 7]; 2],
@@ -39,10 +63,27 @@ pub struct BoardState {
     pub all_pieces: Bitboard,
     pub side_to_move: Color,
     pub castling_rights: u8,
+    // Home square of the rook each castling right (K, Q, k, q) castles
+    // with; the corners unless a Chess960 setup puts them elsewhere
+    pub castling_rooks: [u8; 4],
+    // Write castling as the king taking its own rook (e1h1), the way
+    // Chess960 GUIs expect
+    pub chess960: bool,
     pub ep_square: Option<u8>,
     pub halfmove_clock: u16,
+    // Half-moves since the last null move on the board; repetitions are
+    // never looked for across one
+    pub plies_since_null: u16,
     pub fullmove_number: u16,
     pub hash: u64,
+    // Zobrist key of the pawns alone, for the evaluator's pawn hash
+    pub pawn_hash: u64,
+    // Pawns and kings only, for caches of king safety against the pawns
+    pub pawn_king_hash: u64,
+    // Material plus PST from the built-in eval weights, white minus black,
+    // kept up to date by make_move
+    pub mg_material_pst: i32,
+    pub eg_material_pst: i32,
     pub position_history: VecDeque<u64>,
 }
 
@@ -66,10 +107,17 @@ impl BoardState {
             all_pieces: 0,
             side_to_move: Color::White,
             castling_rights: 0,
+            castling_rooks: STANDARD_CASTLING_ROOKS,
+            chess960: false,
             ep_square: None,
             halfmove_clock: 0,
+            plies_since_null: u16::MAX,
             fullmove_number: 1,
             hash: 0,
+            pawn_hash: 0,
+            pawn_king_hash: 0,
+            mg_material_pst: 0,
+            eg_material_pst: 0,
             position_history: VecDeque::with_capacity(100),
         };
 
@@ -105,20 +153,49 @@ impl BoardState {
             }
         }
 
+        // Check detection, pins and king safety all assume one king a side
+        for (color, name) in [(Color::White, "white"), (Color::Black, "black")] {
+            let kings = count_bits(board.pieces[color as usize][Piece::King as usize]);
+            if kings != 1 {
+                return Err(format!("Invalid FEN: {} has {} kings", name, kings));
+            }
+        }
+
         board.side_to_move = if parts[1] == "w" { Color::White } else { Color::Black };
 
+        // K and Q take the outermost rook on that wing (X-FEN), a file
+        // letter the rook on that file (Shredder-FEN)
         if parts[2] != "-" {
             for ch in parts[2].chars() {
-                board.castling_rights |= match ch {
-                    'K' => 1,
-                    'Q' => 2,
-                    'k' => 4,
-                    'q' => 8,
-                    _ => 0,
+                let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+                let base = if color == Color::White { 0 } else { 56 };
+                let king_file = board.get_king_square(color).unwrap() % 8;
+                let rooks = board.pieces[color as usize][Piece::Rook as usize];
+                let (kingside, rook_file) = match ch.to_ascii_lowercase() {
+                    'k' => (true, (king_file + 1..8).rev().find(|&file| get_bit(rooks, base + file)).unwrap_or(7)),
+                    'q' => (false, (0..king_file).find(|&file| get_bit(rooks, base + file)).unwrap_or(0)),
+                    file @ 'a'..='h' => {
+                        let file = file as u8 - b'a';
+                        (file > king_file, file)
+                    }
+                    _ => continue,
                 };
+
+                let right = 2 * color as usize + !kingside as usize;
+                board.castling_rights |= 1 << right;
+                board.castling_rooks[right] = base + rook_file;
             }
         }
 
+        // Castling rights standard chess can't have make it a Chess960 game
+        board.chess960 = (0..4).any(|right| {
+            let king_home = if right < 2 { 4 } else { 60 };
+            let color = if right < 2 { Color::White } else { Color::Black };
+            board.castling_rights & (1 << right) != 0
+                && (board.castling_rooks[right] != STANDARD_CASTLING_ROOKS[right]
+                    || board.get_king_square(color) != Some(king_home))
+        });
+
         if parts[3] != "-" {
             board.ep_square = Some(parse_square(parts[3])?);
         }
@@ -131,11 +208,39 @@ impl BoardState {
         }
 
         board.hash = board.compute_hash();
+        board.pawn_hash = board.compute_pawn_hash();
+        board.pawn_king_hash = board.compute_pawn_king_hash();
+        (board.mg_material_pst, board.eg_material_pst) = board.compute_material_pst();
         board.position_history.push_back(board.hash);
 
         Ok(board)
     }
 
+    /// The same position with the colours swapped: ranks flipped, white
+    /// pieces black and the other side to move.
+    pub fn mirrored(&self) -> Self {
+        let fen = self.to_fen();
+        let parts: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |ch: char| if ch.is_ascii_uppercase() { ch.to_ascii_lowercase() } else { ch.to_ascii_uppercase() };
+
+        let placement: Vec<String> = parts[0].split('/').rev().map(|rank| rank.chars().map(swap_case).collect()).collect();
+        let side = if self.side_to_move == Color::White { "b" } else { "w" };
+        let mut castling: Vec<char> = parts[2].chars().map(swap_case).collect();
+        castling.sort_by_key(|ch| "KQkq-".find(*ch));
+        let ep = self.ep_square.map_or("-".to_string(), |sq| square_name(sq ^ 56));
+
+        let fen = format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            side,
+            castling.into_iter().collect::<String>(),
+            ep,
+            self.halfmove_clock,
+            self.fullmove_number
+        );
+        Self::from_fen(&fen).expect("mirrored FEN of a valid board")
+    }
+
     pub fn to_fen(&self) -> String {
         let mut fen = String::new();
         
@@ -143,21 +248,12 @@ impl BoardState {
             let mut empty = 0;
             for file in 0..8 {
                 let sq = rank * 8 + file;
-                if let Some((piece, color)) = self.piece_at(sq) {
+                if let Some(ch) = self.piece_char(sq) {
                     if empty > 0 {
                         fen.push_str(&empty.to_string());
                         empty = 0;
                     }
-                    let ch = match piece {
-                        Piece::Pawn => 'p',
-                        Piece::Knight => 'n',
-                        Piece::Bishop => 'b',
-                        Piece::Rook => 'r',
-                        Piece::Queen => 'q',
-                        Piece::King => 'k',
-                        _ => continue,
-                    };
-                    fen.push(if color == Color::White { ch.to_ascii_uppercase() } else { ch });
+                    fen.push(ch);
                 } else {
                     empty += 1;
                 }
@@ -177,10 +273,19 @@ impl BoardState {
         if self.castling_rights == 0 {
             fen.push('-');
         } else {
-            if self.castling_rights & 1 != 0 { fen.push('K'); }
-            if self.castling_rights & 2 != 0 { fen.push('Q'); }
-            if self.castling_rights & 4 != 0 { fen.push('k'); }
-            if self.castling_rights & 8 != 0 { fen.push('q'); }
+            // Rooks off the corners go by their file, Shredder-FEN style
+            for (right, standard) in "KQkq".chars().enumerate() {
+                if self.castling_rights & (1 << right) == 0 {
+                    continue;
+                }
+                let rook = self.castling_rooks[right];
+                if rook == STANDARD_CASTLING_ROOKS[right] {
+                    fen.push(standard);
+                } else {
+                    let file = (b'a' + rook % 8) as char;
+                    fen.push(if right < 2 { file.to_ascii_uppercase() } else { file });
+                }
+            }
         }
 
         fen.push(' ');
@@ -224,6 +329,33 @@ impl BoardState {
         None
     }
 
+    /// FEN letter for the piece on `sq`, uppercase for white.
+    pub fn piece_char(&self, sq: u8) -> Option<char> {
+        let (piece, color) = self.piece_at(sq)?;
+        let ch = match piece {
+            Piece::Pawn => 'p',
+            Piece::Knight => 'n',
+            Piece::Bishop => 'b',
+            Piece::Rook => 'r',
+            Piece::Queen => 'q',
+            Piece::King => 'k',
+            Piece::Empty => return None,
+        };
+        Some(if color == Color::White { ch.to_ascii_uppercase() } else { ch })
+    }
+
+    /// The engine's own Zobrist key of the position. Built from a fixed seed,
+    /// so it is the same on every run, but it means nothing to other
+    /// programs; use `polyglot_key` to talk to opening books.
+    pub fn zobrist_key(&self) -> u64 {
+        self.hash
+    }
+
+    /// The standard Polyglot key, as used by `.bin` opening books.
+    pub fn polyglot_key(&self) -> u64 {
+        crate::opening_book::polyglot_key(self)
+    }
+
     pub fn get_king_square(&self, color: Color) -> Option<u8> {
         lsb(self.pieces[color as usize][Piece::King as usize])
     }
@@ -269,20 +401,114 @@ impl BoardState {
         false
     }
 
+    /// Pieces of `by_color` attacking `sq` on the current board.
+    pub fn attackers(&self, sq: u8, by_color: Color) -> Bitboard {
+        let tables = &ATTACK_TABLES;
+        let pieces = &self.pieces[by_color as usize];
+
+        (tables.pawn_attacks[by_color.flip() as usize][sq as usize] & pieces[Piece::Pawn as usize])
+            | (tables.knight_attacks[sq as usize] & pieces[Piece::Knight as usize])
+            | (tables.get_bishop_attacks(sq, self.all_pieces) & (pieces[Piece::Bishop as usize] | pieces[Piece::Queen as usize]))
+            | (tables.get_rook_attacks(sq, self.all_pieces) & (pieces[Piece::Rook as usize] | pieces[Piece::Queen as usize]))
+            | (tables.king_attacks[sq as usize] & pieces[Piece::King as usize])
+    }
+
+    /// Earlier occurrences of the current position, looking back no further
+    /// than the last capture or pawn move.
+    pub fn repetition_count(&self) -> usize {
+        self.position_history
+            .iter()
+            .rev()
+            .skip(1)
+            .take(self.repetition_window())
+            .filter(|&&h| h == self.hash)
+            .count()
+    }
+
+    /// How far back an earlier occurrence can be: not past the last capture
+    /// or pawn move, nor past a null move, whose positions no game reaches.
+    fn repetition_window(&self) -> usize {
+        self.halfmove_clock.min(self.plies_since_null) as usize
+    }
+
+    /// Seen once before: enough for the search to score the line as a draw.
     pub fn is_repetition(&self) -> bool {
-        self.position_history.iter().filter(|&&h| h == self.hash).count() >= 2
+        self.repetition_count() >= 1
+    }
+
+    /// Third occurrence, the repetition that actually draws the game.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 2
+    }
+
+    /// Repetition rule for a search `ply` half-moves below its root. A
+    /// position repeated inside the tree is scored as a draw straight away,
+    /// since the side that could avoid it can equally repeat again; one that
+    /// only occurred before the root needs the full threefold.
+    pub fn is_repetition_draw(&self, ply: usize) -> bool {
+        let mut count = 0;
+        for (distance, &h) in self.position_history
+            .iter()
+            .rev()
+            .skip(1)
+            .take(self.repetition_window())
+            .enumerate()
+        {
+            if h != self.hash {
+                continue;
+            }
+            // distance + 1 half-moves back; the root itself is ply moves back
+            if distance + 1 < ply {
+                return true;
+            }
+            count += 1;
+            if count >= 2 {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// A hundred half-moves without a capture or pawn move: either player
+    /// may claim the draw, but the game goes on until they do.
+    pub fn can_claim_fifty_move(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// A hundred and fifty half-moves: drawn whether or not anyone claims.
+    pub fn is_seventy_five_move_draw(&self) -> bool {
+        self.halfmove_clock >= 150
     }
 
     pub fn is_draw(&self) -> bool {
-        self.is_repetition() || 
-        self.halfmove_clock >= 100 || 
+        self.is_threefold_repetition() || 
+        self.is_seventy_five_move_draw() || 
         self.is_insufficient_material()
     }
 
     pub fn is_game_over(&self) -> bool {
+        self.result() != GameResult::Ongoing
+    }
+
+    /// Mate and stalemate take precedence over the draw rules.
+    pub fn result(&self) -> GameResult {
         use crate::movegen::MoveGenerator;
-        let moves = MoveGenerator::generate_legal_moves(self);
-        moves.is_empty() || self.is_draw()
+
+        if MoveGenerator::generate_legal_moves(self).is_empty() {
+            if self.is_in_check(self.side_to_move) {
+                GameResult::Checkmate(self.side_to_move.flip())
+            } else {
+                GameResult::Stalemate
+            }
+        } else if self.is_seventy_five_move_draw() {
+            GameResult::DrawSeventyFiveMove
+        } else if self.is_threefold_repetition() {
+            GameResult::DrawRepetition
+        } else if self.is_insufficient_material() {
+            GameResult::DrawInsufficient
+        } else {
+            GameResult::Ongoing
+        }
     }
 
     fn is_insufficient_material(&self) -> bool {
@@ -328,11 +554,75 @@ impl BoardState {
         hash
     }
 
+    fn compute_pawn_hash(&self) -> u64 {
+        let mut hash = 0u64;
+
+        for color in 0..2 {
+            let mut pawns = self.pieces[color][Piece::Pawn as usize];
+            while pawns != 0 {
+                let (new_bb, sq) = pop_lsb(pawns);
+                pawns = new_bb;
+                hash ^= ZOBRIST.piece_keys[color][Piece::Pawn as usize][sq.unwrap() as usize];
+            }
+        }
+
+        hash
+    }
+
+    fn compute_pawn_king_hash(&self) -> u64 {
+        let mut hash = self.compute_pawn_hash();
+
+        for color in 0..2 {
+            if let Some(sq) = lsb(self.pieces[color][Piece::King as usize]) {
+                hash ^= ZOBRIST.piece_keys[color][Piece::King as usize][sq as usize];
+            }
+        }
+
+        hash
+    }
+
+    fn compute_material_pst(&self) -> (i32, i32) {
+        let mut totals = (0, 0);
+
+        for color in [Color::White, Color::Black] {
+            for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King] {
+                let mut pieces = self.pieces[color as usize][piece as usize];
+                while pieces != 0 {
+                    let (new_bb, sq) = pop_lsb(pieces);
+                    pieces = new_bb;
+                    let (mg, eg) = eval::psq(color, piece, sq.unwrap());
+                    totals.0 += mg;
+                    totals.1 += eg;
+                }
+            }
+        }
+
+        totals
+    }
+
+    fn add_psq(&mut self, color: Color, piece: Piece, sq: u8) {
+        let (mg, eg) = eval::psq(color, piece, sq);
+        self.mg_material_pst += mg;
+        self.eg_material_pst += eg;
+    }
+
+    fn remove_psq(&mut self, color: Color, piece: Piece, sq: u8) {
+        let (mg, eg) = eval::psq(color, piece, sq);
+        self.mg_material_pst -= mg;
+        self.eg_material_pst -= eg;
+    }
+
     pub fn make_move(&mut self, mv: &Move) {
         let from = mv.from;
         let to = mv.to;
         let flags = mv.flags;
         let color = self.side_to_move;
+        debug_assert!(
+            self.piece_at(from).is_some_and(|(_, owner)| owner == color),
+            "make_move {}: no {:?} piece on the from-square",
+            mv.to_uci(),
+            color
+        );
         
         if let Some((piece, _)) = self.piece_at(from) {
             // Update halfmove clock
@@ -342,6 +632,7 @@ impl BoardState {
             } else {
                 self.halfmove_clock += 1;
             }
+            self.plies_since_null = self.plies_since_null.saturating_add(1);
 
             // Clear old EP from hash
             if let Some(ep_sq) = self.ep_square {
@@ -357,6 +648,11 @@ impl BoardState {
                     self.color_bb[captured_color as usize] = clear_bit(self.color_bb[captured_color as usize], to);
                     self.all_pieces = clear_bit(self.all_pieces, to);
                     self.hash ^= ZOBRIST.piece_keys[captured_color as usize][captured_piece as usize][to as usize];
+                    self.remove_psq(captured_color, captured_piece, to);
+                    if captured_piece == Piece::Pawn {
+                        self.pawn_hash ^= ZOBRIST.piece_keys[captured_color as usize][Piece::Pawn as usize][to as usize];
+                        self.pawn_king_hash ^= ZOBRIST.piece_keys[captured_color as usize][Piece::Pawn as usize][to as usize];
+                    }
                 }
             } else if flags == EP_CAPTURE {
                 let ep_captured_sq = if color == Color::White { to - 8 } else { to + 8 };
@@ -367,6 +663,20 @@ impl BoardState {
                 self.color_bb[captured_color as usize] = clear_bit(self.color_bb[captured_color as usize], ep_captured_sq);
                 self.all_pieces = clear_bit(self.all_pieces, ep_captured_sq);
                 self.hash ^= ZOBRIST.piece_keys[captured_color as usize][Piece::Pawn as usize][ep_captured_sq as usize];
+                self.remove_psq(captured_color, Piece::Pawn, ep_captured_sq);
+                self.pawn_hash ^= ZOBRIST.piece_keys[captured_color as usize][Piece::Pawn as usize][ep_captured_sq as usize];
+                self.pawn_king_hash ^= ZOBRIST.piece_keys[captured_color as usize][Piece::Pawn as usize][ep_captured_sq as usize];
+            }
+
+            // Castling lifts the rook before the king moves: in Chess960 the
+            // king can land on the rook's square or the rook on the king's
+            if mv.is_castle() {
+                let rook_from = self.castling_rooks[2 * color as usize + (flags == QUEEN_CASTLE) as usize];
+                self.pieces[color as usize][Piece::Rook as usize] = clear_bit(self.pieces[color as usize][Piece::Rook as usize], rook_from);
+                self.color_bb[color as usize] = clear_bit(self.color_bb[color as usize], rook_from);
+                self.all_pieces = clear_bit(self.all_pieces, rook_from);
+                self.hash ^= ZOBRIST.piece_keys[color as usize][Piece::Rook as usize][rook_from as usize];
+                self.remove_psq(color, Piece::Rook, rook_from);
             }
 
             // Move piece
@@ -374,6 +684,13 @@ impl BoardState {
             self.color_bb[color as usize] = clear_bit(self.color_bb[color as usize], from);
             self.all_pieces = clear_bit(self.all_pieces, from);
             self.hash ^= ZOBRIST.piece_keys[color as usize][piece as usize][from as usize];
+            self.remove_psq(color, piece, from);
+            if piece == Piece::Pawn {
+                self.pawn_hash ^= ZOBRIST.piece_keys[color as usize][Piece::Pawn as usize][from as usize];
+            }
+            if piece == Piece::Pawn || piece == Piece::King {
+                self.pawn_king_hash ^= ZOBRIST.piece_keys[color as usize][piece as usize][from as usize];
+            }
 
             // Handle promotions
             let final_piece = if let Some(promo_piece) = mv.promotion_piece() {
@@ -386,32 +703,22 @@ impl BoardState {
             self.color_bb[color as usize] = set_bit(self.color_bb[color as usize], to);
             self.all_pieces = set_bit(self.all_pieces, to);
             self.hash ^= ZOBRIST.piece_keys[color as usize][final_piece as usize][to as usize];
+            self.add_psq(color, final_piece, to);
+            if final_piece == Piece::Pawn {
+                self.pawn_hash ^= ZOBRIST.piece_keys[color as usize][Piece::Pawn as usize][to as usize];
+            }
+            if final_piece == Piece::Pawn || final_piece == Piece::King {
+                self.pawn_king_hash ^= ZOBRIST.piece_keys[color as usize][final_piece as usize][to as usize];
+            }
 
-            // Castling
-            if flags == KING_CASTLE {
-                let (rook_from, rook_to) = if color == Color::White { (7, 5) } else { (63, 61) };
-                
-                self.pieces[color as usize][Piece::Rook as usize] = clear_bit(self.pieces[color as usize][Piece::Rook as usize], rook_from);
+            // The castling rook ends beside the king, towards the centre
+            if mv.is_castle() {
+                let rook_to = if flags == KING_CASTLE { to - 1 } else { to + 1 };
                 self.pieces[color as usize][Piece::Rook as usize] = set_bit(self.pieces[color as usize][Piece::Rook as usize], rook_to);
-                self.color_bb[color as usize] = clear_bit(self.color_bb[color as usize], rook_from);
                 self.color_bb[color as usize] = set_bit(self.color_bb[color as usize], rook_to);
-                self.all_pieces = clear_bit(self.all_pieces, rook_from);
                 self.all_pieces = set_bit(self.all_pieces, rook_to);
-                
-                self.hash ^= ZOBRIST.piece_keys[color as usize][Piece::Rook as usize][rook_from as usize];
-                self.hash ^= ZOBRIST.piece_keys[color as usize][Piece::Rook as usize][rook_to as usize];
-            } else if flags == QUEEN_CASTLE {
-                let (rook_from, rook_to) = if color == Color::White { (0, 3) } else { (56, 59) };
-                
-                self.pieces[color as usize][Piece::Rook as usize] = clear_bit(self.pieces[color as usize][Piece::Rook as usize], rook_from);
-                self.pieces[color as usize][Piece::Rook as usize] = set_bit(self.pieces[color as usize][Piece::Rook as usize], rook_to);
-                self.color_bb[color as usize] = clear_bit(self.color_bb[color as usize], rook_from);
-                self.color_bb[color as usize] = set_bit(self.color_bb[color as usize], rook_to);
-                self.all_pieces = clear_bit(self.all_pieces, rook_from);
-                self.all_pieces = set_bit(self.all_pieces, rook_to);
-                
-                self.hash ^= ZOBRIST.piece_keys[color as usize][Piece::Rook as usize][rook_from as usize];
                 self.hash ^= ZOBRIST.piece_keys[color as usize][Piece::Rook as usize][rook_to as usize];
+                self.add_psq(color, Piece::Rook, rook_to);
             }
 
             // Double pawn push
@@ -433,11 +740,13 @@ impl BoardState {
                 }
             }
 
-            if piece == Piece::Rook || mv.is_capture() {
-                if from == 0 || to == 0 { self.castling_rights &= !2; }
-                if from == 7 || to == 7 { self.castling_rights &= !1; }
-                if from == 56 || to == 56 { self.castling_rights &= !8; }
-                if from == 63 || to == 63 { self.castling_rights &= !4; }
+            // Anything leaving or landing on a rook's home square ends that
+            // side's castling there: the rook moved, was taken (promotion
+            // captures included), or was never there
+            for (right, &rook) in self.castling_rooks.iter().enumerate() {
+                if from == rook || to == rook {
+                    self.castling_rights &= !(1 << right);
+                }
             }
 
             if old_castling != self.castling_rights {
@@ -455,10 +764,87 @@ impl BoardState {
             self.fullmove_number += 1;
         }
 
+        // Catches incremental key updates drifting from the position
+        debug_assert_eq!(self.hash, self.compute_hash(), "hash out of sync after {}", mv.to_uci());
+        debug_assert_eq!(self.pawn_hash, self.compute_pawn_hash(), "pawn hash out of sync after {}", mv.to_uci());
+        debug_assert_eq!(self.pawn_king_hash, self.compute_pawn_king_hash(), "pawn-king hash out of sync after {}", mv.to_uci());
+        debug_assert_eq!(
+            (self.mg_material_pst, self.eg_material_pst),
+            self.compute_material_pst(),
+            "material/PST out of sync after {}",
+            mv.to_uci()
+        );
+
         // Add to position history
         self.position_history.push_back(self.hash);
     }
 
+    /// Passes the turn for null-move pruning; the position history is left
+    /// as it was.
+    pub fn make_null_move(&mut self) -> NullUndo {
+        let undo = NullUndo {
+            ep_square: self.ep_square,
+            hash: self.hash,
+            halfmove_clock: self.halfmove_clock,
+            plies_since_null: self.plies_since_null,
+        };
+
+        if let Some(ep_sq) = self.ep_square.take() {
+            self.hash ^= ZOBRIST.ep_keys[(ep_sq % 8) as usize];
+        }
+        self.side_to_move = self.side_to_move.flip();
+        self.hash ^= ZOBRIST.side_key;
+
+        // Counts as a ply so the history stays one entry per search ply
+        self.halfmove_clock += 1;
+        self.plies_since_null = 0;
+        self.position_history.push_back(self.hash);
+
+        undo
+    }
+
+    /// Takes back the matching `make_null_move`.
+    pub fn unmake_null_move(&mut self, undo: NullUndo) {
+        self.position_history.pop_back();
+        self.side_to_move = self.side_to_move.flip();
+        self.ep_square = undo.ep_square;
+        self.hash = undo.hash;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.plies_since_null = undo.plies_since_null;
+    }
+
+    /// `make_move` for moves from outside the engine: refuses, instead of
+    /// corrupting the board, a move whose from-square isn't the mover's or
+    /// that isn't legal here.
+    pub fn try_make_move(&mut self, mv: &Move) -> Result<(), String> {
+        use crate::movegen::MoveGenerator;
+
+        match self.piece_at(mv.from) {
+            None => return Err(format!("{}: no piece on the from-square", mv.to_uci())),
+            Some((_, owner)) if owner != self.side_to_move => {
+                return Err(format!("{}: the piece on the from-square isn't the side to move's", mv.to_uci()));
+            }
+            _ => {}
+        }
+        if !MoveGenerator::generate_legal_moves(self).contains(mv) {
+            return Err(format!("{}: illegal move", mv.to_uci()));
+        }
+
+        self.make_move(mv);
+        Ok(())
+    }
+
+    /// The destination UCI writes for `mv`: with Chess960 notation,
+    /// castling names the rook's square instead of the king's.
+    pub fn uci_to_square(&self, mv: &Move) -> u8 {
+        if self.chess960 && mv.is_castle() {
+            let color = if mv.from < 8 { Color::White } else { Color::Black };
+            self.castling_rooks[2 * color as usize + (mv.flags == QUEEN_CASTLE) as usize]
+        } else {
+            mv.to
+        }
+    }
+
     pub fn make_move_uci(&mut self, uci: &str) -> Result<bool, String> {
         use crate::movegen::MoveGenerator;
         
@@ -472,7 +858,7 @@ impl BoardState {
         let to = parse_square(&uci[2..4])?;
 
         for mv in legal_moves {
-            if mv.from == from && mv.to == to {
+            if mv.from == from && self.uci_to_square(&mv) == to {
                 if uci.len() == 5 {
                     let promo_char = uci.chars().nth(4).unwrap();
                     if let Some(promo_piece) = mv.promotion_piece() {
@@ -504,8 +890,8 @@ pub fn parse_square(s: &str) -> Result<u8, String> {
     if s.len() != 2 {
         return Err("Invalid square".to_string());
     }
-    let file = s.chars().nth(0).unwrap() as u8 - b'a';
-    let rank = s.chars().nth(1).unwrap() as u8 - b'1';
+    let file = (s.chars().nth(0).unwrap() as u8).wrapping_sub(b'a');
+    let rank = (s.chars().nth(1).unwrap() as u8).wrapping_sub(b'1');
     if file > 7 || rank > 7 {
         return Err("Invalid square".to_string());
     }
@@ -516,4 +902,365 @@ pub fn square_name(sq: u8) -> String {
     let file = (b'a' + (sq % 8)) as char;
     let rank = (b'1' + (sq / 8)) as char;
     format!("{}{}", file, rank)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    fn names(mut bb: Bitboard) -> Vec<String> {
+        let mut squares = Vec::new();
+        while bb != 0 {
+            let (new_bb, sq) = pop_lsb(bb);
+            bb = new_bb;
+            squares.push(square_name(sq.unwrap()));
+        }
+        squares
+    }
+
+    #[test]
+    fn test_from_fen_needs_one_king_each() {
+        assert!(BoardState::from_fen("8/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+        assert!(BoardState::from_fen("4k3/8/8/8/8/8/8/8 b - - 0 1").is_err());
+        assert!(BoardState::from_fen("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").is_err());
+        assert!(BoardState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn test_startpos_legal_moves() {
+        assert_eq!(MoveGenerator::generate_legal_moves(&BoardState::default()).len(), 20);
+    }
+
+    #[test]
+    fn test_result_checkmate() {
+        let board = BoardState::from_fen("3R2k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(board.result(), GameResult::Checkmate(Color::White));
+        assert!(board.is_game_over());
+    }
+
+    #[test]
+    fn test_result_stalemate() {
+        let board = BoardState::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.result(), GameResult::Stalemate);
+    }
+
+    #[test]
+    fn test_result_draws() {
+        let bare_kings = BoardState::from_fen("8/8/4k3/8/8/4K3/8/8 w - - 0 1").unwrap();
+        assert_eq!(bare_kings.result(), GameResult::DrawInsufficient);
+
+        let seventy_five = BoardState::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 150 100").unwrap();
+        assert_eq!(seventy_five.result(), GameResult::DrawSeventyFiveMove);
+
+        let mut board = BoardState::default();
+        assert_eq!(board.result(), GameResult::Ongoing);
+        for _ in 0..2 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                assert!(board.make_move_uci(uci).unwrap());
+            }
+        }
+        assert_eq!(board.result(), GameResult::DrawRepetition);
+    }
+
+    #[test]
+    fn test_fifty_and_seventy_five_move_rules() {
+        let at = |clock: u16| BoardState::from_fen(&format!("4k3/8/8/8/8/8/4P3/4K3 w - - {} 100", clock)).unwrap();
+
+        assert!(!at(99).can_claim_fifty_move());
+        assert_eq!(at(99).result(), GameResult::Ongoing);
+
+        // Claimable, but not over
+        assert!(at(100).can_claim_fifty_move());
+        assert!(!at(100).is_seventy_five_move_draw());
+        assert_eq!(at(100).result(), GameResult::Ongoing);
+
+        assert!(at(149).can_claim_fifty_move());
+        assert!(!at(149).is_seventy_five_move_draw());
+
+        assert!(at(150).is_seventy_five_move_draw());
+        assert_eq!(at(150).result(), GameResult::DrawSeventyFiveMove);
+    }
+
+    #[test]
+    fn test_repetition_twofold_then_threefold() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut board = BoardState::default();
+        assert_eq!(board.repetition_count(), 0);
+
+        for uci in shuffle {
+            assert!(!board.is_repetition());
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(board.repetition_count(), 1);
+        assert!(board.is_repetition());
+        assert!(!board.is_threefold_repetition());
+
+        for uci in shuffle {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(board.repetition_count(), 2);
+        assert!(board.is_threefold_repetition());
+
+        // A pawn move starts a new count
+        for uci in ["e2e3", "e7e6"] {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        for uci in shuffle {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(board.repetition_count(), 1);
+    }
+
+    #[test]
+    fn test_repetition_draw_in_tree_vs_game() {
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut board = BoardState::default();
+        for uci in shuffle {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+
+        // Second occurrence: a draw if the first came after the root...
+        assert!(board.is_repetition_draw(5));
+        // ...but not if it was already on the board at the root
+        assert!(!board.is_repetition_draw(4));
+        assert!(!board.is_repetition_draw(0));
+
+        for uci in shuffle {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        // Third occurrence is a draw however far back the others were
+        assert!(board.is_repetition_draw(0));
+    }
+
+    #[test]
+    fn test_promotion_capture_on_rook_square_clears_castling() {
+        let mut board = BoardState::from_fen("r3k2r/6P1/8/8/8/8/8/4K3 w kq - 0 1").unwrap();
+        assert!(board.make_move_uci("g7h8q").unwrap());
+        assert_eq!(board.castling_rights, 8);
+        assert_eq!(board.hash, board.compute_hash());
+
+        // An underpromoted rook on a home square gives white no rights
+        let mut board = BoardState::from_fen("r3k2r/1P6/8/8/8/8/8/4K3 w kq - 0 1").unwrap();
+        assert!(board.make_move_uci("b7a8r").unwrap());
+        assert_eq!(board.castling_rights, 4);
+        assert_eq!(board.to_fen().split(' ').nth(2), Some("k"));
+
+        // A right left in the FEN with no rook behind it goes once
+        // something else lands on the corner
+        let mut board = BoardState::from_fen("4k3/8/8/8/8/6N1/8/4K3 w K - 0 1").unwrap();
+        assert!(board.make_move_uci("g3h1").unwrap());
+        assert_eq!(board.castling_rights, 0);
+        assert_eq!(board.hash, board.compute_hash());
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // Shredder-FEN rights: the king on c1 castles with the b1 or h1 rook
+        let board = BoardState::from_fen("4k3/8/8/8/8/8/8/rRK4R w BH - 0 1").unwrap();
+        assert!(board.chess960);
+        assert_eq!(board.castling_rooks[..2], [7, 1]);
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/rRK4R w KB - 0 1");
+
+        // Queenside the king stays put and the b1 rook that blocks a1 goes,
+        // leaving the king in check
+        let castles: Vec<String> = MoveGenerator::generate_legal_moves(&board)
+            .iter()
+            .filter(|mv| mv.is_castle())
+            .map(|mv| mv.to_uci_for(&board))
+            .collect();
+        assert_eq!(castles, ["c1h1"]);
+
+        // Written king takes rook; the king lands on g1, the rook on f1
+        let mut castled = board.clone();
+        assert!(castled.make_move_uci("c1h1").unwrap());
+        assert_eq!(castled.to_fen(), "4k3/8/8/8/8/8/8/rR3RK1 b - - 1 1");
+        assert_eq!(castled.hash, castled.compute_hash());
+
+        // The king's destination doesn't name castling in this notation
+        assert!(board.clone().make_move_uci("c1g1").is_err());
+
+        // The king can land on its own rook's square
+        let mut board = BoardState::from_fen("4k3/8/8/8/8/8/8/4KR2 w F - 0 1").unwrap();
+        assert!(board.make_move_uci("e1f1").unwrap());
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_standard_castling_notation() {
+        let mut board = BoardState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(!board.chess960);
+        let castle = MoveGenerator::generate_legal_moves(&board).into_iter().find(|mv| mv.is_castle()).unwrap();
+        assert_eq!(castle.to_uci_for(&board), "e1g1");
+
+        // Chess960 notation for the same move, as a Chess960 GUI sends it
+        board.chess960 = true;
+        assert_eq!(castle.to_uci_for(&board), "e1h1");
+        assert!(board.make_move_uci("e1h1").unwrap());
+        assert_eq!(board.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+    }
+
+    #[test]
+    fn test_try_make_move_rejects_bad_moves() {
+        use crate::movegen::QUIET_MOVE;
+
+        let mut board = BoardState::default();
+        // e4 is empty, e7 holds a black pawn, e2-e5 is no pawn move
+        for (from, to) in [(28, 36), (52, 44), (12, 36)] {
+            assert!(board.try_make_move(&Move::new(from, to, QUIET_MOVE)).is_err());
+        }
+        assert_eq!(board.to_fen(), BoardState::default().to_fen());
+
+        assert_eq!(board.try_make_move(&Move::new(12, 28, DOUBLE_PAWN_PUSH)), Ok(()));
+        assert_eq!(board.side_to_move, Color::Black);
+    }
+
+    #[test]
+    fn test_zobrist_key_is_stable() {
+        let board = BoardState::default();
+        // Pinned down so an accidental change of seed or key layout shows up
+        assert_eq!(board.zobrist_key(), 0x6299d710a4b45a29);
+        assert_eq!(board.polyglot_key(), 0x463b96181691fc9c);
+        assert_ne!(board.zobrist_key(), board.polyglot_key());
+
+        let mut after = board.clone();
+        assert!(after.make_move_uci("e2e4").unwrap());
+        assert_ne!(after.zobrist_key(), board.zobrist_key());
+        assert_eq!(after.zobrist_key(), after.compute_hash());
+
+        // Same position by another move order, same key
+        let mut a = BoardState::default();
+        let mut b = BoardState::default();
+        for uci in ["g1f3", "b8c6", "b1c3"] {
+            assert!(a.make_move_uci(uci).unwrap());
+        }
+        for uci in ["b1c3", "b8c6", "g1f3"] {
+            assert!(b.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(a.zobrist_key(), b.zobrist_key());
+    }
+
+    #[test]
+    fn test_null_move_keeps_history_aligned() {
+        let mut board = BoardState::default();
+        let history = board.position_history.len();
+
+        let undo = board.make_null_move();
+        assert_eq!(board.position_history.len(), history + 1);
+        assert_eq!(board.position_history.back(), Some(&board.hash));
+        assert_eq!(board.halfmove_clock, 1);
+
+        board.unmake_null_move(undo);
+        assert_eq!(board.position_history.len(), history);
+        assert_eq!(board.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn test_repetition_not_across_null_move() {
+        let mut board = BoardState::default();
+        assert!(board.make_move_uci("g1f3").unwrap());
+
+        // Null, knight out, null, knight back: the same position and side
+        // to move as two plies earlier, but only by passing the turn
+        board.make_null_move();
+        assert!(board.make_move_uci("f3g5").unwrap());
+        board.make_null_move();
+        assert!(board.make_move_uci("g5f3").unwrap());
+        assert!(!board.is_repetition_draw(5));
+        assert_eq!(board.repetition_count(), 0);
+
+        // Real moves after the last null move still repeat as usual
+        for uci in ["g8f6", "f3g1", "f6g8", "g1f3"] {
+            assert!(board.make_move_uci(uci).unwrap());
+        }
+        assert!(board.is_repetition_draw(5));
+    }
+
+    #[test]
+    fn test_random_games_keep_hashes_in_sync() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(2320);
+        let mut board = BoardState::default();
+
+        for _ in 0..1000 {
+            let moves = MoveGenerator::generate_legal_moves(&board);
+            if moves.is_empty() || board.halfmove_clock >= 100 {
+                board = BoardState::default();
+                continue;
+            }
+
+            board.make_move(&moves[rng.gen_range(0..moves.len())]);
+            assert_eq!(board.hash, board.compute_hash(), "{}", board.to_fen());
+            assert_eq!(board.pawn_hash, board.compute_pawn_hash(), "{}", board.to_fen());
+            assert_eq!(board.pawn_king_hash, board.compute_pawn_king_hash(), "{}", board.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_pawn_king_hash() {
+        let mut board = BoardState::default();
+        let start = board.pawn_king_hash;
+
+        // Knights don't touch it, pawns and kings do
+        assert!(board.make_move_uci("g1f3").unwrap());
+        assert_eq!(board.pawn_king_hash, start);
+        assert!(board.make_move_uci("e7e5").unwrap());
+        assert_ne!(board.pawn_king_hash, start);
+
+        let after_pawn = board.pawn_king_hash;
+        assert!(board.make_move_uci("b1c3").unwrap());
+        assert!(board.make_move_uci("e8e7").unwrap());
+        assert_ne!(board.pawn_king_hash, after_pawn);
+        assert_eq!(board.pawn_king_hash, BoardState::from_fen(&board.to_fen()).unwrap().pawn_king_hash);
+    }
+
+    #[test]
+    fn test_null_move_make_unmake() {
+        // An ep square so the null move has a key to clear
+        let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3";
+        let mut board = BoardState::from_fen(fen).unwrap();
+        let hash = board.hash;
+
+        let undo = board.make_null_move();
+        assert_eq!(board.side_to_move, Color::Black);
+        assert_eq!(board.ep_square, None);
+        assert_eq!(board.hash, board.compute_hash());
+
+        board.unmake_null_move(undo);
+        assert_eq!(board.hash, hash);
+        assert_eq!(board.to_fen(), fen);
+
+        // Without an ep square two null moves cancel out
+        let mut board = BoardState::default();
+        let first = board.make_null_move();
+        let second = board.make_null_move();
+        assert_eq!(board.hash, BoardState::default().hash);
+        assert_eq!(board.side_to_move, Color::White);
+        board.unmake_null_move(second);
+        board.unmake_null_move(first);
+        assert_eq!(board.to_fen(), BoardState::default().to_fen());
+    }
+
+    #[test]
+    fn test_attackers() {
+        let board = BoardState::from_fen("4k3/8/5n2/3p4/4P3/2N5/8/4K2Q w - - 0 1").unwrap();
+        let e4 = parse_square("e4").unwrap();
+        assert_eq!(names(board.attackers(e4, Color::White)), ["h1", "c3"]);
+        assert_eq!(names(board.attackers(e4, Color::Black)), ["d5", "f6"]);
+
+        // The e4 pawn blocks the queen from d5
+        let d5 = parse_square("d5").unwrap();
+        assert_eq!(names(board.attackers(d5, Color::White)), ["c3", "e4"]);
+    }
+
+    #[test]
+    fn test_piece_char() {
+        let board = BoardState::default();
+        assert_eq!(board.piece_char(parse_square("e1").unwrap()), Some('K'));
+        assert_eq!(board.piece_char(parse_square("d8").unwrap()), Some('q'));
+        assert_eq!(board.piece_char(parse_square("e4").unwrap()), None);
+        assert!(parse_square("E4").is_err());
+    }
+}
@@ -1,5 +1,9 @@
 use crate::board::{BoardState, Piece, Color, PIECE_VALUES};
 use crate::bitboard::*;
+use crate::bitbase;
+use crate::movegen::{Move, MoveGenerator, EP_CAPTURE};
+use std::cell::Cell;
+use std::fmt;
 
 // ══════════════════════════════════════════════════════════════════════════════
 // PROFESSIONAL EVALUATION WEIGHTS (Tournament Tuned)
@@ -13,7 +17,6 @@ const ROOK_VALUE: i32 = 500;
 const QUEEN_VALUE: i32 = 900;
 
 // Tactical Safety Weights (CRITICAL FOR PREVENTING BLUNDERS)
-const HANGING_PIECE_PENALTY: i32 = 150;  // Severe penalty for undefended pieces
 const ABSOLUTE_PIN_PENALTY: i32 = 50;    // Severe penalty for absolute pins
 const RELATIVE_PIN_PENALTY: i32 = 15;    // Lighter penalty for relative pins
 const FORK_BONUS: i32 = 50;              // Bonus for creating forks
@@ -21,6 +24,7 @@ const SKEWER_BONUS: i32 = 40;            // Bonus for skewers
 const DISCOVERED_ATTACK_BONUS: i32 = 35; // Bonus for discovered attacks
 const TRAPPED_PIECE: i32 = 120;          // Heavy penalty for trapped pieces
 const THREAT_BONUS: i32 = 30;            // Bonus for creating threats
+const TACTICAL_SAFETY_WEIGHT: i32 = 75;  // Percent of the block kept; search sees most tactics
 
 // Positional Weights
 const BISHOP_PAIR_BONUS: i32 = 50;
@@ -28,10 +32,12 @@ const ROOK_OPEN_FILE: i32 = 25;
 const ROOK_SEMI_OPEN: i32 = 15;
 const ROOK_SEVENTH_RANK: i32 = 20;
 const CONNECTED_ROOKS: i32 = 15;
+const ROOK_BEHIND_PASSER: i32 = 20;
+const DOUBLED_ROOKS: i32 = 15;
 const KNIGHT_OUTPOST: i32 = 30;
-const BISHOP_LONG_DIAGONAL: i32 = 20;
 const BAD_BISHOP_PENALTY: i32 = 20;
 const FIANCHETTO_BONUS: i32 = 15;
+const TRAPPED_BISHOP: i32 = 250;        // Bishop about to be lost for a pawn or two
 
 // Pawn Structure
 const DOUBLED_PAWN: i32 = 15;
@@ -40,19 +46,24 @@ const BACKWARD_PAWN: i32 = 12;
 const PASSED_PAWN_BONUS: [i32; 8] = [0, 10, 20, 40, 70, 120, 200, 0];
 const PROTECTED_PASSED_PAWN: [i32; 8] = [0, 5, 10, 20, 35, 60, 100, 0];
 const CANDIDATE_PASSED: [i32; 8] = [0, 5, 8, 15, 25, 40, 70, 0];
+const CONNECTED_PASSER: [i32; 8] = [0, 5, 10, 15, 25, 45, 75, 0]; // Per passer with a neighbouring passer
+const OUTSIDE_PASSER: i32 = 8;           // Per file beyond two from the enemy king, in the endgame
 const PAWN_CHAIN_BONUS: i32 = 8;
 const PAWN_STORM_BONUS: i32 = 12;
 
 // King Safety
 const PAWN_SHIELD_BONUS: i32 = 15;
 const OPEN_FILE_NEAR_KING: i32 = 20;
-const KING_ZONE_ATTACK: i32 = 10;
 const CASTLING_RIGHTS_BONUS: i32 = 25;
-const KING_ATTACK_WEIGHT: [i32; 6] = [0, 0, 50, 75, 88, 94]; // By attacker count
+const KING_TROPISM: i32 = 3;             // Per piece, per step closer than 7 to the enemy king
+const KING_ATTACK_UNITS: [i32; 7] = [0, 0, 2, 2, 3, 5, 0]; // Per attacked zone square, by piece
+const KING_ATTACK_WEIGHT: [i32; 32] = [                    // By attack units, grows faster than linear
+    0, 0, 1, 2, 4, 6, 8, 11, 14, 18, 22, 26, 31, 36, 42, 48,
+    54, 61, 68, 76, 84, 92, 101, 110, 120, 130, 140, 151, 162, 174, 186, 198,
+];
 
 // Space and Mobility
 const SPACE_BONUS: i32 = 2;
-const SAFE_MOBILITY_BONUS: i32 = 4;
 const KNIGHT_MOBILITY: i32 = 4;
 const BISHOP_MOBILITY: i32 = 3;
 const ROOK_MOBILITY: i32 = 2;
@@ -61,6 +72,30 @@ const QUEEN_MOBILITY: i32 = 1;
 // Tempo
 const TEMPO_BONUS: i32 = 15;
 
+// Material Imbalance
+const IMBALANCE_BISHOP_PAIR: i32 = 25;      // Pair against a side without one
+const IMBALANCE_KNIGHT_PAWN: i32 = 6;       // Per knight, per own pawn above five
+const IMBALANCE_ROOK_PAWN: i32 = 12;        // Per rook, per own pawn below five
+const IMBALANCE_ROOK_PAIR: i32 = 16;        // Redundant second rook
+const IMBALANCE_QUEEN_ROOK: i32 = 8;        // Queen and rook overlap
+const IMBALANCE_ROOKS_VS_QUEEN: i32 = 25;   // Two rooks against a lone queen
+const IMBALANCE_EXCHANGE: i32 = 20;         // Rook for a minor piece
+
+// Search margins
+const PROBCUT_MARGIN: i32 = 200;            // Over beta for a capture to prune on
+
+// Endgame scaling, in 64ths of the final score
+const SCALE_NORMAL: i32 = 64;
+const SCALE_PURE_OCB: i32 = 16;
+const SCALE_OCB_WITH_PIECES: i32 = 40;
+
+// Bitbase-won KPK endings, plus a bonus per rank so the pawn keeps advancing
+const KNOWN_WIN: i32 = 10000;
+
+// Basic mates: lone king toward the edge, attacking king toward it
+const PUSH_TO_EDGE: i32 = 20;
+const PUSH_CLOSE: i32 = 10;
+
 // Piece-Square Tables (Enhanced with better positional understanding)
 const PAWN_PST_MG: [i32; 64] = [
       0,   0,   0,   0,   0,   0,   0,   0,
@@ -194,6 +229,316 @@ const KING_PST_EG: [i32; 64] = [
     -60, -50, -40, -30, -30, -40, -50, -60
 ];
 
+/// Every evaluation weight, so they can be changed without recompiling.
+/// `Default` reproduces the built-in tables above.
+#[derive(Clone, Debug)]
+pub struct EvalParams {
+    // Material (adjusted for better endgame scaling)
+    pub pawn_value: i32,
+    pub knight_value: i32,
+    pub bishop_value: i32,
+    pub rook_value: i32,
+    pub queen_value: i32,
+
+    // Tactical Safety Weights (CRITICAL FOR PREVENTING BLUNDERS)
+    pub absolute_pin_penalty: i32,
+    pub relative_pin_penalty: i32,
+    pub fork_bonus: i32,
+    pub skewer_bonus: i32,
+    pub discovered_attack_bonus: i32,
+    pub trapped_piece: i32,
+    pub threat_bonus: i32,
+    pub tactical_safety_weight: i32,
+
+    // Positional Weights
+    pub bishop_pair_bonus: i32,
+    pub rook_open_file: i32,
+    pub rook_semi_open: i32,
+    pub rook_seventh_rank: i32,
+    pub connected_rooks: i32,
+    pub rook_behind_passer: i32,
+    pub doubled_rooks: i32,
+    pub knight_outpost: i32,
+    pub bad_bishop_penalty: i32,
+    pub fianchetto_bonus: i32,
+    pub trapped_bishop: i32,
+
+    // Pawn Structure
+    pub doubled_pawn: i32,
+    pub isolated_pawn: i32,
+    pub backward_pawn: i32,
+    pub passed_pawn_bonus: [i32; 8],
+    pub protected_passed_pawn: [i32; 8],
+    pub candidate_passed: [i32; 8],
+    pub connected_passer: [i32; 8],
+    pub outside_passer: i32,
+    pub pawn_chain_bonus: i32,
+    pub pawn_storm_bonus: i32,
+
+    // King Safety
+    pub pawn_shield_bonus: i32,
+    pub open_file_near_king: i32,
+    pub castling_rights_bonus: i32,
+    pub king_tropism: i32,
+    pub king_attack_weight: [i32; 32],
+
+    // Space and Mobility
+    pub space_bonus: i32,
+    pub knight_mobility: i32,
+    pub bishop_mobility: i32,
+    pub rook_mobility: i32,
+    pub queen_mobility: i32,
+
+    // Tempo
+    pub tempo_bonus: i32,
+
+    // Material Imbalance
+    pub imbalance_bishop_pair: i32,
+    pub imbalance_knight_pawn: i32,
+    pub imbalance_rook_pawn: i32,
+    pub imbalance_rook_pair: i32,
+    pub imbalance_queen_rook: i32,
+    pub imbalance_rooks_vs_queen: i32,
+    pub imbalance_exchange: i32,
+
+    // Search Margins (not touched by the Texel tuner)
+    pub probcut_margin: i32,
+
+    // Piece-Square Tables (Enhanced with better positional understanding)
+    pub pawn_pst_mg: [i32; 64],
+    pub pawn_pst_eg: [i32; 64],
+    pub knight_pst_mg: [i32; 64],
+    pub knight_pst_eg: [i32; 64],
+    pub bishop_pst_mg: [i32; 64],
+    pub bishop_pst_eg: [i32; 64],
+    pub rook_pst_mg: [i32; 64],
+    pub rook_pst_eg: [i32; 64],
+    pub queen_pst_mg: [i32; 64],
+    pub queen_pst_eg: [i32; 64],
+    pub king_pst_mg: [i32; 64],
+    pub king_pst_eg: [i32; 64],
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            pawn_value: PAWN_VALUE,
+            knight_value: KNIGHT_VALUE,
+            bishop_value: BISHOP_VALUE,
+            rook_value: ROOK_VALUE,
+            queen_value: QUEEN_VALUE,
+            absolute_pin_penalty: ABSOLUTE_PIN_PENALTY,
+            relative_pin_penalty: RELATIVE_PIN_PENALTY,
+            fork_bonus: FORK_BONUS,
+            skewer_bonus: SKEWER_BONUS,
+            discovered_attack_bonus: DISCOVERED_ATTACK_BONUS,
+            trapped_piece: TRAPPED_PIECE,
+            threat_bonus: THREAT_BONUS,
+            tactical_safety_weight: TACTICAL_SAFETY_WEIGHT,
+            bishop_pair_bonus: BISHOP_PAIR_BONUS,
+            rook_open_file: ROOK_OPEN_FILE,
+            rook_semi_open: ROOK_SEMI_OPEN,
+            rook_seventh_rank: ROOK_SEVENTH_RANK,
+            connected_rooks: CONNECTED_ROOKS,
+            rook_behind_passer: ROOK_BEHIND_PASSER,
+            doubled_rooks: DOUBLED_ROOKS,
+            knight_outpost: KNIGHT_OUTPOST,
+            bad_bishop_penalty: BAD_BISHOP_PENALTY,
+            fianchetto_bonus: FIANCHETTO_BONUS,
+            trapped_bishop: TRAPPED_BISHOP,
+            doubled_pawn: DOUBLED_PAWN,
+            isolated_pawn: ISOLATED_PAWN,
+            backward_pawn: BACKWARD_PAWN,
+            passed_pawn_bonus: PASSED_PAWN_BONUS,
+            protected_passed_pawn: PROTECTED_PASSED_PAWN,
+            candidate_passed: CANDIDATE_PASSED,
+            connected_passer: CONNECTED_PASSER,
+            outside_passer: OUTSIDE_PASSER,
+            pawn_chain_bonus: PAWN_CHAIN_BONUS,
+            pawn_storm_bonus: PAWN_STORM_BONUS,
+            pawn_shield_bonus: PAWN_SHIELD_BONUS,
+            open_file_near_king: OPEN_FILE_NEAR_KING,
+            castling_rights_bonus: CASTLING_RIGHTS_BONUS,
+            king_tropism: KING_TROPISM,
+            king_attack_weight: KING_ATTACK_WEIGHT,
+            space_bonus: SPACE_BONUS,
+            knight_mobility: KNIGHT_MOBILITY,
+            bishop_mobility: BISHOP_MOBILITY,
+            rook_mobility: ROOK_MOBILITY,
+            queen_mobility: QUEEN_MOBILITY,
+            tempo_bonus: TEMPO_BONUS,
+            imbalance_bishop_pair: IMBALANCE_BISHOP_PAIR,
+            imbalance_knight_pawn: IMBALANCE_KNIGHT_PAWN,
+            imbalance_rook_pawn: IMBALANCE_ROOK_PAWN,
+            imbalance_rook_pair: IMBALANCE_ROOK_PAIR,
+            imbalance_queen_rook: IMBALANCE_QUEEN_ROOK,
+            imbalance_rooks_vs_queen: IMBALANCE_ROOKS_VS_QUEEN,
+            imbalance_exchange: IMBALANCE_EXCHANGE,
+            probcut_margin: PROBCUT_MARGIN,
+            pawn_pst_mg: PAWN_PST_MG,
+            pawn_pst_eg: PAWN_PST_EG,
+            knight_pst_mg: KNIGHT_PST_MG,
+            knight_pst_eg: KNIGHT_PST_EG,
+            bishop_pst_mg: BISHOP_PST_MG,
+            bishop_pst_eg: BISHOP_PST_EG,
+            rook_pst_mg: ROOK_PST_MG,
+            rook_pst_eg: ROOK_PST_EG,
+            queen_pst_mg: QUEEN_PST_MG,
+            queen_pst_eg: QUEEN_PST_EG,
+            king_pst_mg: KING_PST_MG,
+            king_pst_eg: KING_PST_EG,
+        }
+    }
+}
+
+impl EvalParams {
+    fn piece_value(&self, piece_type: usize) -> i32 {
+        match piece_type {
+            1 => self.pawn_value,
+            2 => self.knight_value,
+            3 => self.bishop_value,
+            4 => self.rook_value,
+            5 => self.queen_value,
+            _ => PIECE_VALUES[piece_type],
+        }
+    }
+
+    // Middlegame and endgame PST bonus, `sq` already flipped to white's side
+    fn pst(&self, piece_type: usize, sq: usize) -> (i32, i32) {
+        match piece_type {
+            1 => (self.pawn_pst_mg[sq], self.pawn_pst_eg[sq]),
+            2 => (self.knight_pst_mg[sq], self.knight_pst_eg[sq]),
+            3 => (self.bishop_pst_mg[sq], self.bishop_pst_eg[sq]),
+            4 => (self.rook_pst_mg[sq], self.rook_pst_eg[sq]),
+            5 => (self.queen_pst_mg[sq], self.queen_pst_eg[sq]),
+            6 => (self.king_pst_mg[sq], self.king_pst_eg[sq]),
+            _ => (0, 0),
+        }
+    }
+
+    /// Sets a single weight by its field name, case-insensitively, as in
+    /// `rook_open_file`. Tables can't be set this way. False if there's no
+    /// such weight.
+    pub fn set(&mut self, name: &str, value: i32) -> bool {
+        let field = match name.to_lowercase().as_str() {
+            "pawn_value" => &mut self.pawn_value,
+            "knight_value" => &mut self.knight_value,
+            "bishop_value" => &mut self.bishop_value,
+            "rook_value" => &mut self.rook_value,
+            "queen_value" => &mut self.queen_value,
+            "absolute_pin_penalty" => &mut self.absolute_pin_penalty,
+            "relative_pin_penalty" => &mut self.relative_pin_penalty,
+            "fork_bonus" => &mut self.fork_bonus,
+            "skewer_bonus" => &mut self.skewer_bonus,
+            "discovered_attack_bonus" => &mut self.discovered_attack_bonus,
+            "trapped_piece" => &mut self.trapped_piece,
+            "threat_bonus" => &mut self.threat_bonus,
+            "tactical_safety_weight" => &mut self.tactical_safety_weight,
+            "bishop_pair_bonus" => &mut self.bishop_pair_bonus,
+            "rook_open_file" => &mut self.rook_open_file,
+            "rook_semi_open" => &mut self.rook_semi_open,
+            "rook_seventh_rank" => &mut self.rook_seventh_rank,
+            "connected_rooks" => &mut self.connected_rooks,
+            "rook_behind_passer" => &mut self.rook_behind_passer,
+            "doubled_rooks" => &mut self.doubled_rooks,
+            "knight_outpost" => &mut self.knight_outpost,
+            "bad_bishop_penalty" => &mut self.bad_bishop_penalty,
+            "fianchetto_bonus" => &mut self.fianchetto_bonus,
+            "trapped_bishop" => &mut self.trapped_bishop,
+            "doubled_pawn" => &mut self.doubled_pawn,
+            "isolated_pawn" => &mut self.isolated_pawn,
+            "backward_pawn" => &mut self.backward_pawn,
+            "pawn_chain_bonus" => &mut self.pawn_chain_bonus,
+            "outside_passer" => &mut self.outside_passer,
+            "pawn_storm_bonus" => &mut self.pawn_storm_bonus,
+            "pawn_shield_bonus" => &mut self.pawn_shield_bonus,
+            "open_file_near_king" => &mut self.open_file_near_king,
+            "castling_rights_bonus" => &mut self.castling_rights_bonus,
+            "king_tropism" => &mut self.king_tropism,
+            "space_bonus" => &mut self.space_bonus,
+            "knight_mobility" => &mut self.knight_mobility,
+            "bishop_mobility" => &mut self.bishop_mobility,
+            "rook_mobility" => &mut self.rook_mobility,
+            "queen_mobility" => &mut self.queen_mobility,
+            "tempo_bonus" => &mut self.tempo_bonus,
+            "imbalance_bishop_pair" => &mut self.imbalance_bishop_pair,
+            "imbalance_knight_pawn" => &mut self.imbalance_knight_pawn,
+            "imbalance_rook_pawn" => &mut self.imbalance_rook_pawn,
+            "imbalance_rook_pair" => &mut self.imbalance_rook_pair,
+            "imbalance_queen_rook" => &mut self.imbalance_queen_rook,
+            "imbalance_rooks_vs_queen" => &mut self.imbalance_rooks_vs_queen,
+            "imbalance_exchange" => &mut self.imbalance_exchange,
+            "probcut_margin" => &mut self.probcut_margin,
+            _ => return false,
+        };
+        *field = value;
+        true
+    }
+
+    /// Whether piece values and PSTs are the built-in ones, which is what
+    /// the material/PST totals kept on `BoardState` are computed from.
+    pub fn has_builtin_psq(&self) -> bool {
+        let builtin = EvalParams::default();
+        (1..=6).all(|piece_type| {
+            self.piece_value(piece_type) == builtin.piece_value(piece_type)
+                && (0..64).all(|sq| self.pst(piece_type, sq) == builtin.pst(piece_type, sq))
+        })
+    }
+}
+
+lazy_static::lazy_static! {
+    // Material plus middlegame/endgame PST from the built-in weights, for
+    // every color, piece and square; negative for black
+    static ref PSQ: [[[(i32, i32); 64]; 7]; 2] = {
+        let params = EvalParams::default();
+        let mut table = [[[(0, 0); 64]; 7]; 2];
+        for (color, sign) in [(0, 1), (1, -1)] {
+            for (piece_type, squares) in table[color].iter_mut().enumerate().skip(1) {
+                let value = params.piece_value(piece_type);
+                for (sq, entry) in squares.iter_mut().enumerate() {
+                    let pst_sq = if color == 0 { sq } else { sq ^ 56 };
+                    let (mg, eg) = params.pst(piece_type, pst_sq);
+                    *entry = (sign * (value + mg), sign * (value + eg));
+                }
+            }
+        }
+        table
+    };
+
+    // The king's square and its neighbours
+    static ref KING_ZONE: [Bitboard; 64] = {
+        let mut zones = [0; 64];
+        for (sq, zone) in zones.iter_mut().enumerate() {
+            *zone = ATTACK_TABLES.king_attacks[sq] | (1u64 << sq);
+        }
+        zones
+    };
+
+    // Shield squares by [color][king square]: the king's file and its
+    // neighbours, one and two ranks towards the enemy
+    static ref PAWN_SHIELD: [[Bitboard; 64]; 2] = {
+        let mut shields = [[0; 64]; 2];
+        for (color, forward) in [(0, 1i8), (1, -1i8)] {
+            for sq in 0..64u8 {
+                let (file, rank) = ((sq % 8) as i8, (sq / 8) as i8);
+                for f in (file - 1).max(0)..=(file + 1).min(7) {
+                    for r in [rank + forward, rank + 2 * forward] {
+                        if (0..8).contains(&r) {
+                            shields[color][sq as usize] |= 1u64 << (r * 8 + f);
+                        }
+                    }
+                }
+            }
+        }
+        shields
+    };
+}
+
+/// What a piece on `sq` adds to `BoardState`'s running material/PST totals.
+pub fn psq(color: Color, piece: Piece, sq: u8) -> (i32, i32) {
+    PSQ[color as usize][piece as usize][sq as usize]
+}
+
 // Move PinType outside of impl block
 #[derive(PartialEq)]
 enum PinType {
@@ -202,33 +547,165 @@ enum PinType {
     Relative,  // Pinned to valuable piece
 }
 
-pub struct Evaluator;
+// ══════════════════════════════════════════════════════════════════════════════
+// PAWN HASH
+// ══════════════════════════════════════════════════════════════════════════════
+
+const PAWN_TABLE_SIZE: usize = 16384;
+
+#[derive(Clone, Copy, Default)]
+struct PawnEntry {
+    key: u64,
+    score: i32,
+    passed: [Bitboard; 2],
+}
+
+/// Pawn-only evaluation terms keyed on `BoardState::pawn_hash`. Meant to be
+/// owned by a single search thread; entries are only valid for the
+/// `EvalParams` they were computed with.
+pub struct PawnTable {
+    entries: Vec<Cell<PawnEntry>>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        PawnTable {
+            entries: vec![Cell::new(PawnEntry::default()); PAWN_TABLE_SIZE],
+        }
+    }
+
+    // A pawnless board hashes to 0 and matches the zeroed empty entries,
+    // which hold the right answer for it anyway
+    fn probe(&self, key: u64) -> Option<PawnEntry> {
+        let entry = self.entries[key as usize % PAWN_TABLE_SIZE].get();
+        if entry.key == key {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    fn store(&self, entry: PawnEntry) {
+        self.entries[entry.key as usize % PAWN_TABLE_SIZE].set(entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(Cell::new(PawnEntry::default()));
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// EVAL TRACE
+// ══════════════════════════════════════════════════════════════════════════════
+
+/// One labeled evaluation term, from white's point of view. The per-side
+/// columns are `None` for terms only computed as a difference; phase
+/// scaling is applied to each side separately, so they can be a rounding
+/// step away from `total`.
+#[derive(Clone, Debug)]
+pub struct EvalTerm {
+    pub name: &'static str,
+    pub white: Option<i32>,
+    pub black: Option<i32>,
+    pub total: i32,
+}
+
+/// Breakdown of `Evaluator::evaluate`. Term totals add up to the white
+/// point-of-view score; `score` is that score from the side to move, exactly
+/// as `evaluate` returns it.
+#[derive(Clone, Debug)]
+pub struct EvalTrace {
+    pub terms: Vec<EvalTerm>,
+    pub score: i32,
+}
+
+impl EvalTrace {
+    fn push(&mut self, name: &'static str, white: Option<i32>, black: Option<i32>, total: i32) {
+        self.terms.push(EvalTerm { name, white, black, total });
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let column = |value: Option<i32>| value.map_or("----".to_string(), |v| v.to_string());
+
+        writeln!(f, "{:<18}|{:>8} |{:>8} |{:>8}", "Term", "White", "Black", "Total")?;
+        writeln!(f, "{:-<18}+{:->9}+{:->9}+{:->9}", "", "", "", "")?;
+        for term in &self.terms {
+            writeln!(
+                f,
+                "{:<18}|{:>8} |{:>8} |{:>8}",
+                term.name,
+                column(term.white),
+                column(term.black),
+                term.total
+            )?;
+        }
+        writeln!(f, "{:-<18}+{:->9}+{:->9}+{:->9}", "", "", "", "")?;
+        let white_score: i32 = self.terms.iter().map(|term| term.total).sum();
+        writeln!(f, "{:<18}|{:>8} |{:>8} |{:>8}", "Total", "", "", white_score)?;
+        write!(f, "Side to move: {}", self.score)
+    }
+}
+
+pub struct Evaluator<'a> {
+    params: &'a EvalParams,
+    pawn_table: Option<&'a PawnTable>,
+    incremental_psq: bool,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(params: &'a EvalParams) -> Self {
+        Evaluator { params, pawn_table: None, incremental_psq: false }
+    }
+
+    pub fn with_pawn_table(mut self, pawn_table: &'a PawnTable) -> Self {
+        self.pawn_table = Some(pawn_table);
+        self
+    }
+
+    /// Take material and PST from the board's running totals instead of
+    /// summing every piece. Only right when `params.has_builtin_psq()`.
+    pub fn with_incremental_psq(mut self, incremental_psq: bool) -> Self {
+        self.incremental_psq = incremental_psq;
+        self
+    }
 
-impl Evaluator {
-    pub fn evaluate(board: &BoardState) -> i32 {
+    pub fn evaluate(&self, board: &BoardState) -> i32 {
         // Quick draw detection
         if board.halfmove_clock >= 100 {
             return 0;
         }
 
+        if let Some(score) = self.kpk_evaluation(board)
+            .or_else(|| self.basic_mate_evaluation(board))
+            .or_else(|| Self::known_draws(board))
+        {
+            return if board.side_to_move == Color::Black { -score } else { score };
+        }
+
         let phase = Self::game_phase(board);
         
         // Core evaluation components
-        let (mg_score, eg_score) = Self::material_and_pst(board);
+        let (mg_score, eg_score) = self.material_and_pst(board);
         let mut score = Self::tapered_eval(mg_score, eg_score, phase);
+        score += self.imbalance(board);
 
         // CRITICAL: Tactical safety (prevents blunders)
-        score += Self::tactical_safety(board, phase);
+        score += self.tactical_safety(board, phase);
         
         // Positional evaluation
-        score += Self::pawn_structure(board, phase);
-        score += Self::piece_mobility_safe(board, phase);
-        score += Self::king_safety_advanced(board, phase);
-        score += Self::space_evaluation(board, phase);
-        score += Self::rook_evaluation(board);
-        score += Self::bishop_evaluation(board);
-        score += Self::knight_evaluation(board);
-        score += Self::tempo_bonus(board);
+        score += self.pawn_structure(board, phase);
+        score += self.piece_mobility_safe(board, phase);
+        score += self.king_safety_advanced(board, phase);
+        score += self.king_tropism(board, phase);
+        score += self.space_evaluation(board, phase);
+        score += self.rook_evaluation(board);
+        score += self.bishop_evaluation(board);
+        score += self.knight_evaluation(board);
+        score += self.tempo_bonus(board);
+
+        score = score * self.endgame_scale(board, score, phase) / SCALE_NORMAL;
 
         // Return from side-to-move perspective
         if board.side_to_move == Color::Black {
@@ -238,75 +715,386 @@ impl Evaluator {
         }
     }
 
+    /// `evaluate`, term by term. Mirrors its structure, so keep the two in step.
+    pub fn evaluate_trace(&self, board: &BoardState) -> EvalTrace {
+        let mut trace = EvalTrace { terms: Vec::new(), score: 0 };
+
+        if board.halfmove_clock >= 100 {
+            trace.push("Fifty-move rule", None, None, 0);
+        } else if let Some(score) = self.kpk_evaluation(board) {
+            trace.push("KPK bitbase", None, None, score);
+        } else if let Some(score) = self.basic_mate_evaluation(board) {
+            trace.push("Basic mate", None, None, score);
+        } else if let Some(score) = Self::known_draws(board) {
+            trace.push("Known draw", None, None, score);
+        } else {
+            let phase = Self::game_phase(board);
+            let scale = |value: i32, factor: i32| (value * factor) / 24;
+
+            let (white_material, white_mg, white_eg) = self.material_and_pst_side(board, 0);
+            let (black_material, black_mg, black_eg) = self.material_and_pst_side(board, 1);
+            let (mg_score, eg_score) = self.material_and_pst(board);
+            let material = white_material - black_material;
+            trace.push("Material", Some(white_material), Some(black_material), material);
+            trace.push(
+                "PST",
+                Some(Self::tapered_eval(white_mg, white_eg, phase)),
+                Some(Self::tapered_eval(black_mg, black_eg, phase)),
+                Self::tapered_eval(mg_score, eg_score, phase) - material,
+            );
+            trace.push(
+                "Imbalance",
+                Some(self.imbalance_side(board, 0)),
+                Some(self.imbalance_side(board, 1)),
+                self.imbalance(board),
+            );
+
+            trace.push(
+                "Tactical safety",
+                Some(self.scale_tactical(self.tactical_safety_side(board, 0), phase)),
+                Some(self.scale_tactical(self.tactical_safety_side(board, 1), phase)),
+                self.tactical_safety(board, phase),
+            );
+            trace.push("Pawn structure", None, None, self.pawn_structure(board, phase));
+
+            let (white_mobility, black_mobility) = self.mobility_by_side(board);
+            trace.push(
+                "Mobility",
+                Some(scale(white_mobility, phase)),
+                Some(scale(black_mobility, phase)),
+                self.piece_mobility_safe(board, phase),
+            );
+
+            let (white_safety, black_safety) = self.king_safety_by_side(board, phase);
+            trace.push("King safety", Some(white_safety), Some(black_safety), self.king_safety_advanced(board, phase));
+
+            let (white_tropism, black_tropism) = self.king_tropism_by_side(board);
+            trace.push(
+                "King tropism",
+                Some(scale(white_tropism, phase)),
+                Some(scale(black_tropism, phase)),
+                self.king_tropism(board, phase),
+            );
+
+            let (white_space, black_space) = self.space_by_side(board, phase);
+            trace.push(
+                "Space",
+                Some(scale(white_space, phase)),
+                Some(scale(black_space, phase)),
+                self.space_evaluation(board, phase),
+            );
+
+            trace.push("Rooks", None, None, self.rook_evaluation(board));
+            trace.push("Bishops", None, None, self.bishop_evaluation(board));
+            trace.push("Knights", None, None, self.knight_evaluation(board));
+
+            let tempo = self.tempo_bonus(board);
+            trace.push("Tempo", Some(tempo.max(0)), Some((-tempo).max(0)), tempo);
+
+            let unscaled: i32 = trace.terms.iter().map(|term| term.total).sum();
+            let scaled = unscaled * self.endgame_scale(board, unscaled, phase) / SCALE_NORMAL;
+            trace.push("Endgame scaling", None, None, scaled - unscaled);
+        }
+
+        let white_score: i32 = trace.terms.iter().map(|term| term.total).sum();
+        trace.score = if board.side_to_move == Color::Black { -white_score } else { white_score };
+        trace
+    }
+
+    // Exact result for king + pawn vs king, from white's perspective
+    fn kpk_evaluation(&self, board: &BoardState) -> Option<i32> {
+        if count_bits(board.all_pieces) != 3 {
+            return None;
+        }
+
+        let white_pawns = board.pieces[0][Piece::Pawn as usize];
+        let black_pawns = board.pieces[1][Piece::Pawn as usize];
+        let (strong, pawns) = match (white_pawns != 0, black_pawns != 0) {
+            (true, false) => (0, white_pawns),
+            (false, true) => (1, black_pawns),
+            _ => return None,
+        };
+
+        // Flip ranks so the side with the pawn always plays up the board
+        let flip = if strong == 0 { 0 } else { 56 };
+        let pawn = lsb(pawns)? ^ flip;
+        let strong_king = lsb(board.pieces[strong][Piece::King as usize])? ^ flip;
+        let weak_king = lsb(board.pieces[strong ^ 1][Piece::King as usize])? ^ flip;
+        let stm = if board.side_to_move as usize == strong { 0 } else { 1 };
+
+        if !bitbase::probe_kpk(strong_king, pawn, weak_king, stm) {
+            return Some(0);
+        }
+
+        // Material counts on top, as in basic_mate_evaluation, so queening
+        // still scores higher than the pawn it came from
+        let score = KNOWN_WIN + self.params.pawn_value + (pawn / 8) as i32 * 10;
+        Some(if strong == 0 { score } else { -score })
+    }
+
+    // King and heavy pieces against a bare king, from white's perspective:
+    // herd the lone king to the edge and bring the other king in to help
+    fn basic_mate_evaluation(&self, board: &BoardState) -> Option<i32> {
+        let lone_king = |color: usize| board.color_bb[color] == board.pieces[color][Piece::King as usize];
+        let heavy_only = |color: usize| {
+            let heavy = board.pieces[color][Piece::Rook as usize] | board.pieces[color][Piece::Queen as usize];
+            heavy != 0 && board.color_bb[color] == heavy | board.pieces[color][Piece::King as usize]
+        };
+
+        let strong = match (lone_king(0), lone_king(1)) {
+            (false, true) if heavy_only(0) => 0,
+            (true, false) if heavy_only(1) => 1,
+            _ => return None,
+        };
+
+        let strong_king = lsb(board.pieces[strong][Piece::King as usize])?;
+        let weak_king = lsb(board.pieces[strong ^ 1][Piece::King as usize])?;
+
+        let material = count_bits(board.pieces[strong][Piece::Rook as usize]) as i32 * self.params.rook_value
+            + count_bits(board.pieces[strong][Piece::Queen as usize]) as i32 * self.params.queen_value;
+        let score = KNOWN_WIN
+            + material
+            + PUSH_TO_EDGE * center_distance(weak_king)
+            + PUSH_CLOSE * (7 - distance(strong_king, weak_king) as i32);
+
+        Some(if strong == 0 { score } else { -score })
+    }
+
+    // Fortresses the extra material can't break, from white's perspective.
+    // Only clear-cut setups are recognized: calling a won ending drawn costs
+    // more than grinding on in a drawn one.
+    fn known_draws(board: &BoardState) -> Option<i32> {
+        if count_bits(board.all_pieces) > 6 {
+            return None;
+        }
+        Self::philidor_draw(board).or_else(|| Self::wrong_bishop_draw(board))
+    }
+
+    // KRP v KR: defending king in front of the pawn, rook holding the third
+    // rank from the pawn's side so the attacking king can't come forward
+    fn philidor_draw(board: &BoardState) -> Option<i32> {
+        let rook_and_pawns = |color: usize, pawns: u32| {
+            let pieces = &board.pieces[color];
+            board.color_bb[color] == pieces[Piece::King as usize] | pieces[Piece::Rook as usize] | pieces[Piece::Pawn as usize]
+                && count_bits(pieces[Piece::Rook as usize]) == 1
+                && count_bits(pieces[Piece::Pawn as usize]) == pawns
+        };
+
+        let strong = match (rook_and_pawns(0, 1), rook_and_pawns(1, 1)) {
+            (true, false) if rook_and_pawns(1, 0) => 0,
+            (false, true) if rook_and_pawns(0, 0) => 1,
+            _ => return None,
+        };
+        let weak = strong ^ 1;
+        if board.is_in_check(if weak == 0 { Color::White } else { Color::Black }) {
+            return None;
+        }
+
+        // Defending rook must not simply be lost
+        let weak_rook = lsb(board.pieces[weak][Piece::Rook as usize])?;
+        let strong_king = lsb(board.pieces[strong][Piece::King as usize])?;
+        let rook_attacks = ATTACK_TABLES.get_rook_attacks(weak_rook, board.all_pieces);
+        if rook_attacks & board.pieces[strong][Piece::Rook as usize] != 0 || distance(strong_king, weak_rook) <= 1 {
+            return None;
+        }
+
+        // Flip ranks so the pawn always runs up the board
+        let flip = if strong == 0 { 0 } else { 56 };
+        let pawn = lsb(board.pieces[strong][Piece::Pawn as usize])? ^ flip;
+        let weak_king = lsb(board.pieces[weak][Piece::King as usize])? ^ flip;
+
+        let holds = pawn / 8 <= 4
+            && (strong_king ^ flip) / 8 <= 4
+            && (weak_king % 8).abs_diff(pawn % 8) <= 1
+            && weak_king / 8 >= 6
+            && (weak_rook ^ flip) / 8 == 5;
+        holds.then_some(0)
+    }
+
+    // Bishop and rook pawns against a bare king that has reached the
+    // promotion corner the bishop can't cover
+    fn wrong_bishop_draw(board: &BoardState) -> Option<i32> {
+        let bishop_and_pawns = |color: usize| {
+            let pieces = &board.pieces[color];
+            board.color_bb[color] == pieces[Piece::King as usize] | pieces[Piece::Bishop as usize] | pieces[Piece::Pawn as usize]
+                && count_bits(pieces[Piece::Bishop as usize]) == 1
+                && pieces[Piece::Pawn as usize] != 0
+        };
+        let lone_king = |color: usize| board.color_bb[color] == board.pieces[color][Piece::King as usize];
+
+        let strong = match (bishop_and_pawns(0), bishop_and_pawns(1)) {
+            (true, false) if lone_king(1) => 0,
+            (false, true) if lone_king(0) => 1,
+            _ => return None,
+        };
+
+        let pawns = board.pieces[strong][Piece::Pawn as usize];
+        let file = if pawns & FILE_A == pawns {
+            0
+        } else if pawns & FILE_H == pawns {
+            7
+        } else {
+            return None;
+        };
+
+        let promotion = if strong == 0 { 56 + file } else { file };
+        let bishop = lsb(board.pieces[strong][Piece::Bishop as usize])?;
+        let light = |sq: u8| (sq / 8 + sq % 8) % 2 == 1;
+        if light(bishop) == light(promotion) {
+            return None;
+        }
+
+        let weak_king = lsb(board.pieces[strong ^ 1][Piece::King as usize])?;
+        (distance(weak_king, promotion) <= 1).then_some(0)
+    }
+
+    // The scale factor is an endgame one: it takes over from SCALE_NORMAL
+    // as the pieces come off. Without the material to mate, though, the
+    // game is drawn whatever is left.
+    fn endgame_scale(&self, board: &BoardState, score: i32, phase: i32) -> i32 {
+        match self.scale_factor(board, score) {
+            0 => 0,
+            factor => (SCALE_NORMAL * phase + factor * (24 - phase)) / 24,
+        }
+    }
+
+    // How much of a white-perspective score the side ahead in material can
+    // realistically convert; the score only breaks a material tie
+    fn scale_factor(&self, board: &BoardState, score: i32) -> i32 {
+        let non_pawn_material = |color: usize| -> i32 {
+            (Piece::Knight as usize..=Piece::Queen as usize)
+                .map(|piece| count_bits(board.pieces[color][piece]) as i32 * self.params.piece_value(piece))
+                .sum()
+        };
+        let pawns = |color: usize| count_bits(board.pieces[color][Piece::Pawn as usize]) as i32;
+        let total = |color: usize| non_pawn_material(color) + pawns(color) * self.params.pawn_value;
+
+        let strong = match total(0).cmp(&total(1)) {
+            std::cmp::Ordering::Greater => 0,
+            std::cmp::Ordering::Less => 1,
+            std::cmp::Ordering::Equal => if score > 0 { 0 } else { 1 },
+        };
+        let weak = strong ^ 1;
+
+        let strong_material = non_pawn_material(strong);
+        let weak_material = non_pawn_material(weak);
+        let strong_pawns = pawns(strong);
+        let weak_pawns = pawns(weak);
+
+        // Without pawns, a minor piece or less extra isn't enough to mate
+        if strong_pawns == 0 && strong_material - weak_material <= self.params.bishop_value {
+            return if strong_material < self.params.rook_value {
+                0
+            } else if weak_material <= self.params.bishop_value {
+                4
+            } else {
+                14
+            };
+        }
+
+        // Opposite-colored bishops
+        let white_bishops = board.pieces[0][Piece::Bishop as usize];
+        let black_bishops = board.pieces[1][Piece::Bishop as usize];
+        if count_bits(white_bishops) == 1 && count_bits(black_bishops) == 1 {
+            let white_square = lsb(white_bishops).unwrap();
+            let black_square = lsb(black_bishops).unwrap();
+            let white_is_light = (white_square / 8 + white_square % 8).is_multiple_of(2);
+            let black_is_light = (black_square / 8 + black_square % 8).is_multiple_of(2);
+
+            if white_is_light != black_is_light {
+                return if strong_material == self.params.bishop_value
+                    && weak_material == self.params.bishop_value
+                {
+                    SCALE_PURE_OCB
+                } else {
+                    SCALE_OCB_WITH_PIECES
+                };
+            }
+        }
+
+        // A pawn or less up with few pawns left is hard to convert
+        let material_edge = strong_material - weak_material + (strong_pawns - weak_pawns) * self.params.pawn_value;
+        if strong_pawns <= 2 && material_edge <= self.params.pawn_value {
+            return (36 + 7 * strong_pawns).min(SCALE_NORMAL);
+        }
+
+        SCALE_NORMAL
+    }
+
     // ══════════════════════════════════════════════════════════════════════════════
     // TACTICAL SAFETY - PREVENTS BLUNDERS (HIGHEST PRIORITY)
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn tactical_safety(board: &BoardState, phase: i32) -> i32 {
+    fn tactical_safety(&self, board: &BoardState, phase: i32) -> i32 {
+        let score = self.tactical_safety_side(board, 0) - self.tactical_safety_side(board, 1);
+        self.scale_tactical(score, phase)
+    }
+
+    // Scale tactical awareness by game phase (more critical in middlegame),
+    // then down to the configured share
+    fn scale_tactical(&self, score: i32, phase: i32) -> i32 {
+        (score * (12 + phase)) / 24 * self.params.tactical_safety_weight / 100
+    }
+
+    // Hanging, pinned and trapped pieces plus tactical chances for one side
+    fn tactical_safety_side(&self, board: &BoardState, color: usize) -> i32 {
         let mut score = 0;
         let tables = &ATTACK_TABLES;
         
-        // Check both sides for hanging pieces and tactical threats
-        for color in 0..2 {
-            let sign = if color == 0 { 1 } else { -1 };
-            let enemy_color = if color == 0 { 1 } else { 0 };
+        // Check all pieces for being undefended or underdefended
+        for piece_type in 1..=5 {  // Pawn to Queen (not King)
+            let mut pieces = board.pieces[color][piece_type];
             
-            // Check all pieces for being undefended or underdefended
-            for piece_type in 1..=5 {  // Pawn to Queen (not King)
-                let mut pieces = board.pieces[color][piece_type];
+            while pieces != 0 {
+                let (new_bb, sq) = pop_lsb(pieces);
+                pieces = new_bb;
+                let square = sq.unwrap();
                 
-                while pieces != 0 {
-                    let (new_bb, sq) = pop_lsb(pieces);
-                    pieces = new_bb;
-                    let square = sq.unwrap();
-                    
-                    // SEE (Static Exchange Evaluation) for this square
-                    let see_score = Self::see_square(board, square, color as u8);
-                    
-                    if see_score < 0 {
-                        // Losing the piece
-                        score += sign * see_score;
-                    }
-                    
-                    // Advanced pin detection
-                    let pin_type = Self::detect_pin_type(board, square, color as u8, tables);
-                    match pin_type {
-                        PinType::Absolute => {
-                            score += sign * -ABSOLUTE_PIN_PENALTY;
-                        }
-                        PinType::Relative => {
-                            score += sign * -RELATIVE_PIN_PENALTY;
-                        }
-                        PinType::None => {}
+                // SEE (Static Exchange Evaluation) for this square
+                let see_score = Self::see_square(board, square, color as u8);
+                
+                if see_score < 0 {
+                    // Losing the piece
+                    score += see_score;
+                }
+                
+                // Advanced pin detection
+                let pin_type = Self::detect_pin_type(board, square, color as u8, tables);
+                match pin_type {
+                    PinType::Absolute => {
+                        score -= self.params.absolute_pin_penalty;
                     }
-                    
-                    // Trapped piece detection
-                    if Self::is_piece_trapped(board, square, piece_type, color, tables) {
-                        score += sign * -TRAPPED_PIECE;
+                    PinType::Relative => {
+                        score -= self.params.relative_pin_penalty;
                     }
+                    PinType::None => {}
+                }
+                
+                // Trapped piece detection
+                if Self::is_piece_trapped(board, square, piece_type, color, tables) {
+                    score -= self.params.trapped_piece;
                 }
             }
-            
-            // Threat detection (what can we attack next move?)
-            score += sign * Self::detect_threats(board, color as u8, tables);
-            
-            // Fork detection (knight and pawn forks)
-            score += sign * Self::detect_forks(board, color as u8, tables);
-            
-            // Skewer detection
-            score += sign * Self::detect_skewers(board, color as u8, tables);
-            
-            // Discovered attack potential
-            score += sign * Self::detect_discovered_attacks(board, color as u8, tables);
         }
         
-        // Scale tactical awareness by game phase (more critical in middlegame)
-        (score * (12 + phase)) / 24
+        // Threat detection (what can we attack next move?)
+        score += self.detect_threats(board, color as u8, tables);
+        
+        // Fork detection (knight and pawn forks)
+        score += self.detect_forks(board, color as u8, tables);
+        
+        // Skewer detection
+        score += self.detect_skewers(board, color as u8, tables);
+        
+        // Discovered attack potential
+        score += self.detect_discovered_attacks(board, color as u8, tables);
+
+        score
     }
 
     // 🎯 SEE - Static Exchange Evaluation
     fn see_square(board: &BoardState, square: u8, defender_color: u8) -> i32 {
-        let attacker_color = 1 - defender_color;
+        let attacker_color = 1 - defender_color as usize;
         let tables = &ATTACK_TABLES;
         
         // Get piece on square
@@ -315,59 +1103,107 @@ impl Evaluator {
             None => return 0,
         };
         
-        let target_value = PIECE_VALUES[piece as usize];
-        
         // Find least valuable attacker
-        let attacker_sq = Self::find_least_valuable_attacker(board, square, attacker_color as usize, tables);
+        let (attacker_sq, attacker_piece) = match Self::find_least_valuable_attacker(board, square, attacker_color, board.all_pieces, tables) {
+            Some(a) => a,
+            None => return 0, // No attackers, piece is safe
+        };
         
-        if attacker_sq.is_none() {
-            // No attackers, piece is safe
-            return 0;
+        // Play out the full exchange starting with the cheapest capture
+        let occ = board.all_pieces & !(1u64 << attacker_sq);
+        let swing = Self::swap_off(
+            board,
+            square,
+            occ,
+            PIECE_VALUES[piece as usize],
+            PIECE_VALUES[attacker_piece],
+            defender_color as usize,
+        );
+        
+        if swing > 0 {
+            -swing // Losing material on this square
+        } else {
+            0
         }
+    }
+
+    /// Exact material swing of the capture `mv` for the side making it,
+    /// assuming both sides keep recapturing with their least valuable piece
+    /// and may stop whenever continuing would lose material.
+    pub fn see(board: &BoardState, mv: &Move) -> i32 {
+        let (attacker, color) = match board.piece_at(mv.from) {
+            Some(p) => p,
+            None => return 0,
+        };
         
-        let attacker_sq = attacker_sq.unwrap();
-        let (attacker_piece, _) = board.piece_at(attacker_sq).unwrap();
-        let attacker_value = PIECE_VALUES[attacker_piece as usize];
-        
-        // Simple SEE: if we lose more than we gain, it's bad
-        let gain = target_value;
+        let mut occ = board.all_pieces & !(1u64 << mv.from);
         
-        // Check if piece is defended
-        let defenders = Self::count_attackers(board, square, defender_color as usize, tables);
+        let mut captured = if mv.flags == EP_CAPTURE {
+            let ep_captured_sq = if color == Color::White { mv.to - 8 } else { mv.to + 8 };
+            occ &= !(1u64 << ep_captured_sq);
+            PIECE_VALUES[Piece::Pawn as usize]
+        } else {
+            board.piece_at(mv.to).map_or(0, |(piece, _)| PIECE_VALUES[piece as usize])
+        };
         
-        if defenders == 0 {
-            return -target_value; // Hanging piece
+        let mut on_square = PIECE_VALUES[attacker as usize];
+        if let Some(promo) = mv.promotion_piece() {
+            captured += PIECE_VALUES[promo as usize] - PIECE_VALUES[Piece::Pawn as usize];
+            on_square = PIECE_VALUES[promo as usize];
         }
         
-        // Approximate exchange
-        let loss = attacker_value.min(target_value);
+        Self::swap_off(board, mv.to, occ, captured, on_square, color.flip() as usize)
+    }
+
+    // Swap-list evaluation of the exchange on `square`. `first_gain` is what the
+    // initial capture won, `on_square` the value of the piece now standing there
+    // and `side` the color to recapture next. Removing each capturer from `occ`
+    // uncovers x-ray attackers behind it on the following scan.
+    fn swap_off(board: &BoardState, square: u8, mut occ: Bitboard, first_gain: i32, mut on_square: i32, mut side: usize) -> i32 {
+        let tables = &ATTACK_TABLES;
+        let mut gain = [0i32; 32];
+        let mut d = 0;
+        gain[0] = first_gain;
+        
+        while d + 1 < gain.len() {
+            let (sq, piece_type) = match Self::find_least_valuable_attacker(board, square, side, occ, tables) {
+                Some(a) => a,
+                None => break,
+            };
+            
+            d += 1;
+            gain[d] = on_square - gain[d - 1];
+            on_square = PIECE_VALUES[piece_type];
+            occ &= !(1u64 << sq);
+            side = 1 - side;
+        }
         
-        if gain > loss + 100 {
-            return 0; // Winning capture
-        } else if gain < loss - 100 {
-            return gain - loss; // Losing
+        // Negamax the swap list back to the first capture
+        while d > 0 {
+            gain[d - 1] = -(-gain[d - 1]).max(gain[d]);
+            d -= 1;
         }
         
-        0 // About equal
+        gain[0]
     }
 
-    fn find_least_valuable_attacker(board: &BoardState, square: u8, color: usize, tables: &AttackTables) -> Option<u8> {
+    fn find_least_valuable_attacker(board: &BoardState, square: u8, color: usize, occ: Bitboard, tables: &AttackTables) -> Option<(u8, usize)> {
         // Check in order: Pawn, Knight, Bishop, Rook, Queen, King
         for piece_type in 1..=6 {
-            let pieces = board.pieces[color][piece_type];
+            let pieces = board.pieces[color][piece_type] & occ;
             
             let attackers = match piece_type {
                 1 => tables.pawn_attacks[1 - color][square as usize] & pieces,
                 2 => tables.knight_attacks[square as usize] & pieces,
-                3 => tables.get_bishop_attacks(square, board.all_pieces) & pieces,
-                4 => tables.get_rook_attacks(square, board.all_pieces) & pieces,
-                5 => tables.get_queen_attacks(square, board.all_pieces) & pieces,
+                3 => tables.get_bishop_attacks(square, occ) & pieces,
+                4 => tables.get_rook_attacks(square, occ) & pieces,
+                5 => tables.get_queen_attacks(square, occ) & pieces,
                 6 => tables.king_attacks[square as usize] & pieces,
                 _ => 0,
             };
             
             if attackers != 0 {
-                return lsb(attackers);
+                return lsb(attackers).map(|sq| (sq, piece_type));
             }
         }
         
@@ -384,24 +1220,20 @@ impl Evaluator {
         let enemy_color = if color == 0 { 1 } else { 0 };
         let square_bb = 1u64 << square;
         
-        // Check diagonal pins
-        let bishop_attacks_from_king = tables.get_bishop_attacks(king_sq, board.all_pieces);
-        if (bishop_attacks_from_king & square_bb) != 0 {
-            let occ_without_piece = board.all_pieces & !(square_bb);
-            let extended_attacks = tables.get_bishop_attacks(king_sq, occ_without_piece);
-            if (extended_attacks & (board.pieces[enemy_color][Piece::Bishop as usize] |
-                                   board.pieces[enemy_color][Piece::Queen as usize])) != 0 {
-                return PinType::Absolute;
-            }
-        }
-        
-        // Check file/rank pins
-        let rook_attacks_from_king = tables.get_rook_attacks(king_sq, board.all_pieces);
-        if (rook_attacks_from_king & square_bb) != 0 {
-            let occ_without_piece = board.all_pieces & !(square_bb);
-            let extended_attacks = tables.get_rook_attacks(king_sq, occ_without_piece);
-            if (extended_attacks & (board.pieces[enemy_color][Piece::Rook as usize] |
-                                   board.pieces[enemy_color][Piece::Queen as usize])) != 0 {
+        // Absolute pins: an enemy slider lined up with our king with only
+        // this piece in between
+        let enemy_diagonal = board.pieces[enemy_color][Piece::Bishop as usize] |
+                             board.pieces[enemy_color][Piece::Queen as usize];
+        let enemy_straight = board.pieces[enemy_color][Piece::Rook as usize] |
+                             board.pieces[enemy_color][Piece::Queen as usize];
+        let mut snipers = (tables.get_bishop_attacks(king_sq, 0) & enemy_diagonal) |
+                          (tables.get_rook_attacks(king_sq, 0) & enemy_straight);
+        
+        while snipers != 0 {
+            let (new_bb, sniper_sq) = pop_lsb(snipers);
+            snipers = new_bb;
+            
+            if tables.between(king_sq, sniper_sq.unwrap()) & board.all_pieces == square_bb {
                 return PinType::Absolute;
             }
         }
@@ -481,7 +1313,7 @@ impl Evaluator {
     }
 
     // 🛡️ Threat Detection
-    fn detect_threats(board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
+    fn detect_threats(&self, board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
         let mut threat_score = 0;
         let enemy_color = 1 - color as usize;
         
@@ -519,7 +1351,7 @@ impl Evaluator {
                         
                         // Bonus if we attack more valuable piece
                         if t_value > our_value + 100 {
-                            threat_score += THREAT_BONUS;
+                            threat_score += self.params.threat_bonus;
                         }
                     }
                 }
@@ -529,35 +1361,7 @@ impl Evaluator {
         threat_score
     }
     
-    fn count_attackers(board: &BoardState, square: u8, color: usize, tables: &AttackTables) -> i32 {
-        let mut count = 0;
-        
-        // Pawn attackers
-        let pawn_attacks = tables.pawn_attacks[1 - color][square as usize];
-        count += count_bits(pawn_attacks & board.pieces[color][Piece::Pawn as usize]) as i32;
-        
-        // Knight attackers
-        let knight_attacks = tables.knight_attacks[square as usize];
-        count += count_bits(knight_attacks & board.pieces[color][Piece::Knight as usize]) as i32;
-        
-        // Bishop/Queen diagonal attackers
-        let bishop_attacks = tables.get_bishop_attacks(square, board.all_pieces);
-        count += count_bits(bishop_attacks & (board.pieces[color][Piece::Bishop as usize] |
-                                              board.pieces[color][Piece::Queen as usize])) as i32;
-        
-        // Rook/Queen file attackers
-        let rook_attacks = tables.get_rook_attacks(square, board.all_pieces);
-        count += count_bits(rook_attacks & (board.pieces[color][Piece::Rook as usize] |
-                                            board.pieces[color][Piece::Queen as usize])) as i32;
-        
-        // King attackers
-        let king_attacks = tables.king_attacks[square as usize];
-        count += count_bits(king_attacks & board.pieces[color][Piece::King as usize]) as i32;
-        
-        count
-    }
-    
-    fn detect_forks(board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
+    fn detect_forks(&self, board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
         let mut fork_score = 0;
         let enemy_color = 1 - color as usize;
         
@@ -572,7 +1376,7 @@ impl Evaluator {
             let attacked_pieces = attacks & board.color_bb[enemy_color];
             
             if count_bits(attacked_pieces) >= 2 {
-                fork_score += FORK_BONUS;
+                fork_score += self.params.fork_bonus;
             }
         }
         
@@ -587,7 +1391,7 @@ impl Evaluator {
             let attacked_pieces = attacks & board.color_bb[enemy_color];
             
             if count_bits(attacked_pieces) >= 2 {
-                fork_score += FORK_BONUS / 2;
+                fork_score += self.params.fork_bonus / 2;
             }
         }
         
@@ -595,7 +1399,7 @@ impl Evaluator {
     }
 
     // 🎨 Skewer Detection
-    fn detect_skewers(board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
+    fn detect_skewers(&self, board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
         let mut skewer_score = 0;
         let enemy_color = 1 - color as usize;
         
@@ -639,7 +1443,7 @@ impl Evaluator {
                                 
                                 // Skewer if front piece is more valuable
                                 if front_val >= back_val + 200 {
-                                    skewer_score += SKEWER_BONUS;
+                                    skewer_score += self.params.skewer_bonus;
                                 }
                             }
                         }
@@ -652,7 +1456,7 @@ impl Evaluator {
     }
 
     // 🌟 Discovered Attack Detection
-    fn detect_discovered_attacks(board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
+    fn detect_discovered_attacks(&self, board: &BoardState, color: u8, tables: &AttackTables) -> i32 {
         let mut discovered_score = 0;
         let enemy_color = 1 - color as usize;
         
@@ -699,7 +1503,7 @@ impl Evaluator {
                             
                             let new_targets = extended & board.color_bb[enemy_color] & !attacks;
                             if new_targets != 0 {
-                                discovered_score += DISCOVERED_ATTACK_BONUS;
+                                discovered_score += self.params.discovered_attack_bonus;
                             }
                         }
                     }
@@ -714,125 +1518,284 @@ impl Evaluator {
     // GAME PHASE AND TAPERING
     // ══════════════════════════════════════════════════════════════════════════════
     
+    /// Game phase from 0 (pawns and kings only) to 24 (full starting
+    /// material): minors count 1, rooks 2, queens 4. Each side is capped at
+    /// its starting 12, so promotions can't push the phase past the start,
+    /// while a queen promoted after trades still counts toward a middlegame.
     fn game_phase(board: &BoardState) -> i32 {
-        let mut phase = 0;
-        phase += count_bits(board.pieces[0][Piece::Knight as usize]) as i32;
-        phase += count_bits(board.pieces[1][Piece::Knight as usize]) as i32;
-        phase += count_bits(board.pieces[0][Piece::Bishop as usize]) as i32;
-        phase += count_bits(board.pieces[1][Piece::Bishop as usize]) as i32;
-        phase += count_bits(board.pieces[0][Piece::Rook as usize]) as i32 * 2;
-        phase += count_bits(board.pieces[1][Piece::Rook as usize]) as i32 * 2;
-        phase += count_bits(board.pieces[0][Piece::Queen as usize]) as i32 * 4;
-        phase += count_bits(board.pieces[1][Piece::Queen as usize]) as i32 * 4;
-        phase.min(24)
+        let side_phase = |color: usize| {
+            let pieces = &board.pieces[color];
+            let phase = count_bits(pieces[Piece::Knight as usize]) as i32
+                + count_bits(pieces[Piece::Bishop as usize]) as i32
+                + count_bits(pieces[Piece::Rook as usize]) as i32 * 2
+                + count_bits(pieces[Piece::Queen as usize]) as i32 * 4;
+            phase.min(12)
+        };
+        side_phase(0) + side_phase(1)
     }
 
     fn tapered_eval(mg_score: i32, eg_score: i32, phase: i32) -> i32 {
         (mg_score * phase + eg_score * (24 - phase)) / 24
     }
 
+    /// Win/draw/loss chances in permille for a side-to-move `score`. A
+    /// logistic curve whose midpoint and spread grow with the material
+    /// left: the same edge converts more often once the board is empty.
+    pub fn wdl(score: i32, board: &BoardState) -> [u32; 3] {
+        let phase = Self::game_phase(board) as f64;
+        let midpoint = 150.0 + 100.0 * phase / 24.0;
+        let spread = 60.0 + 40.0 * phase / 24.0;
+        let chance = |score: f64| 1000.0 / (1.0 + ((midpoint - score) / spread).exp());
+
+        let score = score.clamp(-10_000, 10_000) as f64;
+        let win = chance(score).round() as u32;
+        let loss = (chance(-score).round() as u32).min(1000 - win);
+        [win, 1000 - win - loss, loss]
+    }
+
     // ══════════════════════════════════════════════════════════════════════════════
     // MATERIAL AND PIECE-SQUARE TABLES
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn material_and_pst(board: &BoardState) -> (i32, i32) {
+    fn material_and_pst(&self, board: &BoardState) -> (i32, i32) {
+        if self.incremental_psq {
+            return (board.mg_material_pst, board.eg_material_pst);
+        }
+
         let mut mg_score = 0;
         let mut eg_score = 0;
 
         for color in 0..2 {
             let sign = if color == 0 { 1 } else { -1 };
+            let (material, pst_mg, pst_eg) = self.material_and_pst_side(board, color);
+            mg_score += sign * (material + pst_mg);
+            eg_score += sign * (material + pst_eg);
+        }
 
-            for piece_type in 1..=6 {
-                let pieces = board.pieces[color][piece_type];
-                let count = count_bits(pieces) as i32;
-                let material = PIECE_VALUES[piece_type] * count;
-                mg_score += sign * material;
-                eg_score += sign * material;
+        (mg_score, eg_score)
+    }
 
-                let mut temp = pieces;
-                while temp != 0 {
-                    let (new_bb, sq) = pop_lsb(temp);
-                    temp = new_bb;
-                    let square = sq.unwrap();
-                    
-                    let pst_sq = if color == 0 { square } else { square ^ 56 };
-                    
-                    let (mg_bonus, eg_bonus) = match piece_type {
-                        1 => (PAWN_PST_MG[pst_sq as usize], PAWN_PST_EG[pst_sq as usize]),
-                        2 => (KNIGHT_PST_MG[pst_sq as usize], KNIGHT_PST_EG[pst_sq as usize]),
-                        3 => (BISHOP_PST_MG[pst_sq as usize], BISHOP_PST_EG[pst_sq as usize]),
-                        4 => (ROOK_PST_MG[pst_sq as usize], ROOK_PST_EG[pst_sq as usize]),
-                        5 => (QUEEN_PST_MG[pst_sq as usize], QUEEN_PST_EG[pst_sq as usize]),
-                        6 => (KING_PST_MG[pst_sq as usize], KING_PST_EG[pst_sq as usize]),
-                        _ => (0, 0),
-                    };
-                    
-                    mg_score += sign * mg_bonus;
-                    eg_score += sign * eg_bonus;
-                }
+    // Material plus middlegame and endgame PST totals for one side
+    fn material_and_pst_side(&self, board: &BoardState, color: usize) -> (i32, i32, i32) {
+        let mut material = 0;
+        let mut pst_mg = 0;
+        let mut pst_eg = 0;
+
+        for piece_type in 1..=6 {
+            let pieces = board.pieces[color][piece_type];
+            material += self.params.piece_value(piece_type) * count_bits(pieces) as i32;
+
+            let mut temp = pieces;
+            while temp != 0 {
+                let (new_bb, sq) = pop_lsb(temp);
+                temp = new_bb;
+                let square = sq.unwrap();
+                
+                let pst_sq = if color == 0 { square } else { square ^ 56 };
+                
+                let (mg_bonus, eg_bonus) = self.params.pst(piece_type, pst_sq as usize);
+                
+                pst_mg += mg_bonus;
+                pst_eg += eg_bonus;
             }
         }
 
-        (mg_score, eg_score)
+        (material, pst_mg, pst_eg)
     }
 
     // ══════════════════════════════════════════════════════════════════════════════
-    // PAWN STRUCTURE
+    // MATERIAL IMBALANCE
     // ══════════════════════════════════════════════════════════════════════════════
-    
-    fn pawn_structure(board: &BoardState, phase: i32) -> i32 {
+
+    fn imbalance(&self, board: &BoardState) -> i32 {
+        self.imbalance_side(board, 0) - self.imbalance_side(board, 1)
+    }
+
+    // Piece combinations worth more or less than their summed values
+    fn imbalance_side(&self, board: &BoardState, color: usize) -> i32 {
+        let count = |color: usize, piece: Piece| count_bits(board.pieces[color][piece as usize]) as i32;
+        let enemy = color ^ 1;
+
+        let pawns = count(color, Piece::Pawn);
+        let knights = count(color, Piece::Knight);
+        let bishops = count(color, Piece::Bishop);
+        let rooks = count(color, Piece::Rook);
+        let queens = count(color, Piece::Queen);
+        let enemy_minors = count(enemy, Piece::Knight) + count(enemy, Piece::Bishop);
+        let enemy_rooks = count(enemy, Piece::Rook);
+        let enemy_queens = count(enemy, Piece::Queen);
+
         let mut score = 0;
-        let white_pawns = board.pieces[0][Piece::Pawn as usize];
-        let black_pawns = board.pieces[1][Piece::Pawn as usize];
 
-        // Doubled, isolated, backward pawns
-        for file in 0..8 {
-            let file_mask = FILE_A << file;
+        if bishops >= 2 && count(enemy, Piece::Bishop) < 2 {
+            score += self.params.imbalance_bishop_pair;
+        }
+
+        // Knights like closed positions, rooks like open ones
+        score += knights * (pawns - 5) * self.params.imbalance_knight_pawn;
+        score += rooks * (5 - pawns) * self.params.imbalance_rook_pawn;
+
+        // Major pieces doing the same job
+        if rooks >= 2 {
+            score -= self.params.imbalance_rook_pair;
+        }
+        if queens >= 1 && rooks >= 1 {
+            score -= self.params.imbalance_queen_rook;
+        }
+
+        if rooks >= 2 && queens == 0 && enemy_queens >= 1 && enemy_rooks + 2 <= rooks {
+            score += self.params.imbalance_rooks_vs_queen;
+        }
+
+        // Up the exchange: one more rook, one fewer minor
+        if rooks == enemy_rooks + 1 && knights + bishops + 1 == enemy_minors {
+            score += self.params.imbalance_exchange;
+        }
+
+        score
+    }
+
+    // ══════════════════════════════════════════════════════════════════════════════
+    // PAWN STRUCTURE
+    // ══════════════════════════════════════════════════════════════════════════════
+    
+    fn pawn_structure(&self, board: &BoardState, phase: i32) -> i32 {
+        let entry = self.pawn_entry(board);
+        let mut score = entry.score;
+
+        // Passed pawns (the king-distance part depends on more than pawns)
+        score += self.passed_pawn_king_distance(board, entry.passed, phase);
+        
+        // Pawn storms
+        score += self.pawn_storms(board, phase);
+
+        score
+    }
+
+    // Terms that depend on the pawns alone, served from the pawn hash when
+    // one is attached
+    fn pawn_entry(&self, board: &BoardState) -> PawnEntry {
+        if let Some(entry) = self.pawn_table.and_then(|table| table.probe(board.pawn_hash)) {
+            return entry;
+        }
+
+        let (passed_score, passed) = self.passed_pawns(board);
+        let entry = PawnEntry {
+            key: board.pawn_hash,
+            score: self.doubled_and_isolated_pawns(board)
+                + self.backward_and_candidate_pawns(board)
+                + self.pawn_chains(board)
+                + passed_score
+                + self.connected_passers(passed),
+            passed,
+        };
+
+        if let Some(table) = self.pawn_table {
+            table.store(entry);
+        }
+        entry
+    }
+
+    // Every pawn standing on a square its own pawns defend is a chain link
+    fn pawn_chains(&self, board: &BoardState) -> i32 {
+        let white_pawns = board.pieces[0][Piece::Pawn as usize];
+        let black_pawns = board.pieces[1][Piece::Pawn as usize];
+        let white_defended = shift_north(shift_east(white_pawns) | shift_west(white_pawns));
+        let black_defended = shift_south(shift_east(black_pawns) | shift_west(black_pawns));
+
+        let links = count_bits(white_pawns & white_defended) as i32 - count_bits(black_pawns & black_defended) as i32;
+        links * self.params.pawn_chain_bonus
+    }
+
+    fn doubled_and_isolated_pawns(&self, board: &BoardState) -> i32 {
+        let mut score = 0;
+        let white_pawns = board.pieces[0][Piece::Pawn as usize];
+        let black_pawns = board.pieces[1][Piece::Pawn as usize];
+
+        // Doubled and isolated pawns
+        for file in 0..8 {
+            let file_mask = FILE_A << file;
             
             // White doubled pawns
             let white_on_file = count_bits(white_pawns & file_mask);
             if white_on_file > 1 {
-                score -= DOUBLED_PAWN * (white_on_file - 1) as i32;
+                score -= self.params.doubled_pawn * (white_on_file - 1) as i32;
             }
 
             // Black doubled pawns
             let black_on_file = count_bits(black_pawns & file_mask);
             if black_on_file > 1 {
-                score += DOUBLED_PAWN * (black_on_file - 1) as i32;
+                score += self.params.doubled_pawn * (black_on_file - 1) as i32;
             }
 
             // Isolated pawns
-            let mut adjacent_files = 0u64;
-            if file > 0 {
-                adjacent_files |= FILE_A << (file - 1);
-            }
-            if file < 7 {
-                adjacent_files |= FILE_A << (file + 1);
-            }
+            let adjacent_files = ISOLATED_MASK[file];
 
             if (white_pawns & file_mask) != 0 && (white_pawns & adjacent_files) == 0 {
-                score -= ISOLATED_PAWN;
+                score -= self.params.isolated_pawn;
             }
 
             if (black_pawns & file_mask) != 0 && (black_pawns & adjacent_files) == 0 {
-                score += ISOLATED_PAWN;
+                score += self.params.isolated_pawn;
             }
         }
 
-        // Passed pawns
-        score += Self::passed_pawn_evaluation(board, phase);
-        
-        // Pawn chains
-        score += Self::pawn_chains(board, phase);
-        
-        // Pawn storms
-        score += Self::pawn_storms(board, phase);
+        score
+    }
+
+    fn backward_and_candidate_pawns(&self, board: &BoardState) -> i32 {
+        let mut score = 0;
+        let tables = &ATTACK_TABLES;
+
+        for color in 0..2 {
+            let sign = if color == 0 { 1 } else { -1 };
+            let own_pawns = board.pieces[color][Piece::Pawn as usize];
+            let enemy_pawns = board.pieces[1 - color][Piece::Pawn as usize];
+
+            let mut temp = own_pawns;
+            while temp != 0 {
+                let (new_bb, sq) = pop_lsb(temp);
+                temp = new_bb;
+                let square = sq.unwrap();
+                let file = square % 8;
+                let rank = square / 8;
+                let relative_rank = if color == 0 { rank } else { 7 - rank };
+
+                if relative_rank == 0 || relative_rank == 7 {
+                    continue;
+                }
+
+                let adjacent_files = ISOLATED_MASK[file as usize];
+                let ahead = forward_ranks(color, square);
+                let neighbours = own_pawns & adjacent_files;
+
+                // Backward: every neighbour has already advanced past it and
+                // an enemy pawn guards the square in front
+                let stop_sq = if color == 0 { square + 8 } else { square - 8 };
+                if neighbours != 0
+                    && (neighbours & !ahead) == 0
+                    && (tables.pawn_attacks[color][stop_sq as usize] & enemy_pawns) != 0
+                {
+                    score -= sign * self.params.backward_pawn;
+                }
+
+                // Candidate passer: open path on its own file and the pawn plus
+                // its supporters outnumber the enemy sentries
+                let sentries = enemy_pawns & PASSED_PAWN_MASK[color][square as usize] & !FRONT_SPAN[color][square as usize];
+                if sentries != 0 && ((own_pawns | enemy_pawns) & FRONT_SPAN[color][square as usize]) == 0 {
+                    let supporters = neighbours & !ahead;
+                    if 1 + count_bits(supporters) > count_bits(sentries) {
+                        score += sign * self.params.candidate_passed[relative_rank as usize];
+                    }
+                }
+            }
+        }
 
         score
     }
 
     // 🏰 Pawn Storm Evaluation
-    fn pawn_storms(board: &BoardState, phase: i32) -> i32 {
+    fn pawn_storms(&self, board: &BoardState, phase: i32) -> i32 {
         let mut score = 0;
         
         // Only relevant in middlegame with opposite side castling
@@ -864,7 +1827,7 @@ impl Evaluator {
             
             // Bonus for advancing pawns near enemy king
             if (file as i32 - bk_file as i32).abs() <= 1 && rank >= 4 {
-                score += PAWN_STORM_BONUS * (rank as i32 - 3);
+                score += self.params.pawn_storm_bonus * (rank as i32 - 3);
             }
         }
         
@@ -878,15 +1841,18 @@ impl Evaluator {
             let file = square % 8;
             
             if (file as i32 - wk_file as i32).abs() <= 1 && rank <= 3 {
-                score -= PAWN_STORM_BONUS * (4 - rank as i32);
+                score -= self.params.pawn_storm_bonus * (4 - rank as i32);
             }
         }
         
         score
     }
 
-    fn passed_pawn_evaluation(board: &BoardState, phase: i32) -> i32 {
+    // Base bonus for every passed pawn, plus the passers themselves
+    fn passed_pawns(&self, board: &BoardState) -> (i32, [Bitboard; 2]) {
         let mut score = 0;
+        let mut passed = [0u64; 2];
+        let tables = &ATTACK_TABLES;
         let white_pawns = board.pieces[0][Piece::Pawn as usize];
         let black_pawns = board.pieces[1][Piece::Pawn as usize];
 
@@ -896,42 +1862,16 @@ impl Evaluator {
             let (new_bb, sq) = pop_lsb(temp);
             temp = new_bb;
             let square = sq.unwrap();
-            let file = square % 8;
             let rank = square / 8;
 
-            let mut ahead_mask = 0u64;
-            for r in (rank + 1)..8 {
-                if file > 0 {
-                    ahead_mask = set_bit(ahead_mask, r * 8 + file - 1);
-                }
-                ahead_mask = set_bit(ahead_mask, r * 8 + file);
-                if file < 7 {
-                    ahead_mask = set_bit(ahead_mask, r * 8 + file + 1);
-                }
-            }
-
-            if (black_pawns & ahead_mask) == 0 {
-                let mut bonus = PASSED_PAWN_BONUS[rank as usize];
+            if (black_pawns & PASSED_PAWN_MASK[0][square as usize]) == 0 {
+                passed[0] = set_bit(passed[0], square);
+                let mut bonus = self.params.passed_pawn_bonus[rank as usize];
                 
-                // Protected passed pawn
-                let protection_mask = if file > 0 { 1u64 << (square - 9) } else { 0 } |
-                                     if file < 7 { 1u64 << (square - 7) } else { 0 };
-                if (white_pawns & protection_mask) != 0 {
-                    bonus += PROTECTED_PASSED_PAWN[rank as usize];
-                }
-                
-                // King proximity (more important in endgame)
-                if let Some(king_sq) = board.get_king_square(Color::White) {
-                    let king_dist = ((king_sq / 8) as i32 - rank as i32).abs() + 
-                                   ((king_sq % 8) as i32 - file as i32).abs();
-                    bonus += ((8 - king_dist) * (24 - phase)) / 8;
-                }
-                
-                // Enemy king distance (penalty if enemy king is close)
-                if let Some(enemy_king_sq) = board.get_king_square(Color::Black) {
-                    let enemy_king_dist = ((enemy_king_sq / 8) as i32 - rank as i32).abs() + 
-                                         ((enemy_king_sq % 8) as i32 - file as i32).abs();
-                    bonus -= ((8 - enemy_king_dist) * (24 - phase)) / 12;
+                // Protected passed pawn: a white pawn on a square a black
+                // pawn here would attack
+                if (white_pawns & tables.pawn_attacks[1][square as usize]) != 0 {
+                    bonus += self.params.protected_passed_pawn[rank as usize];
                 }
                 
                 score += bonus;
@@ -944,186 +1884,112 @@ impl Evaluator {
             let (new_bb, sq) = pop_lsb(temp);
             temp = new_bb;
             let square = sq.unwrap();
-            let file = square % 8;
             let rank = square / 8;
 
-            let mut ahead_mask = 0u64;
-            for r in (0..rank).rev() {
-                if file > 0 {
-                    ahead_mask = set_bit(ahead_mask, r * 8 + file - 1);
-                }
-                ahead_mask = set_bit(ahead_mask, r * 8 + file);
-                if file < 7 {
-                    ahead_mask = set_bit(ahead_mask, r * 8 + file + 1);
-                }
-            }
-
-            if (white_pawns & ahead_mask) == 0 {
-                let mut bonus = PASSED_PAWN_BONUS[(7 - rank) as usize];
-                
-                let protection_mask = if file > 0 { 1u64 << (square + 7) } else { 0 } |
-                                     if file < 7 { 1u64 << (square + 9) } else { 0 };
-                if (black_pawns & protection_mask) != 0 {
-                    bonus += PROTECTED_PASSED_PAWN[(7 - rank) as usize];
-                }
-                
-                if let Some(king_sq) = board.get_king_square(Color::Black) {
-                    let king_dist = ((king_sq / 8) as i32 - rank as i32).abs() + 
-                                   ((king_sq % 8) as i32 - file as i32).abs();
-                    bonus += ((8 - king_dist) * (24 - phase)) / 8;
-                }
+            if (white_pawns & PASSED_PAWN_MASK[1][square as usize]) == 0 {
+                passed[1] = set_bit(passed[1], square);
+                let mut bonus = self.params.passed_pawn_bonus[(7 - rank) as usize];
                 
-                if let Some(enemy_king_sq) = board.get_king_square(Color::White) {
-                    let enemy_king_dist = ((enemy_king_sq / 8) as i32 - rank as i32).abs() + 
-                                         ((enemy_king_sq % 8) as i32 - file as i32).abs();
-                    bonus -= ((8 - enemy_king_dist) * (24 - phase)) / 12;
+                if (black_pawns & tables.pawn_attacks[0][square as usize]) != 0 {
+                    bonus += self.params.protected_passed_pawn[(7 - rank) as usize];
                 }
                 
                 score -= bonus;
             }
         }
 
-        score
+        (score, passed)
     }
-    
-    fn pawn_chains(board: &BoardState, phase: i32) -> i32 {
+
+    // Passers on neighbouring files at most a rank apart, so either can
+    // cover the other's advance
+    fn connected_passers(&self, passed: [Bitboard; 2]) -> i32 {
         let mut score = 0;
-        let white_pawns = board.pieces[0][Piece::Pawn as usize];
-        let black_pawns = board.pieces[1][Piece::Pawn as usize];
 
-        // White king safety
-        if let Some(king_sq) = board.get_king_square(Color::White) {
-            let mut safety = 0;
-            let king_file = king_sq % 8;
-            let king_rank = king_sq / 8;
-
-            // Pawn shield
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    for dr in 1..=2 {
-                        let r = king_rank as i8 + dr;
-                        if r < 8 {
-                            let sq = (r * 8 + f) as u8;
-                            if get_bit(white_pawns, sq) {
-                                safety += PAWN_SHIELD_BONUS * (3 - dr as i32);
-                            } else {
-                                safety -= PAWN_SHIELD_BONUS / 2;
-                            }
-                        }
-                    }
-                }
-            }
+        for (color, &passers) in passed.iter().enumerate() {
+            let beside = shift_east(passers) | shift_west(passers);
+            let neighbours = beside | shift_north(beside) | shift_south(beside);
 
-            // Open files near king
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    let file_mask = FILE_A << f;
-                    if (white_pawns & file_mask) == 0 {
-                        safety -= OPEN_FILE_NEAR_KING;
-                        // Extra penalty if enemy rooks/queens on the file
-                        if (board.pieces[1][Piece::Rook as usize] & file_mask) != 0 ||
-                           (board.pieces[1][Piece::Queen as usize] & file_mask) != 0 {
-                            safety -= OPEN_FILE_NEAR_KING;
-                        }
-                    }
+            let mut temp = passers & neighbours;
+            while temp != 0 {
+                let (new_bb, sq) = pop_lsb(temp);
+                temp = new_bb;
+                let rank = sq.unwrap() / 8;
+
+                if color == 0 {
+                    score += self.params.connected_passer[rank as usize];
+                } else {
+                    score -= self.params.connected_passer[(7 - rank) as usize];
                 }
             }
+        }
 
-            // Attack pattern recognition
-            let attackers = Self::count_king_zone_attackers(board, king_sq, Color::Black);
-            if attackers > 0 {
-                let attack_index = (attackers as usize).min(5);
-                safety -= KING_ATTACK_WEIGHT[attack_index];
-            }
+        score
+    }
 
-            // Penalty for king in center during middlegame
-            if phase > 18 && king_file >= 2 && king_file <= 5 && king_rank <= 2 {
-                safety -= 30;
-            }
+    // Kings close to a passer matter more as material comes off, and a
+    // passer far out on the wing drags the enemy king away from the rest
+    fn passed_pawn_king_distance(&self, board: &BoardState, passed: [Bitboard; 2], phase: i32) -> i32 {
+        let mut score = 0;
 
-            // Castling rights bonus
-            if board.castling_rights & 3 != 0 {
-                safety += CASTLING_RIGHTS_BONUS;
-            }
+        for (color, &passers) in passed.iter().enumerate() {
+            let sign = if color == 0 { 1 } else { -1 };
+            let own_king = board.get_king_square(if color == 0 { Color::White } else { Color::Black });
+            let enemy_king = board.get_king_square(if color == 0 { Color::Black } else { Color::White });
 
-            score += (safety * phase) / 24;
-        }
-
-        // Black king safety
-        if let Some(king_sq) = board.get_king_square(Color::Black) {
-            let mut safety = 0;
-            let king_file = king_sq % 8;
-            let king_rank = king_sq / 8;
-
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    for dr in -2..=-1 {
-                        let r = king_rank as i8 + dr;
-                        if r >= 0 {
-                            let sq = (r * 8 + f) as u8;
-                            if get_bit(black_pawns, sq) {
-                                safety += PAWN_SHIELD_BONUS * (3 + dr as i32);
-                            } else {
-                                safety -= PAWN_SHIELD_BONUS / 2;
-                            }
-                        }
-                    }
+            let mut temp = passers;
+            while temp != 0 {
+                let (new_bb, sq) = pop_lsb(temp);
+                temp = new_bb;
+                let square = sq.unwrap();
+                let file = square % 8;
+                let rank = square / 8;
+                let mut bonus = 0;
+
+                if let Some(king_sq) = own_king {
+                    let king_dist = ((king_sq / 8) as i32 - rank as i32).abs() + 
+                                   ((king_sq % 8) as i32 - file as i32).abs();
+                    bonus += ((8 - king_dist) * (24 - phase)) / 8;
                 }
-            }
+                
+                // Enemy king distance (penalty if enemy king is close)
+                if let Some(enemy_king_sq) = enemy_king {
+                    let enemy_king_dist = ((enemy_king_sq / 8) as i32 - rank as i32).abs() + 
+                                         ((enemy_king_sq % 8) as i32 - file as i32).abs();
+                    bonus -= ((8 - enemy_king_dist) * (24 - phase)) / 12;
 
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    let file_mask = FILE_A << f;
-                    if (black_pawns & file_mask) == 0 {
-                        safety -= OPEN_FILE_NEAR_KING;
-                        if (board.pieces[0][Piece::Rook as usize] & file_mask) != 0 ||
-                           (board.pieces[0][Piece::Queen as usize] & file_mask) != 0 {
-                            safety -= OPEN_FILE_NEAR_KING;
-                        }
+                    let file_dist = ((enemy_king_sq % 8) as i32 - file as i32).abs();
+                    if file_dist > 2 {
+                        bonus += self.params.outside_passer * (file_dist - 2) * (24 - phase) / 24;
                     }
                 }
-            }
-
-            let attackers = Self::count_king_zone_attackers(board, king_sq, Color::White);
-            if attackers > 0 {
-                let attack_index = (attackers as usize).min(5);
-                safety -= KING_ATTACK_WEIGHT[attack_index];
-            }
-
-            if phase > 18 && king_file >= 2 && king_file <= 5 && king_rank >= 5 {
-                safety -= 30;
-            }
 
-            if board.castling_rights & 12 != 0 {
-                safety += CASTLING_RIGHTS_BONUS;
+                score += sign * bonus;
             }
-
-            score -= (safety * phase) / 24;
         }
 
         score
     }
-
-    fn count_king_zone_attackers(board: &BoardState, king_sq: u8, by_color: Color) -> i32 {
+    
+    // Attack units against the king: each knight-to-queen attacker adds its
+    // weight once per zone square it hits. Only `by_color`'s pieces are
+    // walked, so the defending king covering its own zone never counts.
+    fn king_attack_units(board: &BoardState, king_sq: u8, by_color: Color) -> i32 {
         let tables = &ATTACK_TABLES;
-        let king_zone = tables.king_attacks[king_sq as usize] | (1u64 << king_sq);
+        let king_zone = KING_ZONE[king_sq as usize];
         let color = by_color as usize;
-        
-        let mut attackers = 0;
-        
-        // Count different piece types attacking king zone
-        for piece_type in 2..=5 {  // Knight to Queen
+
+        let mut units = 0;
+
+        // Knight to Queen
+        for (piece_type, &weight) in KING_ATTACK_UNITS.iter().enumerate().take(6).skip(2) {
             let mut pieces = board.pieces[color][piece_type];
-            
+
             while pieces != 0 {
                 let (new_bb, sq) = pop_lsb(pieces);
                 pieces = new_bb;
                 let square = sq.unwrap();
-                
+
                 let attacks = match piece_type {
                     2 => tables.knight_attacks[square as usize],
                     3 => tables.get_bishop_attacks(square, board.all_pieces),
@@ -1131,24 +1997,25 @@ impl Evaluator {
                     5 => tables.get_queen_attacks(square, board.all_pieces),
                     _ => 0,
                 };
-                
-                if (attacks & king_zone) != 0 {
-                    attackers += 1;
-                }
+
+                units += weight * count_bits(attacks & king_zone) as i32;
             }
         }
-        
-        attackers
+
+        units
     }
 
     // ══════════════════════════════════════════════════════════════════════════════
     // PIECE-SPECIFIC EVALUATIONS
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn rook_evaluation(board: &BoardState) -> i32 {
+    fn rook_evaluation(&self, board: &BoardState) -> i32 {
         let mut score = 0;
         let white_pawns = board.pieces[0][Piece::Pawn as usize];
         let black_pawns = board.pieces[1][Piece::Pawn as usize];
+        let passed = self.pawn_entry(board).passed;
+        let passers = passed[0] | passed[1];
+        let tables = &ATTACK_TABLES;
 
         // White rooks
         let mut rooks = board.pieces[0][Piece::Rook as usize];
@@ -1166,19 +2033,25 @@ impl Evaluator {
 
             // Open file
             if (white_pawns & file_mask) == 0 && (black_pawns & file_mask) == 0 {
-                score += ROOK_OPEN_FILE;
+                score += self.params.rook_open_file;
             } 
             // Semi-open file
             else if (white_pawns & file_mask) == 0 {
-                score += ROOK_SEMI_OPEN;
+                score += self.params.rook_semi_open;
+            }
+
+            // Behind our passed pawn or in front of theirs (Tarrasch), with
+            // nothing in between
+            if tables.get_rook_attacks(square, board.all_pieces) & FRONT_SPAN[0][square as usize] & passers != 0 {
+                score += self.params.rook_behind_passer;
             }
 
             // 7th rank bonus
             if rank == 6 {
-                score += ROOK_SEVENTH_RANK;
+                score += self.params.rook_seventh_rank;
                 if let Some(enemy_king) = board.get_king_square(Color::Black) {
                     if enemy_king / 8 == 7 {
-                        score += ROOK_SEVENTH_RANK;
+                        score += self.params.rook_seventh_rank;
                     }
                 }
             }
@@ -1187,10 +2060,16 @@ impl Evaluator {
         // Connected rooks
         if white_rook_files.len() == 2 {
             if white_rook_files[0].abs_diff(white_rook_files[1]) == 1 {
-                score += CONNECTED_ROOKS;
+                score += self.params.connected_rooks;
             }
         }
 
+        // Doubled rooks on a file without our pawns
+        if white_rook_files.len() == 2 && white_rook_files[0] == white_rook_files[1]
+            && (white_pawns & (FILE_A << white_rook_files[0])) == 0 {
+            score += self.params.doubled_rooks;
+        }
+
         // Black rooks
         let mut rooks = board.pieces[1][Piece::Rook as usize];
         let mut black_rook_files = Vec::new();
@@ -1206,16 +2085,20 @@ impl Evaluator {
             black_rook_files.push(file);
 
             if (white_pawns & file_mask) == 0 && (black_pawns & file_mask) == 0 {
-                score -= ROOK_OPEN_FILE;
+                score -= self.params.rook_open_file;
             } else if (black_pawns & file_mask) == 0 {
-                score -= ROOK_SEMI_OPEN;
+                score -= self.params.rook_semi_open;
+            }
+
+            if tables.get_rook_attacks(square, board.all_pieces) & FRONT_SPAN[1][square as usize] & passers != 0 {
+                score -= self.params.rook_behind_passer;
             }
 
             if rank == 1 {
-                score -= ROOK_SEVENTH_RANK;
+                score -= self.params.rook_seventh_rank;
                 if let Some(enemy_king) = board.get_king_square(Color::White) {
                     if enemy_king / 8 == 0 {
-                        score -= ROOK_SEVENTH_RANK;
+                        score -= self.params.rook_seventh_rank;
                     }
                 }
             }
@@ -1223,14 +2106,19 @@ impl Evaluator {
 
         if black_rook_files.len() == 2 {
             if black_rook_files[0].abs_diff(black_rook_files[1]) == 1 {
-                score -= CONNECTED_ROOKS;
+                score -= self.params.connected_rooks;
             }
         }
 
+        if black_rook_files.len() == 2 && black_rook_files[0] == black_rook_files[1]
+            && (black_pawns & (FILE_A << black_rook_files[0])) == 0 {
+            score -= self.params.doubled_rooks;
+        }
+
         score
     }
 
-    fn bishop_evaluation(board: &BoardState) -> i32 {
+    fn bishop_evaluation(&self, board: &BoardState) -> i32 {
         let mut score = 0;
         
         // Bishop pair bonus
@@ -1238,69 +2126,74 @@ impl Evaluator {
         let black_bishops = count_bits(board.pieces[1][Piece::Bishop as usize]);
 
         if white_bishops >= 2 {
-            score += BISHOP_PAIR_BONUS;
+            score += self.params.bishop_pair_bonus;
         }
         if black_bishops >= 2 {
-            score -= BISHOP_PAIR_BONUS;
+            score -= self.params.bishop_pair_bonus;
         }
 
         // Bad bishop detection
-        score += Self::bad_bishop_penalty(board);
+        score += self.bad_bishop_penalty(board);
         
         // Fianchetto patterns
-        score += Self::fianchetto_patterns(board);
+        score += self.fianchetto_patterns(board);
+
+        // Bishop that grabbed a rook pawn and got shut in
+        score += self.trapped_bishops(board);
 
         score
     }
-    
-    // 🎨 Fianchetto Pattern Recognition
-    fn fianchetto_patterns(board: &BoardState) -> i32 {
+
+    // Bxa7 ...b6 and friends: the bishop's only way out runs through an
+    // enemy pawn, so it falls next move. Squares are white's; black's are
+    // the same flipped vertically.
+    fn trapped_bishops(&self, board: &BoardState) -> i32 {
+        const PATTERNS: [(u8, u8); 4] = [
+            (48, 41), // a7, pawn b6
+            (56, 49), // a8, pawn b7
+            (55, 46), // h7, pawn g6
+            (63, 54), // h8, pawn g7
+        ];
+
         let mut score = 0;
-        
-        // White fianchetto squares: b2, g2
-        let white_bishops = board.pieces[0][Piece::Bishop as usize];
-        let white_pawns = board.pieces[0][Piece::Pawn as usize];
-        
-        // Kingside fianchetto (g2 bishop, f3, g3, h3 pawns)
-        if get_bit(white_bishops, 6) {  // g1 becomes 6 in our notation
-            let supporting_pawns = get_bit(white_pawns, 14) || get_bit(white_pawns, 15);  // f2, g2
-            if supporting_pawns {
-                score += FIANCHETTO_BONUS;
+        for (bishop_sq, pawn_sq) in PATTERNS {
+            if get_bit(board.pieces[0][Piece::Bishop as usize], bishop_sq)
+                && get_bit(board.pieces[1][Piece::Pawn as usize], pawn_sq)
+            {
+                score -= self.params.trapped_bishop;
             }
-        }
-        
-        // Queenside fianchetto (b2 bishop)
-        if get_bit(white_bishops, 1) {
-            let supporting_pawns = get_bit(white_pawns, 9) || get_bit(white_pawns, 10);  // a2, b2
-            if supporting_pawns {
-                score += FIANCHETTO_BONUS;
+            if get_bit(board.pieces[1][Piece::Bishop as usize], bishop_sq ^ 56)
+                && get_bit(board.pieces[0][Piece::Pawn as usize], pawn_sq ^ 56)
+            {
+                score += self.params.trapped_bishop;
             }
         }
-        
-        // Black fianchetto
-        let black_bishops = board.pieces[1][Piece::Bishop as usize];
-        let black_pawns = board.pieces[1][Piece::Pawn as usize];
-        
-        // Kingside (g7)
-        if get_bit(black_bishops, 62) {
-            let supporting_pawns = get_bit(black_pawns, 54) || get_bit(black_pawns, 55);
-            if supporting_pawns {
-                score -= FIANCHETTO_BONUS;
-            }
-        }
-        
-        // Queenside (b7)
-        if get_bit(black_bishops, 57) {
-            let supporting_pawns = get_bit(black_pawns, 49) || get_bit(black_pawns, 50);
-            if supporting_pawns {
-                score -= FIANCHETTO_BONUS;
-            }
-        }
-        
+
         score
     }
     
-    fn bad_bishop_penalty(board: &BoardState) -> i32 {
+    // 🎨 Fianchetto Pattern Recognition
+    fn fianchetto_patterns(&self, board: &BoardState) -> i32 {
+        // (bishop, knight-file pawn pushed one square, pawns either side of
+        // the bishop's home) per wing: g2/g3/f2+h2 and b2/b3/a2+c2 for white
+        const WHITE: [(u8, u8, [u8; 2]); 2] = [(14, 22, [13, 15]), (9, 17, [8, 10])];
+        const BLACK: [(u8, u8, [u8; 2]); 2] = [(54, 46, [53, 55]), (49, 41, [48, 50])];
+
+        let fianchettoes = |color: usize, patterns: &[(u8, u8, [u8; 2]); 2]| {
+            let bishops = board.pieces[color][Piece::Bishop as usize];
+            let pawns = board.pieces[color][Piece::Pawn as usize];
+            patterns
+                .iter()
+                .filter(|&&(bishop, pushed, [left, right])| {
+                    get_bit(bishops, bishop) && get_bit(pawns, pushed) && (get_bit(pawns, left) || get_bit(pawns, right))
+                })
+                .count() as i32
+        };
+
+        (fianchettoes(0, &WHITE) - fianchettoes(1, &BLACK)) * self.params.fianchetto_bonus
+    }
+    
+    fn bad_bishop_penalty(&self, board: &BoardState) -> i32 {
         let mut score = 0;
         let white_pawns = board.pieces[0][Piece::Pawn as usize];
         let black_pawns = board.pieces[1][Piece::Pawn as usize];
@@ -1328,7 +2221,7 @@ impl Evaluator {
             }
             
             if blocked_count >= 4 {
-                score -= BAD_BISHOP_PENALTY;
+                score -= self.params.bad_bishop_penalty;
             }
         }
         
@@ -1355,14 +2248,14 @@ impl Evaluator {
             }
             
             if blocked_count >= 4 {
-                score += BAD_BISHOP_PENALTY;
+                score += self.params.bad_bishop_penalty;
             }
         }
         
         score
     }
 
-    fn knight_evaluation(board: &BoardState) -> i32 {
+    fn knight_evaluation(&self, board: &BoardState) -> i32 {
         let mut score = 0;
         let white_pawns = board.pieces[0][Piece::Pawn as usize];
         let black_pawns = board.pieces[1][Piece::Pawn as usize];
@@ -1386,7 +2279,7 @@ impl Evaluator {
                 }
                 
                 if protected {
-                    score += KNIGHT_OUTPOST;
+                    score += self.params.knight_outpost;
                     
                     let mut can_be_attacked = false;
                     if file > 0 {
@@ -1403,7 +2296,7 @@ impl Evaluator {
                     }
                     
                     if !can_be_attacked {
-                        score += KNIGHT_OUTPOST / 2;
+                        score += self.params.knight_outpost / 2;
                     }
                 }
             }
@@ -1428,7 +2321,7 @@ impl Evaluator {
                 }
                 
                 if protected {
-                    score -= KNIGHT_OUTPOST;
+                    score -= self.params.knight_outpost;
                     
                     let mut can_be_attacked = false;
                     if file > 0 {
@@ -1445,7 +2338,7 @@ impl Evaluator {
                     }
                     
                     if !can_be_attacked {
-                        score -= KNIGHT_OUTPOST / 2;
+                        score -= self.params.knight_outpost / 2;
                     }
                 }
             }
@@ -1454,11 +2347,11 @@ impl Evaluator {
         score
     }
 
-    fn tempo_bonus(board: &BoardState) -> i32 {
+    fn tempo_bonus(&self, board: &BoardState) -> i32 {
         if board.side_to_move == Color::White {
-            TEMPO_BONUS
+            self.params.tempo_bonus
         } else {
-            -TEMPO_BONUS
+            -self.params.tempo_bonus
         }
     }
 
@@ -1466,110 +2359,67 @@ impl Evaluator {
     // SAFE MOBILITY (ONLY COUNT SAFE SQUARES)
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn piece_mobility_safe(board: &BoardState, phase: i32) -> i32 {
-        let mut white_mobility = 0;
-        let mut black_mobility = 0;
-        let tables = &ATTACK_TABLES;
-
-        // Build enemy attack maps
-        let white_attacks = build_attack_map(board, 0, tables);
-        let black_attacks = build_attack_map(board, 1, tables);
-
-        // Knights - safe mobility
-        let mut knights = board.pieces[0][Piece::Knight as usize];
-        while knights != 0 {
-            let (new_bb, sq) = pop_lsb(knights);
-            knights = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.knight_attacks[square as usize] & !board.color_bb[0];
-            let safe_attacks = attacks & !black_attacks;
-            white_mobility += count_bits(safe_attacks) as i32 * KNIGHT_MOBILITY;
-        }
-
-        let mut knights = board.pieces[1][Piece::Knight as usize];
-        while knights != 0 {
-            let (new_bb, sq) = pop_lsb(knights);
-            knights = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.knight_attacks[square as usize] & !board.color_bb[1];
-            let safe_attacks = attacks & !white_attacks;
-            black_mobility += count_bits(safe_attacks) as i32 * KNIGHT_MOBILITY;
-        }
-
-        // Bishops - safe mobility
-        let mut bishops = board.pieces[0][Piece::Bishop as usize];
-        while bishops != 0 {
-            let (new_bb, sq) = pop_lsb(bishops);
-            bishops = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_bishop_attacks(square, board.all_pieces) & !board.color_bb[0];
-            let safe_attacks = attacks & !black_attacks;
-            white_mobility += count_bits(safe_attacks) as i32 * BISHOP_MOBILITY;
-        }
-
-        let mut bishops = board.pieces[1][Piece::Bishop as usize];
-        while bishops != 0 {
-            let (new_bb, sq) = pop_lsb(bishops);
-            bishops = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_bishop_attacks(square, board.all_pieces) & !board.color_bb[1];
-            let safe_attacks = attacks & !white_attacks;
-            black_mobility += count_bits(safe_attacks) as i32 * BISHOP_MOBILITY;
-        }
+    fn piece_mobility_safe(&self, board: &BoardState, phase: i32) -> i32 {
+        let (white_mobility, black_mobility) = self.mobility_by_side(board);
+        ((white_mobility - black_mobility) * phase) / 24
+    }
 
-        // Rooks - safe mobility
-        let mut rooks = board.pieces[0][Piece::Rook as usize];
-        while rooks != 0 {
-            let (new_bb, sq) = pop_lsb(rooks);
-            rooks = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_rook_attacks(square, board.all_pieces) & !board.color_bb[0];
-            let safe_attacks = attacks & !black_attacks;
-            white_mobility += count_bits(safe_attacks) as i32 * ROOK_MOBILITY;
-        }
+    fn mobility_by_side(&self, board: &BoardState) -> (i32, i32) {
+        let tables = &ATTACK_TABLES;
+        let attack_maps = [build_attack_map(board, 0, tables), build_attack_map(board, 1, tables)];
+        let mut mobility = [0; 2];
+
+        for (color, side_mobility) in mobility.iter_mut().enumerate() {
+            let enemy_attacks = attack_maps[color ^ 1];
+            let king_sq = board.get_king_square(if color == 0 { Color::White } else { Color::Black });
+            let pinned = king_sq.map_or(0, |king_sq| MoveGenerator::pinned_pieces(board, color, king_sq));
+
+            for (piece_type, weight) in [
+                (Piece::Knight, self.params.knight_mobility),
+                (Piece::Bishop, self.params.bishop_mobility),
+                (Piece::Rook, self.params.rook_mobility),
+                (Piece::Queen, self.params.queen_mobility),
+            ] {
+                let mut pieces = board.pieces[color][piece_type as usize];
+                while pieces != 0 {
+                    let (new_bb, sq) = pop_lsb(pieces);
+                    pieces = new_bb;
+                    let square = sq.unwrap();
 
-        let mut rooks = board.pieces[1][Piece::Rook as usize];
-        while rooks != 0 {
-            let (new_bb, sq) = pop_lsb(rooks);
-            rooks = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_rook_attacks(square, board.all_pieces) & !board.color_bb[1];
-            let safe_attacks = attacks & !white_attacks;
-            black_mobility += count_bits(safe_attacks) as i32 * ROOK_MOBILITY;
-        }
+                    let mut attacks = match piece_type {
+                        Piece::Knight => tables.knight_attacks[square as usize],
+                        Piece::Bishop => tables.get_bishop_attacks(square, board.all_pieces),
+                        Piece::Rook => tables.get_rook_attacks(square, board.all_pieces),
+                        _ => tables.get_queen_attacks(square, board.all_pieces),
+                    } & !board.color_bb[color];
 
-        // Queens - safe mobility
-        let mut queens = board.pieces[0][Piece::Queen as usize];
-        while queens != 0 {
-            let (new_bb, sq) = pop_lsb(queens);
-            queens = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_queen_attacks(square, board.all_pieces) & !board.color_bb[0];
-            let safe_attacks = attacks & !black_attacks;
-            white_mobility += count_bits(safe_attacks) as i32 * QUEEN_MOBILITY;
-        }
+                    // A pinned piece can only slide along the pin
+                    if let Some(king_sq) = king_sq.filter(|_| get_bit(pinned, square)) {
+                        attacks &= tables.line(king_sq, square);
+                    }
 
-        let mut queens = board.pieces[1][Piece::Queen as usize];
-        while queens != 0 {
-            let (new_bb, sq) = pop_lsb(queens);
-            queens = new_bb;
-            let square = sq.unwrap();
-            let attacks = tables.get_queen_attacks(square, board.all_pieces) & !board.color_bb[1];
-            let safe_attacks = attacks & !white_attacks;
-            black_mobility += count_bits(safe_attacks) as i32 * QUEEN_MOBILITY;
+                    let safe_attacks = attacks & !enemy_attacks;
+                    *side_mobility += count_bits(safe_attacks) as i32 * weight;
+                }
+            }
         }
 
-        ((white_mobility - black_mobility) * phase) / 24
+        (mobility[0], mobility[1])
     }
 
     // ══════════════════════════════════════════════════════════════════════════════
     // SPACE EVALUATION
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn space_evaluation(board: &BoardState, phase: i32) -> i32 {
+    fn space_evaluation(&self, board: &BoardState, phase: i32) -> i32 {
+        let (white_space, black_space) = self.space_by_side(board, phase);
+        ((white_space - black_space) * phase) / 24
+    }
+
+    fn space_by_side(&self, board: &BoardState, phase: i32) -> (i32, i32) {
         // Space matters more in middlegame
         if phase < 12 {
-            return 0;
+            return (0, 0);
         }
         
         // Define center and extended center
@@ -1585,144 +2435,109 @@ impl Evaluator {
         let white_extended = count_bits(white_control & EXTENDED_CENTER) as i32;
         let black_extended = count_bits(black_control & EXTENDED_CENTER) as i32;
         
-        let center_score = (white_center - black_center) * SPACE_BONUS * 2;
-        let extended_score = (white_extended - black_extended) * SPACE_BONUS;
-        
-        ((center_score + extended_score) * phase) / 24
+        (
+            white_center * self.params.space_bonus * 2 + white_extended * self.params.space_bonus,
+            black_center * self.params.space_bonus * 2 + black_extended * self.params.space_bonus,
+        )
     }
 
     // ══════════════════════════════════════════════════════════════════════════════
     // ELITE KING SAFETY
     // ══════════════════════════════════════════════════════════════════════════════
     
-    fn king_safety_advanced(board: &BoardState, phase: i32) -> i32 {
+    fn king_safety_advanced(&self, board: &BoardState, phase: i32) -> i32 {
+        let (white_safety, black_safety) = self.king_safety_by_side(board, phase);
+        white_safety - black_safety
+    }
+
+    fn king_safety_by_side(&self, board: &BoardState, phase: i32) -> (i32, i32) {
         // King safety mainly matters in middlegame
         if phase < 10 {
-            return 0;
+            return (0, 0);
         }
 
-        let mut score = 0;
-        let white_pawns = board.pieces[0][Piece::Pawn as usize];
-        let black_pawns = board.pieces[1][Piece::Pawn as usize];
-
-        // White king safety
-        if let Some(king_sq) = board.get_king_square(Color::White) {
-            let mut safety = 0;
-            let king_file = king_sq % 8;
-            let king_rank = king_sq / 8;
-
-            // Pawn shield
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    for dr in 1..=2 {
-                        let r = king_rank as i8 + dr;
-                        if r < 8 {
-                            let sq = (r * 8 + f) as u8;
-                            if get_bit(white_pawns, sq) {
-                                safety += PAWN_SHIELD_BONUS * (3 - dr as i32);
-                            } else {
-                                safety -= PAWN_SHIELD_BONUS / 2;
-                            }
-                        }
-                    }
-                }
-            }
+        (self.king_safety_side(board, 0, phase), self.king_safety_side(board, 1, phase))
+    }
 
-            // Open files near king
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    let file_mask = FILE_A << f;
-                    if (white_pawns & file_mask) == 0 {
-                        safety -= OPEN_FILE_NEAR_KING;
-                        // Extra penalty if enemy rooks/queens on the file
-                        if (board.pieces[1][Piece::Rook as usize] & file_mask) != 0 ||
-                            (board.pieces[1][Piece::Queen as usize] & file_mask) != 0 {
-                            safety -= OPEN_FILE_NEAR_KING;
-                        }
-                    }
+    // Shield, open files, attackers, a central king and castling rights for
+    // `color`'s king, scaled by phase
+    fn king_safety_side(&self, board: &BoardState, color: usize, phase: i32) -> i32 {
+        let (us, them) = if color == 0 { (Color::White, Color::Black) } else { (Color::Black, Color::White) };
+        let Some(king_sq) = board.get_king_square(us) else { return 0 };
+        let pawns = board.pieces[color][Piece::Pawn as usize];
+        let king_file = king_sq % 8;
+        let relative_rank = if color == 0 { king_sq / 8 } else { 7 - king_sq / 8 };
+
+        // Pawn shield: twice the bonus right in front of the king, once a
+        // rank further, and a penalty for every hole
+        let shield = PAWN_SHIELD[color][king_sq as usize];
+        let near = shield & KING_ZONE[king_sq as usize];
+        let mut safety = self.params.pawn_shield_bonus * (2 * count_bits(pawns & near) + count_bits(pawns & shield & !near)) as i32
+            - self.params.pawn_shield_bonus / 2 * count_bits(shield & !pawns) as i32;
+
+        // Open files near king
+        let enemy_heavies = board.pieces[them as usize][Piece::Rook as usize] | board.pieces[them as usize][Piece::Queen as usize];
+        for f in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+            let file_mask = FILE_A << f;
+            if (pawns & file_mask) == 0 {
+                safety -= self.params.open_file_near_king;
+                // Extra penalty if enemy rooks/queens on the file
+                if (enemy_heavies & file_mask) != 0 {
+                    safety -= self.params.open_file_near_king;
                 }
             }
+        }
 
-            // Attack pattern recognition
-            let attackers = Self::count_king_zone_attackers(board, king_sq, Color::Black);
-            if attackers > 0 {
-                let attack_index = (attackers as usize).min(5);
-                safety -= KING_ATTACK_WEIGHT[attack_index];
-            }
+        // Attack pattern recognition
+        let units = Self::king_attack_units(board, king_sq, them) as usize;
+        safety -= self.params.king_attack_weight[units.min(KING_ATTACK_WEIGHT.len() - 1)];
 
-            // Penalty for king in center during middlegame
-            if phase > 18 && king_file >= 2 && king_file <= 5 && king_rank <= 2 {
-                safety -= 30;
-            }
+        // Penalty for king in center during middlegame
+        if phase > 18 && (2..=5).contains(&king_file) && relative_rank <= 2 {
+            safety -= 30;
+        }
 
-            // Castling rights bonus
-            if board.castling_rights & 3 != 0 {
-                safety += CASTLING_RIGHTS_BONUS;
-            }
+        // Castling rights bonus
+        if board.castling_rights & (3 << (2 * color)) != 0 {
+            safety += self.params.castling_rights_bonus;
+        }
 
-            score += (safety * phase) / 24;
-        }
-
-        // Black king safety
-        if let Some(king_sq) = board.get_king_square(Color::Black) {
-            let mut safety = 0;
-            let king_file = king_sq % 8;
-            let king_rank = king_sq / 8;
-
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    for dr in -2..=-1 {
-                        let r = king_rank as i8 + dr;
-                        if r >= 0 {
-                            let sq = (r * 8 + f) as u8;
-                            if get_bit(black_pawns, sq) {
-                                safety += PAWN_SHIELD_BONUS * (3 + dr as i32);
-                            } else {
-                                safety -= PAWN_SHIELD_BONUS / 2;
-                            }
-                        }
-                    }
-                }
-            }
+        (safety * phase) / 24
+    }
 
-            for df in -1..=1 {
-                let f = king_file as i8 + df;
-                if f >= 0 && f < 8 {
-                    let file_mask = FILE_A << f;
-                    if (black_pawns & file_mask) == 0 {
-                        safety -= OPEN_FILE_NEAR_KING;
-                        if (board.pieces[0][Piece::Rook as usize] & file_mask) != 0 ||
-                            (board.pieces[0][Piece::Queen as usize] & file_mask) != 0 {
-                            safety -= OPEN_FILE_NEAR_KING;
-                        }
-                    }
-                }
-            }
+    fn king_tropism(&self, board: &BoardState, phase: i32) -> i32 {
+        let (white_tropism, black_tropism) = self.king_tropism_by_side(board);
+        ((white_tropism - black_tropism) * phase) / 24
+    }
 
-            let attackers = Self::count_king_zone_attackers(board, king_sq, Color::White);
-            if attackers > 0 {
-                let attack_index = (attackers as usize).min(5);
-                safety -= KING_ATTACK_WEIGHT[attack_index];
-            }
+    // Knights to queens, each rewarded for standing near the enemy king
+    fn king_tropism_by_side(&self, board: &BoardState) -> (i32, i32) {
+        let mut tropism = [0; 2];
 
-            if phase > 18 && king_file >= 2 && king_file <= 5 && king_rank >= 5 {
-                safety -= 30;
-            }
+        for (color, enemy) in [(0, Color::Black), (1, Color::White)] {
+            let Some(king_sq) = board.get_king_square(enemy) else { continue };
 
-            if board.castling_rights & 12 != 0 {
-                safety += CASTLING_RIGHTS_BONUS;
+            for piece_type in 2..=5 {  // Knight to Queen
+                let mut pieces = board.pieces[color][piece_type];
+                while pieces != 0 {
+                    let (new_bb, sq) = pop_lsb(pieces);
+                    pieces = new_bb;
+                    tropism[color] += self.params.king_tropism * (7 - distance(sq.unwrap(), king_sq) as i32);
+                }
             }
-
-            score -= (safety * phase) / 24;
         }
 
-        score
+        (tropism[0], tropism[1])
     }
 }
 
+// Files plus ranks away from the four centre squares: 0 in the centre, 6 in a corner
+fn center_distance(square: u8) -> i32 {
+    let file = (square % 8) as i32;
+    let rank = (square / 8) as i32;
+    (3 - file).max(file - 4) + (3 - rank).max(rank - 4)
+}
+
 // Helper function moved outside impl block
 fn build_attack_map(board: &BoardState, color: usize, tables: &AttackTables) -> Bitboard {
     let mut attacks = 0u64;
@@ -1780,4 +2595,661 @@ fn build_attack_map(board: &BoardState, color: usize, tables: &AttackTables) ->
     }
     
     attacks
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::parse_square;
+    use crate::movegen::{CAPTURE, QUIET_MOVE};
+
+    fn capture(from: &str, to: &str) -> Move {
+        Move::new(parse_square(from).unwrap(), parse_square(to).unwrap(), CAPTURE)
+    }
+
+    #[test]
+    fn test_see_undefended_pawn() {
+        let board = BoardState::from_fen("4k3/8/8/3p4/8/1B6/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::see(&board, &capture("b3", "d5")), 100);
+    }
+
+    #[test]
+    fn test_see_pawn_defended_by_rook() {
+        // Bxd5 Rxd5 loses the bishop for a pawn
+        let board = BoardState::from_fen("3rk3/8/8/3p4/8/1B6/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::see(&board, &capture("b3", "d5")), 100 - 330);
+    }
+
+    #[test]
+    fn test_see_xray_behind_rook() {
+        // Rxe5 Rxe5 Rxe5: the second white rook recaptures through the first
+        let board = BoardState::from_fen("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::see(&board, &capture("e2", "e5")), 100);
+
+        // Without the back rook the exchange loses the exchange
+        let board = BoardState::from_fen("4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::see(&board, &capture("e2", "e5")), 100 - 500);
+    }
+
+    #[test]
+    fn test_see_classic_knight_exchange() {
+        // Nxe5 with the pawn defended by a knight and backed by a queen x-ray
+        let board = BoardState::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::see(&board, &capture("d3", "e5")), 100 - 320);
+    }
+
+    #[test]
+    fn test_absolute_pin_detection() {
+        // Nc3 pinned by Bb4 against Ke1; Nf3 is free
+        let board = BoardState::from_fen("4k3/8/8/8/1b6/2N2N2/8/4K3 w - - 0 1").unwrap();
+        let tables = &ATTACK_TABLES;
+        assert!(Evaluator::detect_pin_type(&board, parse_square("c3").unwrap(), 0, tables) == PinType::Absolute);
+        assert!(Evaluator::detect_pin_type(&board, parse_square("f3").unwrap(), 0, tables) == PinType::None);
+    }
+
+    #[test]
+    fn test_backward_pawn() {
+        // d3 trails its c4/e4 neighbours and c5 controls d4
+        let board = BoardState::from_fen("4k3/8/8/2p5/2P1P3/3P4/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::new(&EvalParams::default()).backward_and_candidate_pawns(&board), -BACKWARD_PAWN);
+    }
+
+    #[test]
+    fn test_candidate_passed_pawn() {
+        // c4 has an open file and b3 to trade off the b6 sentry
+        let board = BoardState::from_fen("4k3/8/1p6/8/2P5/1P6/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::new(&EvalParams::default()).backward_and_candidate_pawns(&board), CANDIDATE_PASSED[3]);
+
+        // Mirrored for black
+        let board = BoardState::from_fen("4k3/8/1p6/2p5/8/1P6/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(Evaluator::new(&EvalParams::default()).backward_and_candidate_pawns(&board), -CANDIDATE_PASSED[3]);
+    }
+
+    #[test]
+    fn test_pawn_value_param() {
+        // Rooks, and one extra white pawn: enough on the board for none of
+        // the endgame shortcuts or scale factors to apply
+        let board = BoardState::from_fen("r3k3/5pp1/8/8/8/8/4PPP1/R3K3 w - - 0 1").unwrap();
+        let params = EvalParams::default();
+        let base = Evaluator::new(&params).evaluate(&board);
+
+        let doubled = EvalParams { pawn_value: params.pawn_value * 2, ..EvalParams::default() };
+        let score = Evaluator::new(&doubled).evaluate(&board);
+        assert_eq!(score - base, params.pawn_value);
+    }
+
+    #[test]
+    fn test_set_param_by_name() {
+        let mut params = EvalParams::default();
+        assert!(params.set("Rook_Open_File", 40));
+        assert_eq!(params.rook_open_file, 40);
+        assert!(!params.set("pawn_pst_mg", 1));
+        assert!(!params.set("no_such_weight", 1));
+    }
+
+    #[test]
+    fn test_kpk_key_square_win() {
+        // White king on a key square wins whoever moves
+        let board = BoardState::from_fen("3k4/8/3K4/8/3P4/8/8/8 b - - 0 1").unwrap();
+        assert!(Evaluator::new(&EvalParams::default()).evaluate(&board) < -KNOWN_WIN);
+
+        let board = BoardState::from_fen("8/8/8/3p4/8/3k4/8/3K4 b - - 0 1").unwrap();
+        assert!(Evaluator::new(&EvalParams::default()).evaluate(&board) > KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_kpk_win_prefers_queening() {
+        let evaluator_params = EvalParams::default();
+        let evaluator = Evaluator::new(&evaluator_params);
+        let pawn = BoardState::from_fen("8/4P3/8/4K3/8/8/8/k7 w - - 0 1").unwrap();
+        let queen = BoardState::from_fen("4Q3/8/8/4K3/8/8/8/k7 b - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&pawn) > KNOWN_WIN);
+        assert!(-evaluator.evaluate(&queen) > evaluator.evaluate(&pawn));
+    }
+
+    #[test]
+    fn test_kpk_rook_pawn_draw() {
+        let board = BoardState::from_fen("k7/8/K7/P7/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::new(&EvalParams::default()).evaluate(&board), 0);
+    }
+
+    #[test]
+    fn test_philidor_position_is_drawn() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // Rook on the sixth keeps the white king off it
+        let board = BoardState::from_fen("4k3/R7/7r/3KP3/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), Some(0));
+        assert_eq!(evaluator.evaluate(&board), 0);
+
+        // Same for black, with the board flipped
+        let board = BoardState::from_fen("8/8/8/8/3kp3/7R/r7/4K3 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), Some(0));
+
+        // No longer once the pawn has reached the sixth
+        let board = BoardState::from_fen("4k3/R7/4P2r/3K4/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), None);
+        // Or with the rook passive on the back rank
+        let board = BoardState::from_fen("4k2r/R7/8/3KP3/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), None);
+    }
+
+    #[test]
+    fn test_wrong_bishop_rook_pawn_is_drawn() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // Dark-squared bishop can't drive the king out of a8
+        let board = BoardState::from_fen("1k6/8/8/P1B5/2K5/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), Some(0));
+        assert_eq!(evaluator.evaluate(&board), 0);
+
+        // The light-squared bishop covers the corner
+        let board = BoardState::from_fen("1k6/8/8/P2B4/2K5/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), None);
+        assert!(evaluator.evaluate(&board) > 200);
+
+        // King still far from the corner
+        let board = BoardState::from_fen("8/8/8/P1B5/2K5/8/6k1/8 w - - 0 1").unwrap();
+        assert_eq!(Evaluator::known_draws(&board), None);
+    }
+
+    #[test]
+    fn test_game_phase_bounds() {
+        assert_eq!(Evaluator::game_phase(&BoardState::default()), 24);
+
+        // Two extra queens for white can't lift the phase past the start
+        let board = BoardState::from_fen("rnbqkbnr/8/8/8/8/QQ6/8/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(Evaluator::game_phase(&board), 24);
+
+        // White's promotions don't stand in for black's missing pieces
+        let board = BoardState::from_fen("4k3/8/8/8/8/QQ6/8/RNBQKBNR w KQ - 0 1").unwrap();
+        assert_eq!(Evaluator::game_phase(&board), 12);
+
+        // Queens promoted after the pieces came off still count
+        let board = BoardState::from_fen("3qk3/8/8/8/8/8/8/2QQK3 w - - 0 1").unwrap();
+        let phase = Evaluator::game_phase(&board);
+        assert!((0..=24).contains(&phase));
+        assert_eq!(phase, 12);
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_scale_toward_draw() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // A pawn up with bishops on opposite colors
+        let ocb = BoardState::from_fen("4k3/5p2/4b3/8/8/8/3BPP2/4K3 w - - 0 1").unwrap();
+        let ocb_score = evaluator.evaluate(&ocb);
+        assert!(ocb_score.abs() < params.pawn_value);
+
+        // Same structure with bishops on the same color keeps its edge
+        let same = BoardState::from_fen("4k3/5p2/3b4/8/8/8/3BPP2/4K3 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&same) > ocb_score * 2);
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_middlegame_not_scaled() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // A pawn up, bishops on opposite colors, but queens, rooks and
+        // knights still on: the middlegame score stands, near enough
+        let board =
+            BoardState::from_fen("r2qk2r/pp1n1ppp/2pb4/8/3P4/2N5/PPPQBPP1/R3K2R w KQkq - 0 1").unwrap();
+        let trace = evaluator.evaluate_trace(&board);
+        let scaling = trace.terms.iter().find(|term| term.name == "Endgame scaling").unwrap().total;
+        let unscaled: i32 = trace.terms.iter().map(|term| term.total).sum::<i32>() - scaling;
+        assert!(unscaled != 0);
+        assert!(scaling.abs() * 10 < unscaled.abs(), "{} of {}", scaling, unscaled);
+    }
+
+    #[test]
+    fn test_scaling_picks_strong_side_by_material() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // White is a pawn up with two pawns left: hard to convert, whichever
+        // way the score leans
+        let board = BoardState::from_fen("r3k3/4p3/8/8/8/8/3PP3/R3K3 w - - 0 1").unwrap();
+        assert_eq!(evaluator.scale_factor(&board, 50), 36 + 7 * 2);
+        assert_eq!(evaluator.scale_factor(&board, -50), 36 + 7 * 2);
+
+        // Two pawns up is enough, even if black's position is the better one
+        let board = BoardState::from_fen("r3k3/8/8/8/8/8/3PP3/R3K3 w - - 0 1").unwrap();
+        assert_eq!(evaluator.scale_factor(&board, -50), SCALE_NORMAL);
+    }
+
+    #[test]
+    fn test_pawn_hash_shared_across_pieces() {
+        let params = EvalParams::default();
+        let table = PawnTable::new();
+        let evaluator = Evaluator::new(&params).with_pawn_table(&table);
+
+        // Same pawns, different pieces and kings
+        let first = BoardState::from_fen("4k3/pp3p2/2p5/3P4/8/8/PP3PPP/4K3 w - - 0 1").unwrap();
+        let second = BoardState::from_fen("r2qk2r/pp3p2/2p5/3P4/8/2N5/PP3PPP/R3KB2 b - - 0 1").unwrap();
+        assert_eq!(first.pawn_hash, second.pawn_hash);
+
+        let entry = evaluator.pawn_entry(&first);
+        let hit = table.probe(second.pawn_hash).expect("pawn hash hit");
+        assert_eq!(hit.score, entry.score);
+        assert_eq!(evaluator.pawn_entry(&second).score, Evaluator::new(&params).pawn_entry(&second).score);
+    }
+
+    #[test]
+    fn test_eval_trace_sums_to_evaluate() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "4k3/5p2/4b3/8/8/8/3BPP2/4K3 w - - 0 1",
+            "3k4/8/3K4/8/3P4/8/8/8 b - - 0 1",
+        ] {
+            let board = BoardState::from_fen(fen).unwrap();
+            let trace = evaluator.evaluate_trace(&board);
+            let white_score: i32 = trace.terms.iter().map(|term| term.total).sum();
+            let expected = evaluator.evaluate(&board);
+
+            assert_eq!(trace.score, expected, "{}", fen);
+            let expected_white = if board.side_to_move == Color::Black { -expected } else { expected };
+            assert_eq!(white_score, expected_white, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_pinned_piece_mobility() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // Knight pinned on the e-file has no legal moves at all
+        let board = BoardState::from_fen("4k3/8/8/8/4r3/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluator.mobility_by_side(&board).0, 0);
+
+        // Bishop pinned on the diagonal keeps only the capture of the pinner,
+        // since c3 is covered by the pinning bishop
+        let board = BoardState::from_fen("4k3/8/8/8/1b6/8/3B4/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluator.mobility_by_side(&board).0, params.bishop_mobility);
+    }
+
+    #[test]
+    fn test_basic_mate_drives_king_to_corner() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let score = |fen: &str| evaluator.evaluate(&BoardState::from_fen(fen).unwrap());
+
+        // Defending king walked from the centre toward a1, attacker fixed
+        let pushed = [
+            "7R/8/8/8/3k4/8/8/7K w - - 0 1",
+            "7R/8/8/8/8/2k5/8/7K w - - 0 1",
+            "7R/8/8/8/8/8/1k6/7K w - - 0 1",
+            "7R/8/8/8/8/8/8/k6K w - - 0 1",
+        ];
+        for pair in pushed.windows(2) {
+            assert!(score(pair[1]) > score(pair[0]), "{} vs {}", pair[1], pair[0]);
+        }
+
+        // Attacking king walking toward the cornered king
+        let approach = [
+            "7R/8/8/8/8/8/8/k6K w - - 0 1",
+            "7R/8/8/8/8/8/6K1/k7 w - - 0 1",
+            "7R/8/8/8/8/5K2/8/k7 w - - 0 1",
+            "7R/8/8/8/8/3K4/8/k7 w - - 0 1",
+        ];
+        for pair in approach.windows(2) {
+            assert!(score(pair[1]) > score(pair[0]), "{} vs {}", pair[1], pair[0]);
+        }
+
+        // Mirrored for black, from the side to move
+        assert!(score("r6k/8/8/8/8/8/8/K7 b - - 0 1") > KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_queen_weighs_more_on_king_zone() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let king_sq = parse_square("g8").unwrap();
+
+        // Same king, then a queen hitting f7 and h7 or a knight hitting them
+        let base = BoardState::from_fen("r2q1rk1/ppp2ppp/8/8/8/8/PPP2PPP/R2Q1RK1 w - - 0 1").unwrap();
+        let queen = BoardState::from_fen("r2q1rk1/ppp2ppp/8/7Q/8/8/PPP2PPP/R2Q1RK1 w - - 0 1").unwrap();
+        let knight = BoardState::from_fen("r2q1rk1/ppp2ppp/8/6N1/8/8/PPP2PPP/R2Q1RK1 w - - 0 1").unwrap();
+
+        assert_eq!(Evaluator::king_attack_units(&base, king_sq, Color::White), 0);
+        assert_eq!(Evaluator::king_attack_units(&queen, king_sq, Color::White), 10);
+        assert_eq!(Evaluator::king_attack_units(&knight, king_sq, Color::White), 4);
+
+        let black_safety = |board: &BoardState| evaluator.king_safety_by_side(board, 24).1;
+        let base_safety = black_safety(&base);
+        assert!(base_safety - black_safety(&queen) > base_safety - black_safety(&knight));
+        assert!(black_safety(&knight) < base_safety);
+    }
+
+    #[test]
+    fn test_trapped_bishop() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let trapped = |fen: &str| evaluator.trapped_bishops(&BoardState::from_fen(fen).unwrap());
+
+        // Bxa7 ...b6 and Bxh7 ...g6 for white
+        assert_eq!(trapped("4k3/B1p5/1p6/8/8/8/8/4K3 b - - 0 1"), -TRAPPED_BISHOP);
+        assert_eq!(trapped("4k3/5p1B/6p1/8/8/8/8/4K3 b - - 0 1"), -TRAPPED_BISHOP);
+
+        // Mirrored for black on a2 and h2
+        assert_eq!(trapped("4k3/8/8/8/8/1P6/b1P5/4K3 w - - 0 1"), TRAPPED_BISHOP);
+        assert_eq!(trapped("4k3/8/8/8/8/6P1/5P1b/4K3 w - - 0 1"), TRAPPED_BISHOP);
+
+        // Before ...b6 the bishop still gets out via b6
+        assert_eq!(trapped("4k3/B1p5/8/1p6/8/8/8/4K3 b - - 0 1"), 0);
+    }
+
+    #[test]
+    fn test_rook_behind_passed_pawn() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let rooks = |fen: &str| evaluator.rook_evaluation(&BoardState::from_fen(fen).unwrap());
+
+        // The c6 pawn stops d5 from being passed; moved to c2 it no longer does
+        let blocked = rooks("4k3/8/2p5/3P4/8/8/8/3RK3 w - - 0 1");
+        let passed = rooks("4k3/8/8/3P4/8/8/2p5/3RK3 w - - 0 1");
+        assert_eq!(passed - blocked, ROOK_BEHIND_PASSER);
+
+        // A rook in front of the enemy passer counts too, and a piece in
+        // between cancels it
+        assert_eq!(rooks("4k3/8/8/8/3p4/8/8/3RK3 w - - 0 1") - rooks("4k3/8/8/8/3p4/8/3N4/3RK3 w - - 0 1"), ROOK_BEHIND_PASSER);
+
+        // Black's rook behind its own passer
+        assert_eq!(rooks("3rk3/8/8/8/3p4/8/8/4K3 b - - 0 1") - rooks("3rk3/8/8/8/3p4/4P3/8/4K3 b - - 0 1"), -ROOK_BEHIND_PASSER);
+    }
+
+    #[test]
+    fn test_doubled_rooks() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let rooks = |fen: &str| evaluator.rook_evaluation(&BoardState::from_fen(fen).unwrap());
+
+        assert_eq!(rooks("4k3/8/8/8/8/8/3R4/3RK3 w - - 0 1") - rooks("4k3/8/8/8/8/8/1R6/3RK3 w - - 0 1"), DOUBLED_ROOKS);
+        assert_eq!(rooks("2r1k3/2r5/8/8/8/8/8/4K3 b - - 0 1") - rooks("r3k3/2r5/8/8/8/8/8/4K3 b - - 0 1"), -DOUBLED_ROOKS);
+
+        // Our own pawn on the file spoils it
+        let with_pawn = rooks("4k3/8/8/3P4/8/8/3R4/3RK3 w - - 0 1");
+        let split = rooks("4k3/8/8/3P4/8/8/1R6/3RK3 w - - 0 1");
+        assert!(with_pawn - split < DOUBLED_ROOKS);
+    }
+
+    #[test]
+    fn test_king_tropism() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let tropism = |fen: &str| evaluator.king_tropism_by_side(&BoardState::from_fen(fen).unwrap()).0;
+
+        // Qd1-d4-f6 closes in on the g8 king
+        let home = tropism("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1");
+        let center = tropism("6k1/5ppp/8/8/3Q4/8/5PPP/6K1 w - - 0 1");
+        let close = tropism("6k1/5ppp/5Q2/8/8/8/5PPP/6K1 w - - 0 1");
+        assert!(home < center && center < close);
+        assert_eq!(close - home, 5 * KING_TROPISM);
+
+        // Middlegame-heavy: nothing left once the phase runs out
+        let board = BoardState::from_fen("6k1/5ppp/5Q2/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert!(evaluator.king_tropism(&board, 24) > 0);
+        assert_eq!(evaluator.king_tropism(&board, 0), 0);
+    }
+
+    #[test]
+    fn test_incremental_material_and_pst() {
+        let params = EvalParams::default();
+        assert!(params.has_builtin_psq());
+        let evaluator = Evaluator::new(&params);
+
+        // Both castles, a promotion, a capturing promotion and recaptures
+        let mut board = BoardState::from_fen("r3k2r/6P1/8/8/8/8/1p6/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!((board.mg_material_pst, board.eg_material_pst), evaluator.material_and_pst(&board));
+
+        for uci in ["e1g1", "e8c8", "g7h8q", "b2a1q", "h8d8", "c8d8"] {
+            assert!(board.make_move_uci(uci).unwrap(), "{}", uci);
+            assert_eq!((board.mg_material_pst, board.eg_material_pst), evaluator.material_and_pst(&board), "after {}", uci);
+        }
+
+        let mut tuned = EvalParams::default();
+        tuned.knight_pst_mg[0] += 1;
+        assert!(!tuned.has_builtin_psq());
+    }
+
+    #[test]
+    fn test_imbalance_bishop_pair() {
+        let params = EvalParams::default();
+        let board = BoardState::from_fen("2b1kn2/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").unwrap();
+        assert!(Evaluator::new(&params).imbalance(&board) > 0);
+    }
+
+    #[test]
+    fn test_imbalance_knight_gains_with_pawns() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        let few = BoardState::from_fen("4k3/pppppppp/8/8/8/8/PPP5/4KN2 w - - 0 1").unwrap();
+        let many = BoardState::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPP1/4KN2 w - - 0 1").unwrap();
+        assert!(evaluator.imbalance_side(&many, 0) > evaluator.imbalance_side(&few, 0));
+    }
+
+    #[test]
+    fn test_see_quiet_move_is_zero() {
+        let board = BoardState::default();
+        let mv = Move::new(parse_square("g1").unwrap(), parse_square("f3").unwrap(), QUIET_MOVE);
+        assert_eq!(Evaluator::see(&board, &mv), 0);
+    }
+
+    #[test]
+    fn test_wdl() {
+        let middlegame = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let [win, draw, loss] = Evaluator::wdl(300, &middlegame);
+        assert!(win > draw && draw > loss, "{} {} {}", win, draw, loss);
+        assert_eq!(win + draw + loss, 1000);
+
+        // Level positions are mostly drawn, and the chances are symmetric
+        let [win, draw, loss] = Evaluator::wdl(0, &BoardState::default());
+        assert!(draw > win && win == loss);
+        assert_eq!(Evaluator::wdl(-300, &middlegame), {
+            let [w, d, l] = Evaluator::wdl(300, &middlegame);
+            [l, d, w]
+        });
+
+        // A pawn up means more without pieces on the board
+        let ending = BoardState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(Evaluator::wdl(100, &ending)[0] > Evaluator::wdl(100, &middlegame)[0]);
+    }
+
+    #[test]
+    fn test_startpos_is_tempo_only() {
+        // Symmetric position: only the side to move's tempo separates them
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let white = evaluator.evaluate(&BoardState::default());
+        let black = evaluator.evaluate(&BoardState::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap());
+
+        assert!(white.abs() <= 2 * TEMPO_BONUS, "startpos eval {}", white);
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn test_eval_is_colour_symmetric() {
+        use crate::movegen::MoveGenerator;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        // Scores are from the side to move, so a board and its mirror image
+        // must score the same
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        // Fianchettoed bishops, knight outposts, a pin and a fork first
+        for fen in [
+            "rnbqk2r/ppppppbp/5np1/8/8/5NP1/PPPPPPBP/RNBQK2R w KQkq - 2 4",
+            "r1bq1rk1/pp3ppp/2n2n2/2bNp3/4P3/3P4/PPP2PPP/R1BQKB1R b KQ - 0 8",
+            "4k3/8/2n5/8/8/4B3/8/R3K2q w Q - 0 1",
+            "r3k3/8/8/1N6/8/8/8/4K3 w q - 0 1",
+        ] {
+            let board = BoardState::from_fen(fen).unwrap();
+            assert_eq!(evaluator.evaluate(&board), evaluator.evaluate(&board.mirrored()), "{}", fen);
+        }
+
+        let mut rng = StdRng::seed_from_u64(2351);
+        let mut board = BoardState::default();
+
+        for _ in 0..3000 {
+            let moves = MoveGenerator::generate_legal_moves(&board);
+            if moves.is_empty() || board.halfmove_clock >= 100 {
+                board = BoardState::default();
+                continue;
+            }
+            board.make_move(&moves[rng.gen_range(0..moves.len())]);
+
+            let mirrored = board.mirrored();
+            assert_eq!(evaluator.evaluate(&board), evaluator.evaluate(&mirrored), "{} vs {}", board.to_fen(), mirrored.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_fianchetto_bonus_needs_bishop_on_the_long_diagonal() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+
+        // Bg2 behind g3 with f2/h2, against an undeveloped black bishop
+        let board = BoardState::from_fen("rnbqk1nr/pppppppp/8/8/8/6P1/PPPPPPBP/RNBQK1NR w KQkq - 0 3").unwrap();
+        assert_eq!(evaluator.fianchetto_patterns(&board), FIANCHETTO_BONUS);
+        assert_eq!(evaluator.fianchetto_patterns(&board.mirrored()), -FIANCHETTO_BONUS);
+
+        // Both sides fianchetto on both wings
+        let board = BoardState::from_fen("rn1qk1nr/pbppppbp/1p4p1/8/8/1P4P1/PBPPPPBP/RN1QK1NR w KQkq - 0 5").unwrap();
+        assert_eq!(evaluator.fianchetto_patterns(&board), 0);
+
+        // A bishop on g1 behind f2/g2/h2, or on g2 without g3, earns nothing
+        let board = BoardState::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKRB1 w Qkq - 0 1").unwrap();
+        assert_eq!(evaluator.fianchetto_patterns(&board), 0);
+        let board = BoardState::from_fen("rnbqkbnr/pppppppp/8/8/6P1/8/PPPPPPBP/RNBQK1NR w KQkq - 0 3").unwrap();
+        assert_eq!(evaluator.fianchetto_patterns(&board), 0);
+    }
+
+    #[test]
+    fn test_edge_passer_protection() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let passers = |fen: &str| evaluator.passed_pawns(&BoardState::from_fen(fen).unwrap()).0;
+        let protected = PASSED_PAWN_BONUS[4] + PROTECTED_PASSED_PAWN[4];
+
+        // a5 is guarded from b4; with the pawn on b6 instead, a5 guards it
+        assert_eq!(passers("4k3/8/8/P7/1P6/8/8/4K3 w - - 0 1"), protected + PASSED_PAWN_BONUS[3]);
+        assert_eq!(
+            passers("4k3/8/1P6/P7/8/8/8/4K3 w - - 0 1"),
+            PASSED_PAWN_BONUS[4] + PASSED_PAWN_BONUS[5] + PROTECTED_PASSED_PAWN[5]
+        );
+        // Same for h5 and g4, while h3 (a5's index minus 9) is no guard
+        assert_eq!(passers("4k3/8/8/7P/6P1/8/8/4K3 w - - 0 1"), protected + PASSED_PAWN_BONUS[3]);
+        assert_eq!(passers("4k3/8/8/P7/8/7P/8/4K3 w - - 0 1"), PASSED_PAWN_BONUS[4] + PASSED_PAWN_BONUS[2]);
+
+        // Black's edge passers mirror white's
+        for fen in ["4k3/8/8/P7/1P6/8/8/4K3 w - - 0 1", "4k3/8/8/7P/6P1/8/8/4K3 w - - 0 1"] {
+            let board = BoardState::from_fen(fen).unwrap();
+            assert_eq!(evaluator.passed_pawns(&board.mirrored()).0, -passers(fen));
+        }
+        // Low squares: a black passer on a2 and one on h2 don't underflow
+        assert_eq!(passers("4k3/8/8/8/8/8/p6p/4K3 b - - 0 1"), -2 * PASSED_PAWN_BONUS[6]);
+    }
+
+    #[test]
+    fn test_connected_passers() {
+        let params = EvalParams::default();
+        let evaluator = Evaluator::new(&params);
+        let connected = |fen: &str| {
+            let board = BoardState::from_fen(fen).unwrap();
+            evaluator.connected_passers(evaluator.passed_pawns(&board).1)
+        };
+
+        // d5/e5 side by side and d5/e4 one rank apart both count, for each pawn
+        assert_eq!(connected("4k3/8/8/3PP3/8/8/8/4K3 w - - 0 1"), 2 * CONNECTED_PASSER[4]);
+        assert_eq!(connected("4k3/8/8/3P4/4P3/8/8/4K3 w - - 0 1"), CONNECTED_PASSER[4] + CONNECTED_PASSER[3]);
+        // A file apart, two ranks apart, or one of them blocked from passing
+        assert_eq!(connected("4k3/8/8/2P1P3/8/8/8/4K3 w - - 0 1"), 0);
+        assert_eq!(connected("4k3/8/8/3P4/8/4P3/8/4K3 w - - 0 1"), 0);
+        assert_eq!(connected("4k3/3p4/8/3PP3/8/8/8/4K3 w - - 0 1"), 0);
+
+        let board = BoardState::from_fen("4k3/8/8/3P4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let mirrored = board.mirrored();
+        assert_eq!(
+            evaluator.connected_passers(evaluator.passed_pawns(&mirrored).1),
+            -(CONNECTED_PASSER[4] + CONNECTED_PASSER[3])
+        );
+    }
+
+    #[test]
+    fn test_outside_passer() {
+        let params = EvalParams::default();
+        let mut without = EvalParams::default();
+        without.outside_passer = 0;
+        let bonus = |fen: &str, phase: i32| {
+            let board = BoardState::from_fen(fen).unwrap();
+            let passed = Evaluator::new(&params).passed_pawns(&board).1;
+            Evaluator::new(&params).passed_pawn_king_distance(&board, passed, phase)
+                - Evaluator::new(&without).passed_pawn_king_distance(&board, passed, phase)
+        };
+
+        // An a-pawn six files from the black king on g6: four files beyond two
+        let ending = "8/8/6k1/8/P7/8/8/4K3 w - - 0 1";
+        assert_eq!(bonus(ending, 0), 4 * OUTSIDE_PASSER);
+        assert_eq!(bonus(ending, 12), 2 * OUTSIDE_PASSER);
+        assert_eq!(bonus(ending, 24), 0);
+        // An e-pawn two files from the king is not outside
+        assert_eq!(bonus("8/8/6k1/8/4P3/8/8/4K3 w - - 0 1", 0), 0);
+
+        let board = BoardState::from_fen(ending).unwrap().mirrored();
+        let passed = Evaluator::new(&params).passed_pawns(&board).1;
+        assert_eq!(
+            Evaluator::new(&params).passed_pawn_king_distance(&board, passed, 0)
+                - Evaluator::new(&without).passed_pawn_king_distance(&board, passed, 0),
+            -4 * OUTSIDE_PASSER
+        );
+    }
+
+    #[test]
+    fn test_king_safety_counted_once() {
+        // White castled behind an intact shield, black's king in the centre
+        // with the f-pawn gone
+        let board = BoardState::from_fen("r1bqk2r/ppppb1pp/2n2n2/4pp2/4P3/2NP1N2/PPP1BPPP/R1BQ1RK1 w kq - 0 7").unwrap();
+        let params = EvalParams::default();
+        let mut stronger = EvalParams::default();
+        stronger.pawn_shield_bonus += 10;
+        stronger.open_file_near_king += 10;
+
+        let evaluator = Evaluator::new(&params);
+        let boosted = Evaluator::new(&stronger);
+        let phase = Evaluator::game_phase(&board);
+        let safety_change = boosted.king_safety_advanced(&board, phase) - evaluator.king_safety_advanced(&board, phase);
+        assert_ne!(safety_change, 0);
+        assert_eq!(boosted.evaluate(&board) - evaluator.evaluate(&board), safety_change);
+
+        // The shield masks: f2, g2, h2 and f3, g3, h3 for a king on g1
+        assert_eq!(PAWN_SHIELD[0][6], 0x0000_0000_00E0_E000);
+        assert_eq!(PAWN_SHIELD[1][62], PAWN_SHIELD[0][6].swap_bytes());
+        assert_eq!(KING_ZONE[0], 0x0303);
+    }
+
+    #[test]
+    fn test_pawn_chain_links() {
+        // White's c3-d4-e5 chain has two links, black's f7-e6 one; the
+        // lone a- and h-pawns don't wrap onto each other's files
+        let board = BoardState::from_fen("4k3/5p1p/4p3/4P3/3P4/P1P5/7P/4K3 w - - 0 1").unwrap();
+        let params = EvalParams::default();
+        assert_eq!(Evaluator::new(&params).pawn_chains(&board), PAWN_CHAIN_BONUS);
+        assert_eq!(Evaluator::new(&params).pawn_chains(&board.mirrored()), -PAWN_CHAIN_BONUS);
+
+        // The chain weight moves the whole eval by exactly that much, and
+        // nothing else reads it
+        let mut stronger = EvalParams::default();
+        stronger.pawn_chain_bonus += 10;
+        let base = Evaluator::new(&params).evaluate(&board);
+        assert_eq!(Evaluator::new(&stronger).evaluate(&board) - base, 10);
+    }
+}
@@ -2,14 +2,26 @@ use pyo3::prelude::*;
 
 mod board;
 mod bitboard;
+mod bitbase;
 mod movegen;
 mod search;
 mod eval;
 mod zobrist;
 mod opening_book;
+#[cfg(feature = "nnue")]
+mod nnue;
+#[cfg(feature = "syzygy")]
+mod tablebase;
+pub mod tuning;
 
-use board::BoardState;
-use search::SearchEngine;
+use board::{BoardState, GameResult};
+use search::{IterationInfo, SearchEngine, SearchInfo, UciInfo};
+use eval::Evaluator;
+use movegen::MoveGenerator;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 #[pymodule]
 fn chess_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -20,7 +32,63 @@ fn chess_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
 
 #[pyclass]
 struct PyChessEngine {
-    engine: SearchEngine,
+    // Locked for the length of a search, which runs without the GIL
+    engine: Mutex<SearchEngine>,
+    // Shared with the engine so stop() works while a search holds the lock
+    stop: Arc<AtomicBool>,
+    // Likewise for ponderhit()
+    ponder: Arc<AtomicBool>,
+    // Set while a search runs; a second search() is refused rather than
+    // left waiting on the lock
+    searching: AtomicBool,
+}
+
+// Hands each finished iteration to a Python callable as a dict. Root move
+// and string reports are dropped: they only matter to a UCI GUI.
+struct PyInfoCallback(PyObject);
+
+impl SearchInfo for PyInfoCallback {
+    fn on_iteration(&self, info: &IterationInfo) {
+        Python::with_gil(|py| {
+            let dict = pyo3::types::PyDict::new_bound(py);
+            let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci_for(info.root)).collect();
+            let items = [
+                dict.set_item("depth", info.depth),
+                dict.set_item("seldepth", info.seldepth),
+                dict.set_item("multipv", info.multipv),
+                dict.set_item("score", info.score),
+                dict.set_item("wdl", info.wdl),
+                dict.set_item("nodes", info.nodes),
+                dict.set_item("nps", info.nps),
+                dict.set_item("time_ms", info.time_ms),
+                dict.set_item("pv", pv),
+            ];
+
+            let result = items.into_iter().collect::<PyResult<()>>().and_then(|_| self.0.call1(py, (dict,)));
+            if let Err(e) = result {
+                e.print(py);
+            }
+        });
+    }
+
+    fn on_currmove(&self, _root: &BoardState, _depth: u8, _mv: movegen::Move, _move_number: usize) {}
+
+    fn on_string(&self, _msg: &str) {}
+}
+
+// What search() and analyse() hand back to Python; `board` is the searched
+// position, which decides how castling is written
+fn result_dict(py: Python<'_>, result: &search::SearchResult, board: &BoardState) -> PyResult<PyObject> {
+    let dict = pyo3::types::PyDict::new_bound(py);
+
+    let move_str = result.best_move.map(|m| m.to_uci_for(board)).unwrap_or_else(|| "none".to_string());
+    dict.set_item("move", move_str)?;
+    dict.set_item("score", result.score)?;
+    dict.set_item("nodes", result.nodes)?;
+    dict.set_item("thread_nodes", &result.thread_nodes)?;
+    dict.set_item("pv", result.pv.iter().map(|mv| mv.to_uci_for(board)).collect::<Vec<_>>())?;
+
+    Ok(dict.into())
 }
 
 #[pymethods]
@@ -28,62 +96,234 @@ impl PyChessEngine {
     #[new]
     #[pyo3(signature = (threads=None))]
     fn new(threads: Option<usize>) -> Self {
+        let engine = SearchEngine::new(threads.unwrap_or(4));
         PyChessEngine {
-            engine: SearchEngine::new(threads.unwrap_or(4)),
+            stop: engine.stop_handle(),
+            ponder: engine.ponder_handle(),
+            engine: Mutex::new(engine),
+            searching: AtomicBool::new(false),
         }
     }
 
-    #[pyo3(signature = (fen, depth=None, time_ms=None))]
+    /// With `infinite=True` depth and time are ignored and the search runs
+    /// until `stop()` is called from another thread. With `ponder=True` it
+    /// searches on the opponent's time: `time_ms` only starts counting at
+    /// `ponderhit()`, and it returns after that or `stop()`. `nodes` caps
+    /// the nodes searched, for repeatable results.
+    #[pyo3(signature = (fen, depth=None, time_ms=None, infinite=false, ponder=false, nodes=None))]
+    #[allow(clippy::too_many_arguments)]
     fn search(
-        &mut self,
+        &self,
         py: Python<'_>,
         fen: &str,
         depth: Option<u8>,
         time_ms: Option<u64>,
+        infinite: bool,
+        ponder: bool,
+        nodes: Option<u64>,
     ) -> PyResult<PyObject> {
         let board = BoardState::from_fen(fen)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?;
         
-        let result = self.engine.search(
-            board,
-            depth.unwrap_or(64),
-            time_ms,
-        );
+        if self.searching.swap(true, Ordering::Acquire) {
+            return Err(pyo3::exceptions::PyRuntimeError::new_err("a search is already running on this engine"));
+        }
 
-        let dict = pyo3::types::PyDict::new_bound(py);
-        
-        let move_str = result.best_move.map(|m| m.to_uci()).unwrap_or_else(|| "none".to_string());
-        dict.set_item("move", move_str)?;
-        dict.set_item("score", result.score)?;
-        dict.set_item("nodes", result.nodes)?;
-        
-        Ok(dict.into())
+        // Flagged before the search starts so an early ponderhit() isn't lost
+        if ponder {
+            self.ponder.store(true, Ordering::Relaxed);
+        }
+
+        // Other Python threads keep running and can call stop()
+        let result = py.allow_threads(|| {
+            let mut engine = self.engine.lock();
+            engine.set_node_limit(nodes);
+            if infinite {
+                engine.search_infinite(board.clone())
+            } else if ponder {
+                engine.search_ponder(board.clone(), depth.unwrap_or(64), time_ms)
+            } else {
+                engine.search(board.clone(), depth.unwrap_or(64), time_ms)
+            }
+        });
+        self.searching.store(false, Ordering::Release);
+
+        result_dict(py, &result, &board)
+    }
+
+    /// Searches to `depth` calling `callback(info)` after every completed
+    /// iteration, with the same dict as `set_info_callback`. The callback
+    /// only applies to this search; the engine's own reporting is restored
+    /// afterwards. Returns the same dict as `search()`.
+    #[pyo3(signature = (fen, depth, callback))]
+    fn analyse(&self, py: Python<'_>, fen: &str, depth: u8, callback: PyObject) -> PyResult<PyObject> {
+        let board = BoardState::from_fen(fen)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+
+        if self.searching.swap(true, Ordering::Acquire) {
+            return Err(pyo3::exceptions::PyRuntimeError::new_err("a search is already running on this engine"));
+        }
+
+        // The callback takes the GIL back for each report
+        let result = py.allow_threads(|| {
+            let mut engine = self.engine.lock();
+            let previous = engine.set_info(Arc::new(PyInfoCallback(callback)));
+            let result = engine.search(board.clone(), depth, None);
+            engine.set_info(previous);
+            result
+        });
+        self.searching.store(false, Ordering::Release);
+
+        result_dict(py, &result, &board)
     }
 
     fn new_game(&mut self) {
-        self.engine.new_game();
+        self.engine.get_mut().new_game();
+    }
+
+    fn threads(&self) -> usize {
+        self.engine.lock().threads()
     }
 
     fn set_threads(&mut self, threads: usize) {
-        self.engine.set_threads(threads);
+        self.engine.get_mut().set_threads(threads);
     }
     
     fn set_multi_pv(&mut self, count: usize) {
-        self.engine.set_multi_pv(count);
+        self.engine.get_mut().set_multi_pv(count);
     }
     
     fn set_hash_size(&mut self, size_mb: usize) {
-        self.engine.set_hash_size(size_mb);
+        self.engine.get_mut().set_hash_size(size_mb);
+    }
+
+    fn set_contempt(&mut self, contempt: i32) {
+        self.engine.get_mut().set_contempt(contempt);
+    }
+
+    fn set_deterministic(&mut self, deterministic: bool) {
+        self.engine.get_mut().set_deterministic(deterministic);
+    }
+
+    /// Calls `callback(info)` after every completed depth with a dict of
+    /// depth, seldepth, score, wdl, nodes, nps, time_ms and pv, in place of
+    /// the printed UCI lines. None goes back to printing.
+    #[pyo3(signature = (callback=None))]
+    fn set_info_callback(&mut self, callback: Option<PyObject>) {
+        let info: Arc<dyn SearchInfo> = match callback {
+            Some(callback) => Arc::new(PyInfoCallback(callback)),
+            None => Arc::new(UciInfo),
+        };
+        self.engine.get_mut().set_info(info);
+    }
+
+    fn set_show_wdl(&mut self, show_wdl: bool) {
+        self.engine.get_mut().set_show_wdl(show_wdl);
+    }
+
+    /// Overrides one search margin by its UCI option name, e.g.
+    /// `FutilityBase` or `AspirationWindow`.
+    fn set_search_param(&mut self, name: &str, value: i32) -> PyResult<()> {
+        if self.engine.get_mut().search_params_mut().set(name, value) {
+            Ok(())
+        } else {
+            Err(pyo3::exceptions::PyValueError::new_err(format!("unknown search parameter: {}", name)))
+        }
+    }
+
+    /// Sets an engine option by its UCI name, as `setoption name <name>
+    /// value <value>` would, e.g. `set_option("Contempt", "50")`. Raises
+    /// ValueError for unknown options and unparseable values.
+    fn set_option(&mut self, name: &str, value: &str) -> PyResult<()> {
+        self.engine
+            .get_mut()
+            .set_option(name, value)
+            .map(|_| ())
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Overrides one evaluation weight by its field name, e.g.
+    /// `rook_open_file`.
+    fn set_eval_param(&mut self, name: &str, value: i32) -> PyResult<()> {
+        if self.engine.get_mut().set_eval_param(name, value) {
+            Ok(())
+        } else {
+            Err(pyo3::exceptions::PyValueError::new_err(format!("unknown eval parameter: {}", name)))
+        }
+    }
+
+    fn set_own_book(&mut self, own_book: bool) {
+        self.engine.get_mut().set_own_book(own_book);
+    }
+
+    fn set_book_best_move(&mut self, book_best_move: bool) {
+        self.engine.get_mut().set_book_best_move(book_best_move);
+    }
+
+    /// Same seed, same book lines; 0 picks book moves unseeded again.
+    fn set_book_seed(&mut self, seed: u64) {
+        self.engine.get_mut().set_book_seed(seed);
+    }
+
+    fn set_analyse_mode(&mut self, analyse_mode: bool) {
+        self.engine.get_mut().set_analyse_mode(analyse_mode);
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// The opponent played the move being pondered on: the ponder search
+    /// becomes a normal one with its clock starting now.
+    fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    /// Loads a Polyglot .bin book, tried before the built-in one; an empty
+    /// path goes back to the built-in book only. Returns the entry count.
+    fn set_book_file(&mut self, path: &str) -> PyResult<usize> {
+        if path.is_empty() {
+            opening_book::unload_polyglot();
+            return Ok(0);
+        }
+        opening_book::load_polyglot(path).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
     }
 
-    fn stop(&mut self) {
-        self.engine.stop();
+    /// Static evaluation in centipawns from the side to move's point of
+    /// view, so positive means the player about to move is better.
+    fn evaluate(&self, fen: &str) -> PyResult<i32> {
+        BoardState::from_fen(fen)
+            .map(|board| Evaluator::new(self.engine.lock().eval_params()).evaluate(&board))
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// Term-by-term breakdown of the static evaluation, as printed by the
+    /// UCI `eval` command.
+    fn eval_trace(&self, fen: &str) -> PyResult<String> {
+        BoardState::from_fen(fen)
+            .map(|board| Evaluator::new(self.engine.lock().eval_params()).evaluate_trace(&board).to_string())
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// Loads an NNUE file for evaluation; an empty path goes back to the
+    /// handcrafted eval.
+    #[cfg(feature = "nnue")]
+    fn set_eval_file(&mut self, path: &str) -> PyResult<()> {
+        self.engine.get_mut().set_eval_file(path).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+    }
+
+    /// Points the engine at Syzygy files; returns how many tables were found.
+    #[cfg(feature = "syzygy")]
+    fn set_syzygy_path(&mut self, path: &str) -> usize {
+        tablebase::init(path)
     }
 }
 
 #[pyclass]
 struct PyBoardState {
     board: BoardState,
+    // Position before each move played, with the move, for pop()
+    undo_stack: Vec<(BoardState, String)>,
 }
 
 #[pymethods]
@@ -98,16 +338,55 @@ impl PyBoardState {
             BoardState::default()
         };
         
-        Ok(PyBoardState { board })
+        Ok(PyBoardState { board, undo_stack: Vec::new() })
     }
 
     fn to_fen(&self) -> String {
         self.board.to_fen()
     }
 
+    /// Plays `uci` if it is legal; false leaves the board unchanged. The
+    /// move can be taken back with pop().
     fn make_move(&mut self, uci: &str) -> PyResult<bool> {
-        self.board.make_move_uci(uci)
-            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))
+        let before = self.board.clone();
+        let played = self.board.make_move_uci(uci)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?;
+        if played {
+            self.undo_stack.push((before, uci.to_string()));
+        }
+        Ok(played)
+    }
+
+    /// Same as make_move, named to pair with pop().
+    fn push(&mut self, uci: &str) -> PyResult<bool> {
+        self.make_move(uci)
+    }
+
+    /// Takes back the last move and returns it; IndexError when no moves
+    /// have been played.
+    fn pop(&mut self) -> PyResult<String> {
+        let (board, uci) = self
+            .undo_stack
+            .pop()
+            .ok_or_else(|| pyo3::exceptions::PyIndexError::new_err("pop from empty move stack"))?;
+        self.board = board;
+        Ok(uci)
+    }
+
+    /// The engine's internal Zobrist key. Stable across runs but specific to
+    /// this engine; see polyglot_key() for the standard book key.
+    fn zobrist(&self) -> u64 {
+        self.board.zobrist_key()
+    }
+
+    /// Polyglot key of the position, for looking it up in `.bin` books.
+    fn polyglot_key(&self) -> u64 {
+        self.board.polyglot_key()
+    }
+
+    /// Moves played on this board so far, oldest first.
+    fn move_stack(&self) -> Vec<String> {
+        self.undo_stack.iter().map(|(_, uci)| uci.clone()).collect()
     }
 
     fn is_game_over(&self) -> bool {
@@ -117,4 +396,69 @@ impl PyBoardState {
     fn is_in_check(&self) -> bool {
         self.board.is_in_check(self.board.side_to_move)
     }
+
+    /// Whether either side may claim a draw under the fifty-move rule.
+    fn can_claim_fifty_move(&self) -> bool {
+        self.board.can_claim_fifty_move()
+    }
+
+    /// "ongoing", "white_wins" or "black_wins" (by checkmate), "stalemate",
+    /// "draw_seventy_five_move", "draw_repetition" or "draw_insufficient".
+    fn result(&self) -> &'static str {
+        match self.board.result() {
+            GameResult::Ongoing => "ongoing",
+            GameResult::Checkmate(board::Color::White) => "white_wins",
+            GameResult::Checkmate(board::Color::Black) => "black_wins",
+            GameResult::Stalemate => "stalemate",
+            GameResult::DrawSeventyFiveMove => "draw_seventy_five_move",
+            GameResult::DrawRepetition => "draw_repetition",
+            GameResult::DrawInsufficient => "draw_insufficient",
+        }
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        MoveGenerator::generate_legal_moves(&self.board)
+            .iter()
+            .map(|mv| mv.to_uci_for(&self.board))
+            .collect()
+    }
+
+    /// Squares holding a `color` piece ("white" or "black") that attacks
+    /// `square`.
+    fn attackers(&self, square: &str, color: &str) -> PyResult<Vec<String>> {
+        let sq = board::parse_square(square).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        let color = match color.to_ascii_lowercase().as_str() {
+            "white" | "w" => board::Color::White,
+            "black" | "b" => board::Color::Black,
+            _ => return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid color '{}'", color))),
+        };
+
+        let mut attackers = self.board.attackers(sq, color);
+        let mut squares = Vec::new();
+        while attackers != 0 {
+            let (new_bb, from) = bitboard::pop_lsb(attackers);
+            attackers = new_bb;
+            squares.push(board::square_name(from.unwrap()));
+        }
+        Ok(squares)
+    }
+
+    /// Leaf nodes of the legal move tree `depth` plies deep.
+    fn perft(&self, depth: u32) -> u64 {
+        MoveGenerator::perft(&self.board, depth)
+    }
+
+    /// `perft` split by root move, as a dict of UCI move to leaf count.
+    fn perft_divide(&self, depth: u32) -> HashMap<String, u64> {
+        MoveGenerator::perft_divide(&self.board, depth)
+            .into_iter()
+            .map(|(mv, nodes)| (mv.to_uci_for(&self.board), nodes))
+            .collect()
+    }
+
+    /// FEN letter of the piece on `square`, or None when it is empty.
+    fn piece_at(&self, square: &str) -> PyResult<Option<char>> {
+        let sq = board::parse_square(square).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        Ok(self.board.piece_char(sq))
+    }
 }
\ No newline at end of file
@@ -1,13 +1,41 @@
 mod board;
 mod bitboard;
+mod bitbase;
 mod movegen;
 mod search;
 mod eval;
 mod zobrist;
 mod opening_book;
+#[cfg(feature = "nnue")]
+mod nnue;
+#[cfg(feature = "syzygy")]
+mod tablebase;
 mod uci;
+mod xboard;
+
+use std::io::{self, BufRead};
 
 fn main() {
-    let mut engine = uci::UCIEngine::new();
-    engine.run();
-}
\ No newline at end of file
+    // `chess_uci bench [depth]` prints the bench signature and exits, for
+    // comparing builds from a script
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "bench") {
+        uci::UCIEngine::new().handle_command(&args.join(" "));
+        return;
+    }
+
+    // The first command picks the protocol; anything but `xboard` is UCI
+    let mut first = String::new();
+    if io::stdin().lock().read_line(&mut first).is_err() {
+        return;
+    }
+
+    if first.trim() == "xboard" {
+        xboard::XBoardEngine::new().run();
+    } else {
+        let mut engine = uci::UCIEngine::new();
+        if engine.handle_command(first.trim()) {
+            engine.run();
+        }
+    }
+}
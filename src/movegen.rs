@@ -46,6 +46,20 @@ impl Move {
         }
     }
 
+    /// `to_uci` as written in `board`'s game: with Chess960 notation on,
+    /// castling is the king taking its own rook (e1h1), not its two-square
+    /// step.
+    pub fn to_uci_for(self, board: &BoardState) -> String {
+        if board.chess960 && self.is_castle() {
+            return format!("{}{}", square_name(self.from), square_name(board.uci_to_square(&self)));
+        }
+        self.to_uci()
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.flags == KING_CASTLE || self.flags == QUEEN_CASTLE
+    }
+
     pub fn is_capture(&self) -> bool {
         self.flags == CAPTURE || self.flags == EP_CAPTURE || self.flags >= KNIGHT_PROMO_CAPTURE
     }
@@ -54,6 +68,19 @@ impl Move {
         self.flags >= KNIGHT_PROMOTION
     }
 
+    /// Packs the move into 16 bits: 6 bits from, 6 bits to, 4 bits flags.
+    pub fn pack(&self) -> u16 {
+        (self.from as u16 & 0x3F) | ((self.to as u16 & 0x3F) << 6) | ((self.flags as u16 & 0xF) << 12)
+    }
+
+    pub fn unpack(packed: u16) -> Self {
+        Move {
+            from: (packed & 0x3F) as u8,
+            to: ((packed >> 6) & 0x3F) as u8,
+            flags: (packed >> 12) as u8,
+        }
+    }
+
     pub fn promotion_piece(&self) -> Option<Piece> {
         match self.flags {
             KNIGHT_PROMOTION | KNIGHT_PROMO_CAPTURE => Some(Piece::Knight),
@@ -69,6 +96,234 @@ pub struct MoveGenerator;
 
 impl MoveGenerator {
     pub fn generate_legal_moves(board: &BoardState) -> Vec<Move> {
+        let us = board.side_to_move;
+        let them = us.flip();
+        let tables = &ATTACK_TABLES;
+
+        let king_sq = match board.get_king_square(us) {
+            Some(sq) => sq,
+            // Without a king nothing can be illegal
+            None => return Self::generate_pseudo_legal(board),
+        };
+
+        let checkers = Self::attackers_to(board, king_sq, them, board.all_pieces);
+
+        // The king must not step back along the checking ray, so look
+        // through it when building the danger map
+        let king_danger = Self::attack_map(board, them, board.all_pieces & !(1u64 << king_sq));
+        let pinned = Self::pinned_pieces(board, us as usize, king_sq);
+
+        if checkers != EMPTY {
+            return Self::evasions(board, king_sq, checkers, king_danger, pinned);
+        }
+
+        let mut moves = Self::generate_pseudo_legal(board);
+        moves.retain(|mv| {
+            if mv.from == king_sq {
+                // Castling paths are already checked by the generator
+                return mv.is_castle() || !get_bit(king_danger, mv.to);
+            }
+
+            if mv.flags == EP_CAPTURE {
+                // Removing two pawns from one rank can expose the king, so
+                // settle this rare case by playing it out
+                let mut new_board = board.clone();
+                new_board.make_move(mv);
+                return !new_board.is_in_check(us);
+            }
+
+            !get_bit(pinned, mv.from) || get_bit(tables.line(king_sq, mv.from), mv.to)
+        });
+
+        moves
+    }
+
+    /// Legal moves for a side in check, generated straight from the check
+    /// instead of filtering the full move list. Not in check, this is the
+    /// ordinary legal move list.
+    pub fn generate_evasions(board: &BoardState) -> Vec<Move> {
+        let us = board.side_to_move;
+        let them = us.flip();
+
+        let king_sq = match board.get_king_square(us) {
+            Some(sq) => sq,
+            None => return Self::generate_pseudo_legal(board),
+        };
+
+        let checkers = Self::attackers_to(board, king_sq, them, board.all_pieces);
+        if checkers == EMPTY {
+            return Self::generate_legal_moves(board);
+        }
+
+        let king_danger = Self::attack_map(board, them, board.all_pieces & !(1u64 << king_sq));
+        let pinned = Self::pinned_pieces(board, us as usize, king_sq);
+        Self::evasions(board, king_sq, checkers, king_danger, pinned)
+    }
+
+    // King steps off the danger map; with a single checker, also captures of
+    // it and interpositions on the squares between it and the king
+    fn evasions(board: &BoardState, king_sq: u8, checkers: Bitboard, king_danger: Bitboard, pinned: Bitboard) -> Vec<Move> {
+        let us = board.side_to_move;
+        let tables = &ATTACK_TABLES;
+        let own = board.color_bb[us as usize];
+        let mut moves = Vec::with_capacity(32);
+
+        // Double check: only the king can move. Otherwise pieces go in the
+        // same order as the full generator, so both list moves alike.
+        if count_bits(checkers) == 1 {
+            Self::block_or_capture(board, king_sq, checkers, pinned, &mut moves);
+        }
+
+        let mut king_moves = tables.king_attacks[king_sq as usize] & !own & !king_danger;
+        while king_moves != 0 {
+            let (new_bb, to) = pop_lsb(king_moves);
+            king_moves = new_bb;
+            let to_sq = to.unwrap();
+            let flag = if get_bit(board.all_pieces, to_sq) { CAPTURE } else { QUIET_MOVE };
+            moves.push(Move::new(king_sq, to_sq, flag));
+        }
+
+        moves
+    }
+
+    // Non-king moves onto the single checker or the squares between it and
+    // the king. A pinned piece can do neither, so only free pieces are tried.
+    fn block_or_capture(board: &BoardState, king_sq: u8, checkers: Bitboard, pinned: Bitboard, moves: &mut Vec<Move>) {
+        let us = board.side_to_move;
+        let tables = &ATTACK_TABLES;
+        let checker_sq = lsb(checkers).unwrap();
+        let target = checkers | tables.between(king_sq, checker_sq);
+        let pieces = &board.pieces[us as usize];
+
+        let direction: i8 = if us == Color::White { 8 } else { -8 };
+        let start_rank = if us == Color::White { 1 } else { 6 };
+        let promo_rank = if us == Color::White { 7 } else { 0 };
+
+        let mut temp = pieces[Piece::Pawn as usize] & !pinned;
+        while temp != 0 {
+            let (new_bb, sq) = pop_lsb(temp);
+            temp = new_bb;
+            let from_sq = sq.unwrap();
+
+            // Interpose by pushing
+            let to_sq = (from_sq as i8 + direction) as u8;
+            if !get_bit(board.all_pieces, to_sq) {
+                if get_bit(target, to_sq) {
+                    Self::push_pawn_move(moves, from_sq, to_sq, false, promo_rank);
+                }
+
+                let to_sq2 = (to_sq as i8 + direction) as u8;
+                if from_sq / 8 == start_rank && !get_bit(board.all_pieces, to_sq2) && get_bit(target, to_sq2) {
+                    moves.push(Move::new(from_sq, to_sq2, DOUBLE_PAWN_PUSH));
+                }
+            }
+
+            // Take the checker
+            let attacks = tables.pawn_attacks[us as usize][from_sq as usize];
+            if get_bit(attacks, checker_sq) {
+                Self::push_pawn_move(moves, from_sq, checker_sq, true, promo_rank);
+            }
+
+            // En passant removes a checking pawn; played out like in the
+            // full generator in case it uncovers the king along the rank
+            if let Some(ep_sq) = board.ep_square {
+                let captured_sq = (ep_sq as i8 - direction) as u8;
+                if get_bit(attacks, ep_sq) && (captured_sq == checker_sq || get_bit(target, ep_sq)) {
+                    let mv = Move::new(from_sq, ep_sq, EP_CAPTURE);
+                    let mut new_board = board.clone();
+                    new_board.make_move(&mv);
+                    if !new_board.is_in_check(us) {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+
+        for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+            let mut temp = pieces[piece as usize] & !pinned;
+            while temp != 0 {
+                let (new_bb, sq) = pop_lsb(temp);
+                temp = new_bb;
+                let from_sq = sq.unwrap();
+
+                let attacks = match piece {
+                    Piece::Knight => tables.knight_attacks[from_sq as usize],
+                    Piece::Bishop => tables.get_bishop_attacks(from_sq, board.all_pieces),
+                    Piece::Rook => tables.get_rook_attacks(from_sq, board.all_pieces),
+                    _ => tables.get_queen_attacks(from_sq, board.all_pieces),
+                };
+
+                let mut targets = attacks & target;
+                while targets != 0 {
+                    let (new_targets, to) = pop_lsb(targets);
+                    targets = new_targets;
+                    let to_sq = to.unwrap();
+                    let flag = if to_sq == checker_sq { CAPTURE } else { QUIET_MOVE };
+                    moves.push(Move::new(from_sq, to_sq, flag));
+                }
+            }
+        }
+    }
+
+    // A pawn move to `to_sq`, as all four promotions on the last rank
+    fn push_pawn_move(moves: &mut Vec<Move>, from_sq: u8, to_sq: u8, capture: bool, promo_rank: u8) {
+        if to_sq / 8 == promo_rank {
+            let flags = if capture {
+                [QUEEN_PROMO_CAPTURE, ROOK_PROMO_CAPTURE, BISHOP_PROMO_CAPTURE, KNIGHT_PROMO_CAPTURE]
+            } else {
+                [QUEEN_PROMOTION, ROOK_PROMOTION, BISHOP_PROMOTION, KNIGHT_PROMOTION]
+            };
+            for flag in flags {
+                moves.push(Move::new(from_sq, to_sq, flag));
+            }
+        } else {
+            moves.push(Move::new(from_sq, to_sq, if capture { CAPTURE } else { QUIET_MOVE }));
+        }
+    }
+
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep.
+    pub fn perft(board: &BoardState, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = Self::generate_legal_moves(board);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        moves
+            .iter()
+            .map(|mv| {
+                let mut new_board = board.clone();
+                new_board.make_move(mv);
+                Self::perft(&new_board, depth - 1)
+            })
+            .sum()
+    }
+
+    /// `perft` split by root move: each legal move paired with the leaf
+    /// count of its subtree, in generation order. The counts add up to
+    /// `perft(board, depth)`; depth 0 has no moves to split on.
+    pub fn perft_divide(board: &BoardState, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+
+        Self::generate_legal_moves(board)
+            .into_iter()
+            .map(|mv| {
+                let mut new_board = board.clone();
+                new_board.make_move(&mv);
+                (mv, Self::perft(&new_board, depth - 1))
+            })
+            .collect()
+    }
+
+    /// Reference generator that plays every pseudo-legal move and rejects
+    /// the ones leaving the king in check. Kept for perft cross-checks.
+    #[cfg(test)]
+    fn generate_legal_moves_reference(board: &BoardState) -> Vec<Move> {
         let pseudo_legal = Self::generate_pseudo_legal(board);
         let mut legal_moves = Vec::with_capacity(pseudo_legal.len());
 
@@ -85,6 +340,84 @@ impl MoveGenerator {
         legal_moves
     }
 
+    // Pieces of `by_color` attacking `sq` given the occupancy `occ`
+    fn attackers_to(board: &BoardState, sq: u8, by_color: Color, occ: Bitboard) -> Bitboard {
+        let tables = &ATTACK_TABLES;
+        let pieces = &board.pieces[by_color as usize];
+
+        (tables.pawn_attacks[by_color.flip() as usize][sq as usize] & pieces[Piece::Pawn as usize])
+            | (tables.knight_attacks[sq as usize] & pieces[Piece::Knight as usize])
+            | (tables.get_bishop_attacks(sq, occ) & (pieces[Piece::Bishop as usize] | pieces[Piece::Queen as usize]))
+            | (tables.get_rook_attacks(sq, occ) & (pieces[Piece::Rook as usize] | pieces[Piece::Queen as usize]))
+            | (tables.king_attacks[sq as usize] & pieces[Piece::King as usize])
+    }
+
+    // Every square attacked by `color` with sliders blocked by `occ`
+    fn attack_map(board: &BoardState, color: Color, occ: Bitboard) -> Bitboard {
+        let tables = &ATTACK_TABLES;
+        let pieces = &board.pieces[color as usize];
+        let mut attacks = 0u64;
+
+        let mut temp = pieces[Piece::Pawn as usize];
+        while temp != 0 {
+            let (new_bb, sq) = pop_lsb(temp);
+            temp = new_bb;
+            attacks |= tables.pawn_attacks[color as usize][sq.unwrap() as usize];
+        }
+
+        let mut temp = pieces[Piece::Knight as usize];
+        while temp != 0 {
+            let (new_bb, sq) = pop_lsb(temp);
+            temp = new_bb;
+            attacks |= tables.knight_attacks[sq.unwrap() as usize];
+        }
+
+        let mut temp = pieces[Piece::Bishop as usize] | pieces[Piece::Queen as usize];
+        while temp != 0 {
+            let (new_bb, sq) = pop_lsb(temp);
+            temp = new_bb;
+            attacks |= tables.get_bishop_attacks(sq.unwrap(), occ);
+        }
+
+        let mut temp = pieces[Piece::Rook as usize] | pieces[Piece::Queen as usize];
+        while temp != 0 {
+            let (new_bb, sq) = pop_lsb(temp);
+            temp = new_bb;
+            attacks |= tables.get_rook_attacks(sq.unwrap(), occ);
+        }
+
+        if let Some(king_sq) = lsb(pieces[Piece::King as usize]) {
+            attacks |= tables.king_attacks[king_sq as usize];
+        }
+
+        attacks
+    }
+
+    /// Pieces of `us` that are the only blocker between their king on
+    /// `king_sq` and an enemy slider
+    pub fn pinned_pieces(board: &BoardState, us: usize, king_sq: u8) -> Bitboard {
+        let tables = &ATTACK_TABLES;
+        let them = &board.pieces[1 - us];
+
+        let mut snipers = (tables.get_bishop_attacks(king_sq, 0)
+            & (them[Piece::Bishop as usize] | them[Piece::Queen as usize]))
+            | (tables.get_rook_attacks(king_sq, 0)
+            & (them[Piece::Rook as usize] | them[Piece::Queen as usize]));
+
+        let mut pinned = 0u64;
+        while snipers != 0 {
+            let (new_bb, sq) = pop_lsb(snipers);
+            snipers = new_bb;
+
+            let blockers = tables.between(king_sq, sq.unwrap()) & board.all_pieces;
+            if count_bits(blockers) == 1 {
+                pinned |= blockers & board.color_bb[us];
+            }
+        }
+
+        pinned
+    }
+
     pub fn generate_captures(board: &BoardState) -> Vec<Move> {
         Self::generate_legal_moves(board)
             .into_iter()
@@ -92,6 +425,68 @@ impl MoveGenerator {
             .collect()
     }
 
+    /// Quiet (non-capturing, non-promoting) legal moves that give check.
+    /// Direct checks come from check squares taken from the enemy king's
+    /// point of view: a piece gives check from any square it would be
+    /// attacked from. Discovered checks and castling checks are rarer and
+    /// are confirmed by playing the move.
+    pub fn generate_checks(board: &BoardState) -> Vec<Move> {
+        let us = board.side_to_move as usize;
+        let enemy = board.side_to_move.flip();
+        let king_sq = match board.get_king_square(enemy) {
+            Some(sq) => sq,
+            None => return Vec::new(),
+        };
+
+        let tables = &ATTACK_TABLES;
+        let bishop_checks = tables.get_bishop_attacks(king_sq, board.all_pieces);
+        let rook_checks = tables.get_rook_attacks(king_sq, board.all_pieces);
+
+        // Own pieces that alone stand between one of our sliders and the
+        // enemy king
+        let diagonal = board.pieces[us][Piece::Bishop as usize] | board.pieces[us][Piece::Queen as usize];
+        let straight = board.pieces[us][Piece::Rook as usize] | board.pieces[us][Piece::Queen as usize];
+        let mut discoverers = 0u64;
+        let mut blockers = (bishop_checks | rook_checks) & board.color_bb[us];
+        while blockers != 0 {
+            let (new_bb, sq) = pop_lsb(blockers);
+            blockers = new_bb;
+            let sq = sq.unwrap();
+            let without = clear_bit(board.all_pieces, sq);
+            if tables.get_bishop_attacks(king_sq, without) & diagonal != 0
+                || tables.get_rook_attacks(king_sq, without) & straight != 0
+            {
+                discoverers = set_bit(discoverers, sq);
+            }
+        }
+
+        let mut check_squares = [0u64; 7];
+        check_squares[Piece::Pawn as usize] = tables.pawn_attacks[enemy as usize][king_sq as usize];
+        check_squares[Piece::Knight as usize] = tables.knight_attacks[king_sq as usize];
+        check_squares[Piece::Bishop as usize] = bishop_checks;
+        check_squares[Piece::Rook as usize] = rook_checks;
+        check_squares[Piece::Queen as usize] = bishop_checks | rook_checks;
+
+        Self::generate_legal_moves(board)
+            .into_iter()
+            .filter(|mv| {
+                if mv.is_capture() || mv.is_promotion() {
+                    return false;
+                }
+                let direct = match board.piece_at(mv.from) {
+                    Some((piece, _)) => get_bit(check_squares[piece as usize], mv.to),
+                    None => false,
+                };
+                direct
+                    || (get_bit(discoverers, mv.from) || mv.is_castle()) && {
+                        let mut new_board = board.clone();
+                        new_board.make_move(mv);
+                        new_board.is_in_check(enemy)
+                    }
+            })
+            .collect()
+    }
+
     fn generate_pseudo_legal(board: &BoardState) -> Vec<Move> {
         let mut moves = Vec::with_capacity(256);
         let color = board.side_to_move;
@@ -300,48 +695,51 @@ impl MoveGenerator {
         }
     }
 
+    // Chess960 rules, of which standard castling is the special case: the
+    // squares the king and rook cross or land on must be empty but for the
+    // two of them
     fn generate_castling_moves(board: &BoardState, color: Color, moves: &mut Vec<Move>) {
-        if color == Color::White {
-            // Kingside castling
-            if board.castling_rights & 1 != 0 {
-                if !get_bit(board.all_pieces, 5) && !get_bit(board.all_pieces, 6) &&
-                   !board.is_square_attacked(4, Color::Black) &&
-                   !board.is_square_attacked(5, Color::Black) &&
-                   !board.is_square_attacked(6, Color::Black) {
-                    moves.push(Move::new(4, 6, KING_CASTLE));
-                }
+        let tables = &ATTACK_TABLES;
+        let king_sq = match board.get_king_square(color) {
+            Some(sq) => sq,
+            None => return,
+        };
+        let base = if color == Color::White { 0 } else { 56 };
+        let kingside = 2 * color as usize;
+
+        for (right, flag, king_to, rook_to) in [
+            (kingside, KING_CASTLE, base + 6, base + 5),
+            (kingside + 1, QUEEN_CASTLE, base + 2, base + 3),
+        ] {
+            if board.castling_rights & (1 << right) == 0 {
+                continue;
             }
-            
-            // Queenside castling
-            if board.castling_rights & 2 != 0 {
-                if !get_bit(board.all_pieces, 3) && !get_bit(board.all_pieces, 2) &&
-                   !get_bit(board.all_pieces, 1) &&
-                   !board.is_square_attacked(4, Color::Black) &&
-                   !board.is_square_attacked(3, Color::Black) &&
-                   !board.is_square_attacked(2, Color::Black) {
-                    moves.push(Move::new(4, 2, QUEEN_CASTLE));
-                }
+
+            let rook_sq = board.castling_rooks[right];
+            let castlers = (1u64 << king_sq) | (1u64 << rook_sq);
+            let path = tables.between(king_sq, king_to) | tables.between(rook_sq, rook_to)
+                | (1u64 << king_to) | (1u64 << rook_to);
+            if path & board.all_pieces & !castlers != EMPTY {
+                continue;
             }
-        } else {
-            // Kingside castling
-            if board.castling_rights & 4 != 0 {
-                if !get_bit(board.all_pieces, 61) && !get_bit(board.all_pieces, 62) &&
-                   !board.is_square_attacked(60, Color::White) &&
-                   !board.is_square_attacked(61, Color::White) &&
-                   !board.is_square_attacked(62, Color::White) {
-                    moves.push(Move::new(60, 62, KING_CASTLE));
+
+            // The king may not start or cross in check, nor land in it once
+            // both pieces have moved: in Chess960 the castling rook can be
+            // what shielded the king's new square
+            let mut king_path = tables.between(king_sq, king_to) | (1u64 << king_sq);
+            let mut safe = true;
+            while let (rest, Some(sq)) = pop_lsb(king_path) {
+                if board.is_square_attacked(sq, color.flip()) {
+                    safe = false;
+                    break;
                 }
+                king_path = rest;
             }
-            
-            // Queenside castling
-            if board.castling_rights & 8 != 0 {
-                if !get_bit(board.all_pieces, 59) && !get_bit(board.all_pieces, 58) &&
-                   !get_bit(board.all_pieces, 57) &&
-                   !board.is_square_attacked(60, Color::White) &&
-                   !board.is_square_attacked(59, Color::White) &&
-                   !board.is_square_attacked(58, Color::White) {
-                    moves.push(Move::new(60, 58, QUEEN_CASTLE));
-                }
+            let occ_after = (board.all_pieces & !castlers) | (1u64 << king_to) | (1u64 << rook_to);
+            safe = safe && Self::attackers_to(board, king_to, color.flip(), occ_after) == EMPTY;
+
+            if safe {
+                moves.push(Move::new(king_sq, king_to, flag));
             }
         }
     }
@@ -351,4 +749,220 @@ fn square_name(sq: u8) -> String {
     let file = (b'a' + (sq % 8)) as char;
     let rank = (b'1' + (sq / 8)) as char;
     format!("{}{}", file, rank)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perft(board: &BoardState, depth: u32, generate: fn(&BoardState) -> Vec<Move>) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = generate(board);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        moves
+            .iter()
+            .map(|mv| {
+                let mut new_board = board.clone();
+                new_board.make_move(mv);
+                perft(&new_board, depth - 1, generate)
+            })
+            .sum()
+    }
+
+    fn assert_perft(fen: &str, depth: u32, expected: u64) {
+        let board = BoardState::from_fen(fen).unwrap();
+        let fast = MoveGenerator::perft(&board, depth);
+        let reference = perft(&board, depth, MoveGenerator::generate_legal_moves_reference);
+        assert_eq!(fast, reference, "generators disagree on {}", fen);
+        assert_eq!(fast, expected, "wrong perft({}) for {}", depth, fen);
+    }
+
+    #[test]
+    fn test_perft_startpos() {
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 3, 8_902);
+    }
+
+    #[test]
+    fn test_perft_startpos_depth_two() {
+        assert_eq!(MoveGenerator::perft(&BoardState::default(), 2), 400);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97_862);
+    }
+
+    #[test]
+    fn test_perft_endgame_pins() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43_238);
+    }
+
+    #[test]
+    fn test_perft_promotions() {
+        assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9_467);
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        // Shredder-FEN castling rights, rooks off the corners
+        assert_perft("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 4, 326_672);
+        assert_perft("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", 3, 18_002);
+        assert_perft("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", 4, 273_318);
+    }
+
+    #[test]
+    fn test_perft_divide_sums_to_perft() {
+        let board = BoardState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let divide = MoveGenerator::perft_divide(&board, 3);
+
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97_862);
+        assert!(MoveGenerator::perft_divide(&board, 0).is_empty());
+    }
+
+    #[test]
+    fn test_pack_round_trip() {
+        for flags in 0..16u8 {
+            for from in 0..64u8 {
+                for to in 0..64u8 {
+                    let mv = Move::new(from, to, flags);
+                    assert_eq!(Move::unpack(mv.pack()), mv);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_checks() {
+        let board = BoardState::from_fen("4k3/8/8/8/4N3/8/8/R3K3 w - - 0 1").unwrap();
+        let checks = MoveGenerator::generate_checks(&board);
+        let ucis: Vec<String> = checks.iter().map(|m| m.to_uci()).collect();
+
+        assert!(ucis.contains(&"a1a8".to_string()));
+        assert!(ucis.contains(&"e4f6".to_string()));
+        assert!(ucis.contains(&"e4d6".to_string()));
+        assert!(!ucis.contains(&"a1a2".to_string()));
+        assert!(!ucis.contains(&"e4g5".to_string()));
+
+        assert_checks_by_play(&board, &checks);
+    }
+
+    // Exactly the quiet legal moves that leave the enemy king in check
+    fn assert_checks_by_play(board: &BoardState, checks: &[Move]) {
+        let expected: Vec<Move> = MoveGenerator::generate_legal_moves(board)
+            .into_iter()
+            .filter(|mv| {
+                let mut new_board = board.clone();
+                new_board.make_move(mv);
+                !mv.is_capture() && !mv.is_promotion() && new_board.is_in_check(new_board.side_to_move)
+            })
+            .collect();
+        assert_eq!(checks.len(), expected.len());
+        for mv in &expected {
+            assert!(checks.contains(mv), "missing check {}", mv.to_uci());
+        }
+    }
+
+    #[test]
+    fn test_generate_discovered_checks() {
+        // Knight in front of the queen, pawn in front of the rook
+        let board = BoardState::from_fen("4k3/8/8/1N6/Q7/4P3/8/4R1K1 w - - 0 1").unwrap();
+        let checks = MoveGenerator::generate_checks(&board);
+        let ucis: Vec<String> = checks.iter().map(|m| m.to_uci()).collect();
+
+        // Neither knight square checks directly
+        assert!(ucis.contains(&"b5a3".to_string()));
+        assert!(ucis.contains(&"b5c3".to_string()));
+        // The pushed pawn still blocks the file
+        assert!(!ucis.contains(&"e3e4".to_string()));
+        assert_checks_by_play(&board, &checks);
+    }
+
+    #[test]
+    fn test_generate_castling_checks() {
+        // O-O brings the rook to f1, on the king's file
+        let board = BoardState::from_fen("5k2/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        let checks = MoveGenerator::generate_checks(&board);
+        let ucis: Vec<String> = checks.iter().map(|m| m.to_uci()).collect();
+
+        assert!(ucis.contains(&"e1g1".to_string()));
+        assert!(!ucis.contains(&"e1c1".to_string()));
+        assert_checks_by_play(&board, &checks);
+    }
+
+    fn assert_same_moves(fen: &str, mut got: Vec<Move>, mut expected: Vec<Move>) {
+        got.sort_by_key(|mv| mv.pack());
+        expected.sort_by_key(|mv| mv.pack());
+        assert_eq!(got, expected, "evasions differ on {}", fen);
+    }
+
+    // Evasions against the reference generator at every checked node of
+    // the perft tree below `board`
+    fn check_evasions(board: &BoardState, depth: u32) -> u64 {
+        let mut checked = 0;
+        if board.is_in_check(board.side_to_move) {
+            let fen = board.to_fen();
+            let reference = MoveGenerator::generate_legal_moves_reference(board);
+            assert_same_moves(&fen, MoveGenerator::generate_evasions(board), reference);
+            checked += 1;
+        }
+        if depth == 0 {
+            return checked;
+        }
+        for mv in MoveGenerator::generate_legal_moves(board) {
+            let mut new_board = board.clone();
+            new_board.make_move(&mv);
+            checked += check_evasions(&new_board, depth - 1);
+        }
+        checked
+    }
+
+    #[test]
+    fn test_evasions_double_check() {
+        // Rook on e-file and knight on f3 both check: king moves only
+        let fen = "k3r3/8/8/8/8/5n2/3P4/3QK2R w K - 0 1";
+        let board = BoardState::from_fen(fen).unwrap();
+        let evasions = MoveGenerator::generate_evasions(&board);
+        assert!(!evasions.is_empty());
+        assert!(evasions.iter().all(|mv| mv.from == 4));
+        assert_same_moves(fen, evasions, MoveGenerator::generate_legal_moves_reference(&board));
+    }
+
+    #[test]
+    fn test_evasions_single_slider_check() {
+        let positions = [
+            // Rook check: block on the file, capture, or step aside
+            "4r2k/8/8/8/8/2N5/1B6/R3K3 w Q - 0 1",
+            // Rook check; Nf1 would block but the knight is pinned
+            "7k/8/8/b7/8/8/3N4/4K2r w - - 0 1",
+            // Taking the checker with a promotion
+            "3r3k/2P5/8/8/8/8/8/3K4 w - - 0 1",
+            // Checking pawn taken en passant
+            "8/8/8/3pP3/2K5/8/8/7k w - d6 0 1",
+        ];
+        for fen in positions {
+            let board = BoardState::from_fen(fen).unwrap();
+            assert!(board.is_in_check(board.side_to_move), "{} not in check", fen);
+            assert_same_moves(fen, MoveGenerator::generate_evasions(&board), MoveGenerator::generate_legal_moves_reference(&board));
+        }
+    }
+
+    #[test]
+    fn test_evasions_match_reference_in_perft_trees() {
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        for fen in positions {
+            let board = BoardState::from_fen(fen).unwrap();
+            assert!(check_evasions(&board, 3) > 0, "no checks reached from {}", fen);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::bitboard::*;
+use crate::board::{BoardState, Color, Piece};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+
+// ══════════════════════════════════════════════════════════════════════════════
+// NETWORK LAYOUT
+// ══════════════════════════════════════════════════════════════════════════════
+//
+// File format (little endian):
+//   b"RCNN"            magic
+//   u32                hidden size H
+//   i16 × FEATURES×H   feature weights, feature-major
+//   i16 × H            feature bias
+//   i16 × 2H           output weights (side to move half first)
+//   i32                output bias
+//
+// Features are king-bucketed piece-squares seen from each side: the king's
+// quadrant picks one of four buckets, then (own/enemy, piece, square).
+
+const MAGIC: &[u8; 4] = b"RCNN";
+const KING_BUCKETS: usize = 4;
+const FEATURES: usize = KING_BUCKETS * 2 * 6 * 64;
+const MAX_HIDDEN: usize = 4096;
+
+// Quantisation: accumulator clipped to [0, QA], output weights scaled by QB
+const QA: i32 = 255;
+const QB: i32 = 64;
+const OUTPUT_SCALE: i32 = 400;
+
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::read(&mut BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self, String> {
+        let io_err = |e: std::io::Error| e.to_string();
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(io_err)?;
+        if &magic != MAGIC {
+            return Err("not an NNUE file".to_string());
+        }
+
+        let hidden = reader.read_u32::<LittleEndian>().map_err(io_err)? as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(format!("unsupported hidden size {}", hidden));
+        }
+
+        let mut feature_weights = vec![0i16; FEATURES * hidden];
+        reader.read_i16_into::<LittleEndian>(&mut feature_weights).map_err(io_err)?;
+        let mut feature_bias = vec![0i16; hidden];
+        reader.read_i16_into::<LittleEndian>(&mut feature_bias).map_err(io_err)?;
+        let mut output_weights = vec![0i16; 2 * hidden];
+        reader.read_i16_into::<LittleEndian>(&mut output_weights).map_err(io_err)?;
+        let output_bias = reader.read_i32::<LittleEndian>().map_err(io_err)?;
+
+        Ok(Network {
+            hidden,
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    fn weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// ACCUMULATOR
+// ══════════════════════════════════════════════════════════════════════════════
+
+// One per search thread. It follows the thread from one evaluated position
+// to the next, so it only moves by the pieces that differ between the two.
+pub struct Accumulator {
+    network: Arc<Network>,
+    // First-layer sums from white's and black's point of view
+    values: [Vec<i16>; 2],
+    king_buckets: [usize; 2],
+    // The position the sums are for
+    pieces: [[Bitboard; 7]; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: Arc<Network>, board: &BoardState) -> Self {
+        let mut acc = Accumulator {
+            values: [network.feature_bias.clone(), network.feature_bias.clone()],
+            king_buckets: [0; 2],
+            pieces: board.pieces,
+            network,
+        };
+        acc.refresh(board, 0);
+        acc.refresh(board, 1);
+        acc
+    }
+
+    fn refresh(&mut self, board: &BoardState, perspective: usize) {
+        self.values[perspective].copy_from_slice(&self.network.feature_bias);
+        self.king_buckets[perspective] = king_bucket(board, perspective);
+
+        for color in 0..2 {
+            for piece in 1..=6 {
+                let mut bb = board.pieces[color][piece];
+                while bb != 0 {
+                    let (new_bb, sq) = pop_lsb(bb);
+                    bb = new_bb;
+                    self.add(perspective, color, piece, sq.unwrap());
+                }
+            }
+        }
+    }
+
+    /// Scores `board` from the side to move's point of view, first moving
+    /// the accumulator over to it.
+    pub fn evaluate_board(&mut self, board: &BoardState) -> i32 {
+        self.update(board);
+        self.evaluate(board.side_to_move)
+    }
+
+    /// Brings the accumulator to the position on `board`. Only the changed
+    /// squares are touched unless a king crossed into another bucket.
+    fn update(&mut self, board: &BoardState) {
+        let old_pieces = std::mem::replace(&mut self.pieces, board.pieces);
+        if old_pieces == board.pieces {
+            return;
+        }
+
+        for perspective in 0..2 {
+            if king_bucket(board, perspective) != self.king_buckets[perspective] {
+                self.refresh(board, perspective);
+                continue;
+            }
+
+            for (color, (old_row, new_row)) in old_pieces.iter().zip(&board.pieces).enumerate() {
+                for (piece, (&old, &new)) in old_row.iter().zip(new_row).enumerate().skip(1) {
+                    let mut removed = old & !new;
+                    while removed != 0 {
+                        let (new_bb, sq) = pop_lsb(removed);
+                        removed = new_bb;
+                        self.sub(perspective, color, piece, sq.unwrap());
+                    }
+
+                    let mut added = new & !old;
+                    while added != 0 {
+                        let (new_bb, sq) = pop_lsb(added);
+                        added = new_bb;
+                        self.add(perspective, color, piece, sq.unwrap());
+                    }
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, perspective: usize, color: usize, piece: usize, square: u8) {
+        let feature = feature_index(perspective, self.king_buckets[perspective], color, piece, square);
+        let network = Arc::clone(&self.network);
+        for (value, weight) in self.values[perspective].iter_mut().zip(network.weights(feature)) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    fn sub(&mut self, perspective: usize, color: usize, piece: usize, square: u8) {
+        let feature = feature_index(perspective, self.king_buckets[perspective], color, piece, square);
+        let network = Arc::clone(&self.network);
+        for (value, weight) in self.values[perspective].iter_mut().zip(network.weights(feature)) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    fn evaluate(&self, side_to_move: Color) -> i32 {
+        let us = side_to_move as usize;
+        let hidden = self.network.hidden;
+        let weights = &self.network.output_weights;
+
+        let mut sum: i64 = 0;
+        for (half, perspective) in [us, 1 - us].into_iter().enumerate() {
+            for (value, weight) in self.values[perspective]
+                .iter()
+                .zip(&weights[half * hidden..(half + 1) * hidden])
+            {
+                sum += (*value as i32).clamp(0, QA) as i64 * *weight as i64;
+            }
+        }
+
+        ((sum / QA as i64 + self.network.output_bias as i64) * OUTPUT_SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+fn king_bucket(board: &BoardState, perspective: usize) -> usize {
+    match lsb(board.pieces[perspective][Piece::King as usize]) {
+        Some(king_sq) => {
+            let sq = orient(king_sq, perspective);
+            (sq % 8 >= 4) as usize + 2 * (sq / 8 >= 4) as usize
+        }
+        None => 0,
+    }
+}
+
+fn orient(square: u8, perspective: usize) -> u8 {
+    if perspective == 0 { square } else { square ^ 56 }
+}
+
+fn feature_index(perspective: usize, bucket: usize, color: usize, piece: usize, square: u8) -> usize {
+    let relative_color = (color != perspective) as usize;
+    (((bucket * 2 + relative_color) * 6 + (piece - 1)) * 64) + orient(square, perspective) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::MoveGenerator;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_network(hidden: usize, seed: u64) -> Network {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut random = |len: usize| (0..len).map(|_| rng.gen_range(-64..64)).collect::<Vec<i16>>();
+        Network {
+            hidden,
+            feature_weights: random(FEATURES * hidden),
+            feature_bias: random(hidden),
+            output_weights: random(2 * hidden),
+            output_bias: 1000,
+        }
+    }
+
+    #[test]
+    fn test_zero_network_is_constant() {
+        let network = Arc::new(Network {
+            hidden: 8,
+            feature_weights: vec![0; FEATURES * 8],
+            feature_bias: vec![0; 8],
+            output_weights: vec![0; 16],
+            output_bias: 2 * QA * QB,
+        });
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "4k3/8/8/8/8/8/8/Q3K3 b - - 0 1",
+        ] {
+            let board = BoardState::from_fen(fen).unwrap();
+            let acc = Accumulator::new(Arc::clone(&network), &board);
+            assert_eq!(acc.evaluate(board.side_to_move), 2 * OUTPUT_SCALE);
+        }
+    }
+
+    #[test]
+    fn test_incremental_update_matches_refresh() {
+        let network = Arc::new(random_network(16, 3));
+        // Castling, en passant, promotions and king bucket changes all come up
+        let mut board = BoardState::from_fen("r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut acc = Accumulator::new(Arc::clone(&network), &board);
+        let mut rng = StdRng::seed_from_u64(11);
+
+        for _ in 0..60 {
+            let moves = MoveGenerator::generate_legal_moves(&board);
+            if moves.is_empty() {
+                break;
+            }
+            let mv = moves[rng.gen_range(0..moves.len())];
+
+            board.make_move(&mv);
+            acc.update(&board);
+
+            let fresh = Accumulator::new(Arc::clone(&network), &board);
+            assert_eq!(acc.values, fresh.values, "after {}", mv.to_uci());
+            assert_eq!(acc.evaluate(board.side_to_move), fresh.evaluate(board.side_to_move));
+        }
+    }
+
+    #[test]
+    fn test_update_between_unrelated_positions() {
+        let network = Arc::new(random_network(16, 7));
+        let start = BoardState::default();
+        let other = BoardState::from_fen("8/2k5/3p4/p2P1p2/P2P1P2/8/6K1/8 b - - 0 1").unwrap();
+
+        let mut acc = Accumulator::new(Arc::clone(&network), &start);
+        let fresh = Accumulator::new(Arc::clone(&network), &other);
+        assert_eq!(acc.evaluate_board(&other), fresh.evaluate(other.side_to_move));
+        assert_eq!(acc.values, fresh.values);
+    }
+
+    #[test]
+    fn test_network_file_round_trip() {
+        let network = random_network(4, 5);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(4u32.to_le_bytes());
+        for value in network.feature_weights.iter().chain(&network.feature_bias).chain(&network.output_weights) {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(network.output_bias.to_le_bytes());
+
+        let loaded = Network::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.feature_weights, network.feature_weights);
+        assert_eq!(loaded.output_weights, network.output_weights);
+        assert_eq!(loaded.output_bias, network.output_bias);
+
+        assert!(Network::read(&mut &bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::read(&mut &b"XXXX"[..]).is_err());
+    }
+}
@@ -1,17 +1,53 @@
 use crate::board::{BoardState, Color, PIECE_VALUES};
+use crate::bitboard::{RANK_2, RANK_7};
 use crate::movegen::{Move, MoveGenerator};
-use crate::eval::Evaluator;
+use crate::eval::{EvalParams, Evaluator, PawnTable};
+use crate::bitbase;
 use crate::opening_book;
-use parking_lot::{Mutex, RwLock};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use rayon::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 pub const INFINITY: i32 = 999999;
 pub const MATE_SCORE: i32 = 900000;
+// Tablebase wins sit below every real mate score
+#[cfg(feature = "syzygy")]
+const TB_WIN_SCORE: i32 = MATE_SCORE - 1000;
 const MAX_PLY: usize = 128;
-const MAX_THREADS: usize = 256;
+// Deepest iteration a search runs; `go infinite` asks for this
+const MAX_SEARCH_DEPTH: u8 = (MAX_PLY - 1) as u8;
+// Iterations spent scoring the only legal move
+const FORCED_MOVE_DEPTH: u8 = 6;
+// Engine limits, also advertised by the UCI options
+pub const MAX_THREADS: usize = 256;
+pub const MAX_MULTI_PV: usize = 5;
+// Game plies the book is consulted for; 30 covers white's 15th move and black's
+pub const DEFAULT_BOOK_DEPTH: u16 = 30;
+pub const MAX_BOOK_DEPTH: u16 = 1000;
+pub const MIN_HASH_MB: usize = 16;
+// Skill Level runs from 0 to full strength; UCI_Elo maps linearly onto it.
+// The Elo ends are rough guesses, not measured against rated play.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+pub const MIN_ELO: u32 = 1350;
+pub const MAX_ELO: u32 = 2850;
+// Lines a weakened engine chooses its move from
+const SKILL_MULTI_PV: usize = 4;
+// Eval noise in centipawns per level below full strength
+const SKILL_NOISE: i32 = 8;
+pub const MAX_HASH_MB: usize = 32768;
+// Captures ProbCut verifies before giving up on the node
+const PROBCUT_TRIES: usize = 2;
+// One block of [piece][to] per (previous piece, previous to)
+const CONTINUATION_BLOCK: usize = 7 * 64;
+const CONTINUATION_SIZE: usize = 7 * 64 * CONTINUATION_BLOCK;
+// Bound history and continuation-history entries approach but never pass
+const HISTORY_MAX: i32 = 10_000;
+// Root moves are only announced once a search has run this long
+const CURRMOVE_DELAY: Duration = Duration::from_secs(1);
 
 // LMR reduction table
 lazy_static::lazy_static! {
@@ -28,31 +64,212 @@ lazy_static::lazy_static! {
     };
 }
 
+/// Aspiration window and pruning margins in centipawns, kept out of the code
+/// so the search can be tuned without recompiling. `Default` is the tuned set.
+#[derive(Clone, Debug)]
+pub struct SearchParams {
+    // First half-width around the previous score; doubles on each fail
+    pub aspiration_window: i32,
+    // Reverse futility, per ply of depth
+    pub rfp_margin: i32,
+    pub futility_base: i32,
+    pub futility_per_depth: i32,
+    pub razor_base: i32,
+    pub razor_per_depth: i32,
+    // Quiescence skips a capture whose victim plus this can't reach alpha
+    pub delta_margin: i32,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            aspiration_window: 50,
+            rfp_margin: 90,
+            futility_base: 150,
+            futility_per_depth: 130,
+            razor_base: 350,
+            razor_per_depth: 200,
+            delta_margin: 200,
+        }
+    }
+}
+
+impl SearchParams {
+    /// Sets a field by its option name, case-insensitively, as in
+    /// `FutilityBase`. False if there's no such field.
+    pub fn set(&mut self, name: &str, value: i32) -> bool {
+        let field = match name.to_lowercase().as_str() {
+            "aspirationwindow" => &mut self.aspiration_window,
+            "rfpmargin" => &mut self.rfp_margin,
+            "futilitybase" => &mut self.futility_base,
+            "futilityperdepth" => &mut self.futility_per_depth,
+            "razorbase" => &mut self.razor_base,
+            "razorperdepth" => &mut self.razor_per_depth,
+            "deltamargin" => &mut self.delta_margin,
+            _ => return false,
+        };
+        *field = value;
+        true
+    }
+}
+
 pub struct SearchResult {
     pub best_move: Option<Move>,
     pub score: i32,
     pub nodes: u64,
+    // Nodes searched by each thread; sums to `nodes`
+    pub thread_nodes: Vec<u64>,
+    // First move and score of each MultiPV line, best first
     pub pv_lines: Vec<(Move, i32)>,
+    // Principal variation behind `best_move`, starting with it
+    pub pv: Vec<Move>,
+}
+
+/// One completed iterative-deepening iteration, as handed to `SearchInfo`.
+pub struct IterationInfo<'a> {
+    pub depth: u8,
+    pub seldepth: usize,
+    // 1-based MultiPV line this PV belongs to
+    pub multipv: usize,
+    // Centipawns from the side to move; beyond MATE_SCORE - 100 it is a mate
+    pub score: i32,
+    // Win/draw/loss permille, present when UCI_ShowWDL is on
+    pub wdl: Option<[u32; 3]>,
+    pub nodes: u64,
+    pub nps: u64,
+    pub hashfull: usize,
+    pub time_ms: u64,
+    pub pv: &'a [Move],
+    // Position the search started from; write moves with `to_uci_for` it
+    pub root: &'a BoardState,
+}
+
+/// Where a running search reports its progress. The default methods print
+/// the UCI `info` lines, so an empty impl is a UCI reporter.
+pub trait SearchInfo: Send + Sync {
+    fn on_iteration(&self, info: &IterationInfo) {
+        let score = if info.score.abs() > MATE_SCORE - 100 {
+            let mate_in = (MATE_SCORE - info.score.abs() + 1) / 2;
+            format!("mate {}", if info.score > 0 { mate_in } else { -mate_in })
+        } else {
+            format!("cp {}", info.score)
+        };
+        let wdl = info.wdl.map_or(String::new(), |[win, draw, loss]| format!(" wdl {} {} {}", win, draw, loss));
+        let pv: Vec<String> = info.pv.iter().take(10).map(|mv| mv.to_uci_for(info.root)).collect();
+
+        println!(
+            "info depth {} seldepth {} multipv {} score {}{} nodes {} nps {} hashfull {} time {} pv {}",
+            info.depth,
+            info.seldepth,
+            info.multipv,
+            score,
+            wdl,
+            info.nodes,
+            info.nps,
+            info.hashfull,
+            info.time_ms,
+            pv.join(" ")
+        );
+    }
+
+    fn on_currmove(&self, root: &BoardState, depth: u8, mv: Move, move_number: usize) {
+        println!("info depth {} currmove {} currmovenumber {}", depth, mv.to_uci_for(root), move_number);
+    }
+
+    fn on_string(&self, msg: &str) {
+        println!("info string {}", msg);
+    }
 }
 
+/// Prints UCI `info` lines; what a new engine reports to.
+pub struct UciInfo;
+
+impl SearchInfo for UciInfo {}
+
 pub struct SearchEngine {
-    tt: Arc<RwLock<TranspositionTable>>,
+    tt: Arc<TranspositionTable>,
     threads: usize,
     nodes: Arc<AtomicU64>,
     stop: Arc<AtomicBool>,
+    // Set while searching on the opponent's time; no clock runs until ponderhit clears it
+    pondering: Arc<AtomicBool>,
+    // Milliseconds into the search at which the clock started: 0 normally,
+    // u64::MAX while pondering until the ponderhit is noticed
+    clock_offset_ms: AtomicU64,
+    // Nodes, summed over all threads, after which a search stops
+    node_limit: Option<u64>,
     multi_pv: usize,
+    eval_params: EvalParams,
+    search_params: SearchParams,
+    // Eval may use the board's running material/PST totals
+    incremental_psq: bool,
+    // Net from EvalFile, used instead of the handcrafted eval when set
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<crate::nnue::Network>>,
+    // One thread, best book move, fresh hash and no clock: same input, same output
+    deterministic: bool,
+    // Centipawns the root side gives up by accepting a draw
+    contempt: i32,
+    own_book: bool,
+    // Highest-weight book move instead of a weighted random pick
+    book_best_move: bool,
+    // Last game ply, counted from the fullmove number, that uses the book
+    book_depth: u16,
+    // Weighted book picks; seeded by set_book_seed for repeatable openings
+    book_rng: StdRng,
+    skill_level: u8,
+    // Play at `elo` instead of `skill_level`
+    limit_strength: bool,
+    elo: u32,
+    // Eval noise seeds and weakened move picks
+    skill_rng: StdRng,
+    // This search's node cap: node_limit, tightened by the skill level
+    node_budget: Option<u64>,
+    // Largest eval noise this search, 0 at full strength
+    noise_amplitude: i32,
+    noise_seed: u64,
+    // Analysis: no book, no contempt, and every iteration's move is reported
+    analyse_mode: bool,
+    singular_extensions: bool,
+    probcut: bool,
+    late_move_pruning: bool,
+    currmove_delay: Duration,
+    // Append `wdl W D L` to info lines
+    show_wdl: bool,
+    info: Arc<dyn SearchInfo>,
     
     // Per-thread data
     thread_data: Arc<Vec<Mutex<ThreadData>>>,
+    // Lazy SMP workers, one per thread; rebuilt by set_threads
+    pool: rayon::ThreadPool,
+    // OS threads search_root_thread has run on
+    #[cfg(test)]
+    root_workers: Mutex<std::collections::HashSet<std::thread::ThreadId>>,
 }
 
 struct ThreadData {
     killer_moves: [[Option<Move>; 2]; MAX_PLY],
     history_table: [[i32; 64]; 64],
     countermove_table: [[Option<Move>; 64]; 64],
+    // Quiet-move scores by [previous piece][previous to][piece][to], flattened
+    continuation_history: Vec<i32>,
     nodes_searched: u64,
+    // Part of nodes_searched already added to the shared counter
+    nodes_flushed: u64,
+    seldepth: usize,
     pv_table: [[Option<Move>; MAX_PLY]; MAX_PLY],
     pv_length: [usize; MAX_PLY],
+    // Move left out of the singular verification search at each ply
+    excluded_move: [Option<Move>; MAX_PLY],
+    // Move played at each ply of the current line, None for a null move
+    move_stack: [Option<Move>; MAX_PLY],
+    // Static eval at each ply of the current line, -INFINITY when in check
+    static_evals: [i32; MAX_PLY],
+    // Pawn-only eval terms, keyed on the pawn hash
+    pawn_table: PawnTable,
+    // This thread's copy of the net's first layer, set up at the root
+    #[cfg(feature = "nnue")]
+    accumulator: Option<crate::nnue::Accumulator>,
 }
 
 impl ThreadData {
@@ -61,9 +278,18 @@ impl ThreadData {
             killer_moves: [[None; 2]; MAX_PLY],
             history_table: [[0; 64]; 64],
             countermove_table: [[None; 64]; 64],
+            continuation_history: vec![0; CONTINUATION_SIZE],
             nodes_searched: 0,
+            nodes_flushed: 0,
+            seldepth: 0,
             pv_table: [[None; MAX_PLY]; MAX_PLY],
             pv_length: [0; MAX_PLY],
+            excluded_move: [None; MAX_PLY],
+            move_stack: [None; MAX_PLY],
+            static_evals: [-INFINITY; MAX_PLY],
+            pawn_table: PawnTable::new(),
+            #[cfg(feature = "nnue")]
+            accumulator: None,
         }
     }
 
@@ -71,30 +297,86 @@ impl ThreadData {
         self.killer_moves = [[None; 2]; MAX_PLY];
         self.history_table = [[0; 64]; 64];
         self.countermove_table = [[None; 64]; 64];
+        self.continuation_history.fill(0);
         self.nodes_searched = 0;
+        self.nodes_flushed = 0;
+        self.seldepth = 0;
         self.pv_table = [[None; MAX_PLY]; MAX_PLY];
         self.pv_length = [0; MAX_PLY];
+        self.excluded_move = [None; MAX_PLY];
+        self.move_stack = [None; MAX_PLY];
+        self.static_evals = [-INFINITY; MAX_PLY];
+        self.pawn_table.clear();
     }
 }
 
 impl SearchEngine {
     pub fn new(threads: usize) -> Self {
+        Self::with_eval_params(threads, EvalParams::default())
+    }
+
+    pub fn with_eval_params(threads: usize, eval_params: EvalParams) -> Self {
         let threads = threads.clamp(1, MAX_THREADS);
+        bitbase::init();
+
         let mut thread_data = Vec::new();
         for _ in 0..threads {
             thread_data.push(Mutex::new(ThreadData::new()));
         }
 
         SearchEngine {
-            tt: Arc::new(RwLock::new(TranspositionTable::new(512))),
+            tt: Arc::new(TranspositionTable::new(512)),
             threads,
             nodes: Arc::new(AtomicU64::new(0)),
             stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            clock_offset_ms: AtomicU64::new(0),
+            node_limit: None,
             multi_pv: 1,
+            incremental_psq: eval_params.has_builtin_psq(),
+            #[cfg(feature = "nnue")]
+            network: None,
+            eval_params,
+            search_params: SearchParams::default(),
+            deterministic: false,
+            contempt: 0,
+            own_book: true,
+            book_best_move: false,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            book_rng: StdRng::from_entropy(),
+            skill_level: MAX_SKILL_LEVEL,
+            limit_strength: false,
+            elo: MIN_ELO,
+            skill_rng: StdRng::from_entropy(),
+            node_budget: None,
+            noise_amplitude: 0,
+            noise_seed: 0,
+            analyse_mode: false,
+            singular_extensions: true,
+            probcut: true,
+            late_move_pruning: true,
+            currmove_delay: CURRMOVE_DELAY,
+            show_wdl: false,
+            info: Arc::new(UciInfo),
             thread_data: Arc::new(thread_data),
+            pool: Self::build_pool(threads),
+            #[cfg(test)]
+            root_workers: Mutex::new(std::collections::HashSet::new()),
         }
     }
 
+    fn build_pool(threads: usize) -> rayon::ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("search-{}", index))
+            .build()
+            .expect("failed to start search threads")
+    }
+
+    /// One search at a time per engine: `&mut self` rules out overlap, so
+    /// callers sharing an engine across threads have to serialize on a lock.
+    /// The stop flag is cleared on entry, so a stop aimed at an earlier search
+    /// can't cut this one short.
     pub fn search(
         &mut self,
         board: BoardState,
@@ -103,37 +385,142 @@ impl SearchEngine {
     ) -> SearchResult {
         self.nodes.store(0, Ordering::Relaxed);
         self.stop.store(false, Ordering::Relaxed);
+        let offset = if self.pondering.load(Ordering::Relaxed) { u64::MAX } else { 0 };
+        self.clock_offset_ms.store(offset, Ordering::Relaxed);
+
+        // Below full strength: a shallower, capped and noisier search, and
+        // the move is drawn from several lines at the end
+        let skill = self.skill_level();
+        let weakened = skill < MAX_SKILL_LEVEL;
+        let mut skill_rng = if self.deterministic {
+            StdRng::seed_from_u64(board.hash)
+        } else {
+            StdRng::seed_from_u64(self.skill_rng.gen())
+        };
+        let max_depth = if weakened { max_depth.min(skill / 2 + 1) } else { max_depth };
+        self.node_budget = if weakened {
+            let skill_nodes = (skill as u64 + 1).pow(2) * 1000;
+            Some(self.node_limit.map_or(skill_nodes, |limit| limit.min(skill_nodes)))
+        } else {
+            self.node_limit
+        };
+        let was_weakened = self.noise_amplitude != 0;
+        self.noise_amplitude = (MAX_SKILL_LEVEL - skill) as i32 * SKILL_NOISE;
+        self.noise_seed = skill_rng.gen();
+
+        // Evals and scores in the hash carry the eval noise they were
+        // searched with, so a weakened search neither inherits entries nor
+        // leaves them to the next search, which has a new seed or none
+        if self.deterministic || weakened || was_weakened {
+            self.tt.clear();
+        } else {
+            self.tt.increment_age();
+        }
 
         // Clear thread data
         for thread_data in self.thread_data.iter() {
-            thread_data.lock().clear();
+            let mut thread_data = thread_data.lock();
+            thread_data.clear();
+            #[cfg(feature = "nnue")]
+            {
+                thread_data.accumulator = self
+                    .network
+                    .clone()
+                    .map(|network| crate::nnue::Accumulator::new(network, &board));
+            }
         }
 
-        // Opening book probe
-        if board.fullmove_number <= 15 {
-            if let Some(book_move_uci) = opening_book::probe_book(&board.to_fen()) {
-                let moves = MoveGenerator::generate_legal_moves(&board);
-                for mv in moves {
-                    if mv.to_uci() == book_move_uci {
-                        println!("info string Opening book hit");
-                        return SearchResult {
-                            best_move: Some(mv),
-                            score: 0,
-                            nodes: 0,
-                            pv_lines: vec![(mv, 0)],
-                        };
-                    }
+        // Generated once: the game-over check, the book and every iteration
+        // share them
+        let root_moves = MoveGenerator::generate_legal_moves(&board);
+        if root_moves.is_empty() {
+            let score = if board.is_in_check(board.side_to_move) { -MATE_SCORE } else { self.draw_score(0) };
+            return SearchResult {
+                best_move: None,
+                score,
+                nodes: 0,
+                thread_nodes: vec![0; self.threads],
+                pv_lines: Vec::new(),
+                pv: Vec::new(),
+            };
+        }
+
+        // A claimable draw is reported, but a move is still searched for in
+        // case the GUI doesn't claim it
+        if let Some(claim) = Self::draw_claim(&board) {
+            self.info.on_string(claim);
+        }
+
+        // Opening book probe: a loaded Polyglot book first, as deep as it
+        // goes, then the built-in lines up to BookDepth. Both are keyed on
+        // the position, so a transposition out of book just misses.
+        let book_best = self.book_best_move || self.deterministic;
+        let game_ply = board.fullmove_number.saturating_sub(1) * 2 + (board.side_to_move == Color::Black) as u16;
+        let book_move = if self.own_book && !self.analyse_mode {
+            let rng = &mut self.book_rng;
+            opening_book::probe_polyglot(&board, book_best, rng).or_else(|| {
+                if game_ply >= self.book_depth {
+                    None
+                } else if book_best {
+                    opening_book::probe_book_best(&board)
+                } else {
+                    opening_book::probe_book(&board, rng)
+                }
+            })
+        } else {
+            None
+        };
+        if let Some(book_move_uci) = book_move {
+            for &mv in &root_moves {
+                if mv.to_uci() == book_move_uci {
+                    self.info.on_string("Opening book hit");
+                    return SearchResult {
+                        best_move: Some(mv),
+                        score: 0,
+                        nodes: 0,
+                        thread_nodes: vec![0; self.threads],
+                        pv_lines: vec![(mv, 0)],
+                        pv: vec![mv],
+                    };
                 }
             }
         }
 
+        // One line straight from the tables, so not for MultiPV or analysis,
+        // which want every line searched
+        #[cfg(feature = "syzygy")]
+        if let Some((mv, wdl)) = (self.multi_pv == 1 && !self.analyse_mode)
+            .then(|| crate::tablebase::probe_root(&board))
+            .flatten()
+        {
+            let score = Self::tablebase_score(wdl, 0);
+            self.info.on_string("Tablebase hit");
+            return SearchResult {
+                best_move: Some(mv),
+                score,
+                nodes: 0,
+                thread_nodes: vec![0; self.threads],
+                pv_lines: vec![(mv, score)],
+                pv: vec![mv],
+            };
+        }
+
         let start_time = Instant::now();
-        let time_limit = time_ms.map(Duration::from_millis);
+        // The clock would make the reached depth vary between runs
+        let time_limit = if self.deterministic { None } else { time_ms.map(Duration::from_millis) };
 
         let mut best_move = None;
         let mut best_score = 0;
+        let mut best_pv = Vec::new();
         let mut prev_score = 0;
-        let pv_lines = Vec::new();
+        let mut pv_lines = Vec::new();
+        // Full line behind each pv_lines entry, for the skill pick
+        let mut line_pvs: Vec<Vec<Move>> = Vec::new();
+        // Can't show more lines than there are moves
+        let multi_pv = if weakened { self.multi_pv.max(SKILL_MULTI_PV) } else { self.multi_pv };
+        let multi_pv = multi_pv.min(root_moves.len());
+        // A forced move needs no deep search, only an honest score
+        let max_depth = if root_moves.len() == 1 { max_depth.min(FORCED_MOVE_DEPTH) } else { max_depth };
 
         // Iterative deepening
         for depth in 1..=max_depth {
@@ -144,13 +531,24 @@ impl SearchEngine {
             let soft_limit = time_limit.map(|t| t.mul_f64(0.4));
             let hard_limit = time_limit;
 
+            for thread_data in self.thread_data.iter() {
+                thread_data.lock().seldepth = 0;
+            }
+
             let (score, mv, pv) = if depth >= 5 {
-                self.search_aspiration(&board, depth, prev_score, start_time, soft_limit, hard_limit)
+                self.search_aspiration(&board, depth, prev_score, start_time, soft_limit, hard_limit, &root_moves)
             } else {
-                self.search_root(&board, depth, -INFINITY, INFINITY, start_time, soft_limit, hard_limit)
+                self.search_root(&board, depth, -INFINITY, INFINITY, start_time, soft_limit, hard_limit, &root_moves)
             };
 
-            if self.stop.load(Ordering::Relaxed) && depth > 1 {
+            // A stopped iteration is incomplete: keep the last finished one and
+            // only fall back to the partial result if there is nothing else
+            if self.stop.load(Ordering::Relaxed) {
+                if best_move.is_none() {
+                    best_move = mv;
+                    best_score = score;
+                    best_pv = pv;
+                }
                 break;
             }
 
@@ -158,14 +556,15 @@ impl SearchEngine {
                 let score_drop = prev_score - score;
                 
                 // PV stability check
-                let should_reject = depth > 7
+                let should_reject = !self.analyse_mode
+                    && depth > 7
                     && best_move.is_some()
                     && score_drop > 250
                     && prev_score > -400
                     && prev_score < MATE_SCORE - 1000;
 
                 if should_reject {
-                    println!("info string Score drop {}cp, keeping previous move", score_drop);
+                    self.info.on_string(&format!("Score drop {}cp, keeping previous move", score_drop));
                     break;
                 }
 
@@ -173,41 +572,77 @@ impl SearchEngine {
                 best_score = score;
                 prev_score = score;
 
-                let elapsed_ms = start_time.elapsed().as_millis();
-                let nodes = self.nodes.load(Ordering::Relaxed);
-                let nps = if elapsed_ms > 0 {
-                    (nodes as u128 * 1000 / elapsed_ms) as u64
-                } else {
-                    0
-                };
-
-                let mut pv_str = String::new();
-                for pv_move in pv.iter().take(10) {
-                    pv_str.push_str(&format!("{} ", pv_move.to_uci()));
+                // MultiPV: each further line is the best move left once the
+                // earlier lines' moves are taken out of the root
+                let mut lines = vec![(score, pv)];
+                let mut remaining: Vec<Move> = root_moves.iter().copied().filter(|&mv| mv != m).collect();
+                while lines.len() < multi_pv {
+                    let (line_score, line_move, line_pv) = self.search_root(
+                        &board, depth, -INFINITY, INFINITY, start_time, soft_limit, hard_limit, &remaining,
+                    );
+                    match line_move {
+                        Some(line_move) if !self.stop.load(Ordering::Relaxed) => {
+                            remaining.retain(|&mv| mv != line_move);
+                            lines.push((line_score, line_pv));
+                        }
+                        _ => break,
+                    }
+                }
+                // A later line can come out ahead of the first: report the
+                // lines best first and play the best of them. The sort is
+                // stable, so on a tie the first line stays in front.
+                lines.sort_by_key(|&(line_score, _)| std::cmp::Reverse(line_score));
+                if lines[0].0 > score {
+                    best_move = lines[0].1.first().copied();
+                    best_score = lines[0].0;
+                    prev_score = best_score;
                 }
+                best_pv = lines[0].1.clone();
+                pv_lines = lines.iter()
+                    .filter_map(|(line_score, line_pv)| line_pv.first().map(|&mv| (mv, *line_score)))
+                    .collect();
 
-                if score.abs() > MATE_SCORE - 100 {
-                    let mate_in = (MATE_SCORE - score.abs() + 1) / 2;
-                    println!(
-                        "info depth {} score mate {} nodes {} nps {} time {} pv {}",
+                let elapsed_ms = start_time.elapsed().as_millis() as u64;
+                let nodes = self.nodes.load(Ordering::Relaxed);
+                let nps = if elapsed_ms > 0 { nodes * 1000 / elapsed_ms } else { 0 };
+                let seldepth = self.thread_data.iter()
+                    .map(|thread_data| thread_data.lock().seldepth)
+                    .max()
+                    .unwrap_or(0)
+                    .max(depth as usize);
+
+                let hashfull = self.tt.hashfull();
+
+                for (index, (line_score, line_pv)) in lines.iter().enumerate() {
+                    let line_score = *line_score;
+                    let wdl = self.show_wdl.then(|| {
+                        if line_score.abs() > MATE_SCORE - 100 {
+                            if line_score > 0 { [1000, 0, 0] } else { [0, 0, 1000] }
+                        } else {
+                            Evaluator::wdl(line_score, &board)
+                        }
+                    });
+
+                    self.info.on_iteration(&IterationInfo {
                         depth,
-                        if score > 0 { mate_in } else { -mate_in },
+                        seldepth,
+                        multipv: index + 1,
+                        score: line_score,
+                        wdl,
                         nodes,
                         nps,
-                        elapsed_ms,
-                        pv_str.trim()
-                    );
-                } else {
-                    println!(
-                        "info depth {} score cp {} nodes {} nps {} time {} pv {}",
-                        depth, score, nodes, nps, elapsed_ms, pv_str.trim()
-                    );
+                        hashfull,
+                        time_ms: elapsed_ms,
+                        pv: line_pv,
+                        root: &board,
+                    });
                 }
+                line_pvs = lines.into_iter().map(|(_, line_pv)| line_pv).collect();
 
                 // Smart time management
-                if let Some(soft) = soft_limit {
-                    if start_time.elapsed() > soft {
-                        let time_ratio = start_time.elapsed().as_millis() as f64 / soft.as_millis() as f64;
+                if let (Some(soft), Some(elapsed)) = (soft_limit, self.clock(start_time)) {
+                    if elapsed > soft {
+                        let time_ratio = elapsed.as_millis() as f64 / soft.as_millis() as f64;
                         
                         if score < -500 || (time_ratio > 1.5 && score_drop.abs() < 30) {
                             break;
@@ -216,21 +651,114 @@ impl SearchEngine {
                 }
             }
 
-            if let Some(hard) = hard_limit {
-                if start_time.elapsed() > hard.mul_f64(0.9) {
+            if let (Some(hard), Some(elapsed)) = (hard_limit, self.clock(start_time)) {
+                if elapsed > hard.mul_f64(0.9) {
                     break;
                 }
             }
         }
 
+        if weakened && pv_lines.len() > 1 {
+            let (mv, score) = Self::pick_skill_move(&pv_lines, skill, &mut skill_rng);
+            if best_move != Some(mv) {
+                best_move = Some(mv);
+                best_score = score;
+                best_pv = line_pvs.iter()
+                    .find(|line_pv| line_pv.first() == Some(&mv))
+                    .cloned()
+                    .unwrap_or_else(|| vec![mv]);
+            }
+        }
+
+        // Never hand back an empty move while a legal one exists
+        if best_move.is_none() {
+            best_move = root_moves.first().copied();
+        }
+        if best_pv.first() != best_move.as_ref() {
+            best_pv = best_move.into_iter().collect();
+        }
+
+        // Publish whatever the threads haven't flushed yet so the per-thread
+        // counts add up to the total
+        let thread_nodes: Vec<u64> = self.thread_data.iter()
+            .map(|thread_data| {
+                let mut thread_data = thread_data.lock();
+                self.flush_nodes(&mut thread_data);
+                thread_data.nodes_searched
+            })
+            .collect();
+
         SearchResult {
             best_move,
             score: best_score,
             nodes: self.nodes.load(Ordering::Relaxed),
+            thread_nodes,
             pv_lines,
+            pv: best_pv,
+        }
+    }
+
+    // Stockfish's scheme: each line gets a random push that grows as the
+    // skill drops, plus most of its gap to the top line back, so a weak
+    // engine often prefers a move that is only a little worse
+    fn pick_skill_move(lines: &[(Move, i32)], skill: u8, rng: &mut StdRng) -> (Move, i32) {
+        let weakness = 120 - 2 * skill as i32;
+        let top = lines[0].1;
+        let delta = (top - lines[lines.len() - 1].1).min(PIECE_VALUES[1]);
+
+        let mut best = lines[0];
+        let mut best_pushed = -INFINITY;
+        for &(mv, score) in lines {
+            let push = (weakness * (top - score) + delta * rng.gen_range(0..weakness)) / 128;
+            if score + push >= best_pushed {
+                best = (mv, score);
+                best_pushed = score + push;
+            }
+        }
+        best
+    }
+
+    /// Search on the opponent's time for `go ponder`. The caller sets the
+    /// `ponder_handle` flag first, so a ponderhit can't arrive before the
+    /// search starts; clearing it is the ponderhit. Until then no clock runs
+    /// and the search keeps deepening; after it, `time_ms` counts from the
+    /// ponderhit as in a normal search. Returns only after ponderhit or stop.
+    pub fn search_ponder(&mut self, board: BoardState, max_depth: u8, time_ms: Option<u64>) -> SearchResult {
+        let result = self.search(board, max_depth, time_ms);
+        while self.pondering.load(Ordering::Relaxed) && !self.stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(5));
         }
+        self.pondering.store(false, Ordering::Relaxed);
+        result
     }
 
+    // Time used on our own clock, or None while pondering. The first call
+    // after a ponderhit starts the clock.
+    fn clock(&self, start_time: Instant) -> Option<Duration> {
+        if self.pondering.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let elapsed_ms = start_time.elapsed().as_millis() as u64;
+        let offset = match self.clock_offset_ms.compare_exchange(u64::MAX, elapsed_ms, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => elapsed_ms,
+            Err(offset) => offset,
+        };
+        Some(Duration::from_millis(elapsed_ms.saturating_sub(offset)))
+    }
+
+    /// Analysis for `go infinite`: iterates as deep as the search goes and
+    /// only returns once the stop flag is set, even if the last iteration
+    /// finished before that. The result is the deepest completed iteration.
+    pub fn search_infinite(&mut self, board: BoardState) -> SearchResult {
+        let result = self.search(board, MAX_SEARCH_DEPTH, None);
+        while !self.stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn search_aspiration(
         &self,
         board: &BoardState,
@@ -239,15 +767,16 @@ impl SearchEngine {
         start_time: Instant,
         soft_limit: Option<Duration>,
         hard_limit: Option<Duration>,
+        root_moves: &[Move],
     ) -> (i32, Option<Move>, Vec<Move>) {
-        let mut window = 50;
+        let mut window = self.search_params.aspiration_window;
         let mut alpha = prev_score - window;
         let mut beta = prev_score + window;
         let mut fail_high_count = 0;
         let mut fail_low_count = 0;
 
         loop {
-            let (score, mv, pv) = self.search_root(board, depth, alpha, beta, start_time, soft_limit, hard_limit);
+            let (score, mv, pv) = self.search_root(board, depth, alpha, beta, start_time, soft_limit, hard_limit, root_moves);
 
             if self.stop.load(Ordering::Relaxed) {
                 return (prev_score, mv, pv);
@@ -258,12 +787,12 @@ impl SearchEngine {
                 fail_low_count += 1;
                 beta = (alpha + beta) / 2;
                 alpha = (alpha - window * (1 + fail_low_count)).max(-INFINITY);
-                println!("info string Fail low, widening window to [{}, {}]", alpha, beta);
+                self.info.on_string(&format!("Fail low, widening window to [{}, {}]", alpha, beta));
             } else if score >= beta {
                 // Fail high
                 fail_high_count += 1;
                 beta = (beta + window * (1 + fail_high_count)).min(INFINITY);
-                println!("info string Fail high, widening window to [{}, {}]", alpha, beta);
+                self.info.on_string(&format!("Fail high, widening window to [{}, {}]", alpha, beta));
             } else {
                 return (score, mv, pv);
             }
@@ -272,11 +801,14 @@ impl SearchEngine {
 
             // Emergency exit on extreme fails
             if fail_high_count + fail_low_count > 5 {
-                return self.search_root(board, depth, -INFINITY, INFINITY, start_time, soft_limit, hard_limit);
+                return self.search_root(board, depth, -INFINITY, INFINITY, start_time, soft_limit, hard_limit, root_moves);
             }
         }
     }
 
+    // Searches only `root_moves`, the legal moves `search` generated once;
+    // MultiPV passes what is left after the lines it already has.
+    #[allow(clippy::too_many_arguments)]
     fn search_root(
         &self,
         board: &BoardState,
@@ -286,20 +818,12 @@ impl SearchEngine {
         start_time: Instant,
         soft_limit: Option<Duration>,
         hard_limit: Option<Duration>,
+        root_moves: &[Move],
     ) -> (i32, Option<Move>, Vec<Move>) {
-        let mut moves = MoveGenerator::generate_legal_moves(board);
-
-        if moves.is_empty() {
-            return if board.is_in_check(board.side_to_move) {
-                (-MATE_SCORE, None, vec![])
-            } else {
-                (0, None, vec![])
-            };
-        }
-
-        if moves.len() == 1 {
-            return (0, Some(moves[0]), vec![moves[0]]);
+        if root_moves.is_empty() {
+            return (-INFINITY, None, vec![]);
         }
+        let mut moves = root_moves.to_vec();
 
         // Order moves using main thread data
         let mut thread_data = self.thread_data[0].lock();
@@ -311,42 +835,44 @@ impl SearchEngine {
         let mut best_pv = Vec::new();
 
         // Lazy SMP: Launch parallel search on multiple threads
-        if self.threads > 1 && depth >= 6 {
-            let results: Vec<_> = (0..self.threads)
-                .into_par_iter()
-                .map(|thread_id| {
-                    if self.stop.load(Ordering::Relaxed) {
-                        return (-INFINITY, None, vec![]);
-                    }
+        if self.threads > 1 && !self.deterministic && depth >= 6 {
+            let results: Vec<_> = self.pool.install(|| {
+                (0..self.threads)
+                    .into_par_iter()
+                    .map(|thread_id| {
+                        if self.stop.load(Ordering::Relaxed) {
+                            return (-INFINITY, None, vec![], 0);
+                        }
 
-                    let depth_variation = if thread_id > 0 {
-                        // Vary depth for helper threads
-                        let offset = (thread_id as i32) % 4 - 1;
-                        (depth as i32 + offset).max(1).min(depth as i32) as u8
-                    } else {
-                        depth
-                    };
+                        let depth_variation = if thread_id > 0 {
+                            // Vary depth for helper threads
+                            let offset = (thread_id as i32) % 4 - 1;
+                            (depth as i32 + offset).max(1).min(depth as i32) as u8
+                        } else {
+                            depth
+                        };
 
-                    self.search_root_thread(
-                        board,
-                        depth_variation,
-                        alpha,
-                        beta,
-                        thread_id,
-                        start_time,
-                        soft_limit,
-                        hard_limit,
-                    )
-                })
-                .collect();
+                        let (score, mv, pv) = self.search_root_thread(
+                            board,
+                            depth_variation,
+                            alpha,
+                            beta,
+                            thread_id,
+                            start_time,
+                            soft_limit,
+                            hard_limit,
+                            root_moves,
+                        );
+                        (score, mv, pv, depth_variation)
+                    })
+                    .collect()
+            });
 
-            // Select best result
-            for (score, mv, pv) in results {
-                if score > best_score {
-                    best_score = score;
-                    best_move = mv;
-                    best_pv = pv;
-                }
+            if let Some(index) = Self::vote_root_move(&results) {
+                let (score, mv, pv, _) = results.into_iter().nth(index).unwrap();
+                best_score = score;
+                best_move = mv;
+                best_pv = pv;
             }
         } else {
             // Single-threaded search
@@ -359,6 +885,7 @@ impl SearchEngine {
                 start_time,
                 soft_limit,
                 hard_limit,
+                root_moves,
             );
             best_score = score;
             best_move = mv;
@@ -368,6 +895,33 @@ impl SearchEngine {
         (best_score, best_move, best_pv)
     }
 
+    // Lazy SMP vote over (score, move, pv, depth) thread results: each thread
+    // backs its move with its depth times how far its score is above the
+    // worst thread's. Ties go to the deepest supporting thread. Returns the
+    // index of the result to use.
+    fn vote_root_move(results: &[(i32, Option<Move>, Vec<Move>, u8)]) -> Option<usize> {
+        let min_score = results.iter().filter(|r| r.1.is_some()).map(|r| r.0).min()?;
+
+        let mut votes: Vec<(Move, i64)> = Vec::new();
+        for (score, mv, _, depth) in results {
+            if let Some(mv) = mv {
+                let weight = (*score as i64 - min_score as i64 + 14) * *depth as i64;
+                match votes.iter_mut().find(|(voted, _)| voted == mv) {
+                    Some(vote) => vote.1 += weight,
+                    None => votes.push((*mv, weight)),
+                }
+            }
+        }
+
+        let top = votes.iter().map(|&(_, weight)| weight).max()?;
+        results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.1.is_some_and(|mv| votes.contains(&(mv, top))))
+            .max_by_key(|(_, r)| (r.3, r.2.len(), r.0))
+            .map(|(index, _)| index)
+    }
+
     fn search_root_thread(
         &self,
         board: &BoardState,
@@ -378,8 +932,12 @@ impl SearchEngine {
         start_time: Instant,
         soft_limit: Option<Duration>,
         hard_limit: Option<Duration>,
+        root_moves: &[Move],
     ) -> (i32, Option<Move>, Vec<Move>) {
-        let mut moves = MoveGenerator::generate_legal_moves(board);
+        #[cfg(test)]
+        self.root_workers.lock().insert(std::thread::current().id());
+
+        let mut moves = root_moves.to_vec();
         let mut thread_data = self.thread_data[thread_id].lock();
         self.order_moves_internal(board, &mut moves, None, 0, &mut thread_data);
         
@@ -393,19 +951,25 @@ impl SearchEngine {
                 break;
             }
 
+            // Main thread only, and not until a GUI would want to show it
+            if thread_id == 0 && start_time.elapsed() >= self.currmove_delay {
+                self.info.on_currmove(board, depth, mv, move_count + 1);
+            }
+
             let mut new_board = board.clone();
             new_board.make_move(&mv);
+            thread_data.move_stack[0] = Some(mv);
 
             let score = if move_count == 0 {
                 // Full window search for first move
-                -self.pvs(&new_board, depth - 1, -beta, -alpha, 1, true, thread_id, start_time, soft_limit, hard_limit, &mut thread_data)
+                -self.pvs(&mut new_board, depth - 1, -beta, -alpha, 1, true, thread_id, start_time, soft_limit, hard_limit, &mut thread_data)
             } else {
                 // PVS: null window search
-                let mut score = -self.pvs(&new_board, depth - 1, -alpha - 1, -alpha, 1, false, thread_id, start_time, soft_limit, hard_limit, &mut thread_data);
+                let mut score = -self.pvs(&mut new_board, depth - 1, -alpha - 1, -alpha, 1, false, thread_id, start_time, soft_limit, hard_limit, &mut thread_data);
                 
                 if score > alpha && score < beta {
                     // Re-search with full window
-                    score = -self.pvs(&new_board, depth - 1, -beta, -alpha, 1, true, thread_id, start_time, soft_limit, hard_limit, &mut thread_data);
+                    score = -self.pvs(&mut new_board, depth - 1, -beta, -alpha, 1, true, thread_id, start_time, soft_limit, hard_limit, &mut thread_data);
                 }
                 score
             };
@@ -439,6 +1003,7 @@ impl SearchEngine {
             }
         }
 
+        self.flush_nodes(&mut thread_data);
         drop(thread_data);
         (best_score, best_move, best_pv)
     }
@@ -446,7 +1011,7 @@ impl SearchEngine {
     #[allow(clippy::too_many_arguments)]
     fn pvs(
         &self,
-        board: &BoardState,
+        board: &mut BoardState,
         depth: u8,
         mut alpha: i32,
         beta: i32,
@@ -458,24 +1023,45 @@ impl SearchEngine {
         hard_limit: Option<Duration>,
         thread_data: &mut ThreadData,
     ) -> i32 {
+        // Cleared before any early return, so a parent never copies a line
+        // left over from an earlier sibling
+        thread_data.pv_length[ply] = 0;
+
+        // Checked before counting this node, so a stopped search never
+        // counts past its node budget however many callers still recurse
+        if self.stop.load(Ordering::Relaxed) || self.out_of_nodes(thread_data) {
+            return 0;
+        }
+
         // Periodic stop check
         thread_data.nodes_searched += 1;
-        if thread_data.nodes_searched & 2047 == 0 {
-            self.nodes.fetch_add(2048, Ordering::Relaxed);
-            thread_data.nodes_searched = 0;
+        thread_data.seldepth = thread_data.seldepth.max(ply);
+        if thread_data.nodes_searched - thread_data.nodes_flushed >= 2048 {
+            self.flush_nodes(thread_data);
 
             if self.check_time_abort(start_time, soft_limit, hard_limit) {
                 return 0;
             }
         }
 
-        if self.stop.load(Ordering::Relaxed) {
-            return 0;
+        // Draw detection
+        if board.halfmove_clock >= 100 || board.is_repetition_draw(ply) {
+            return self.draw_score(ply);
         }
 
-        // Draw detection
-        if board.halfmove_clock >= 100 || board.is_repetition() {
-            return 0;
+        // Out of per-ply tables; only a very deep iteration gets this far
+        if ply >= MAX_PLY - 1 {
+            return self.evaluate(board, thread_data);
+        }
+
+        // Tablebase cutoff, probed only right after a zeroing move
+        #[cfg(feature = "syzygy")]
+        if ply > 0 && board.halfmove_clock == 0 {
+            if let Some(wdl) = crate::tablebase::probe_wdl(board) {
+                let score = Self::tablebase_score(wdl, ply);
+                self.tt.store(board.hash, depth, score, TT_EXACT, None, NO_EVAL);
+                return score;
+            }
         }
 
         // Mate distance pruning
@@ -495,15 +1081,19 @@ impl SearchEngine {
 
         // Quiescence at leaf nodes
         if depth == 0 {
-            return self.quiescence(board, alpha, beta_new, 0, thread_data);
+            return self.quiescence(board, alpha, beta_new, 0, ply, thread_data);
         }
 
+        // Inside a singular verification search this node is a different
+        // problem: no hash cutoffs, no hash stores, no pruning on the eval
+        let excluded_move = thread_data.excluded_move[ply];
+
         // TT probe
-        let tt_entry = self.tt.read().probe(board.hash);
-        let mut tt_move = tt_entry.as_ref().and_then(|e| e.best_move);
+        let tt_entry = self.tt.probe(board.hash);
+        let mut tt_move = tt_entry.as_ref().and_then(|e| e.best_move());
 
         if let Some(entry) = &tt_entry {
-            if entry.depth >= depth && !pv_node && ply > 0 {
+            if entry.depth >= depth && !pv_node && ply > 0 && excluded_move.is_none() {
                 match entry.flag {
                     TT_EXACT => return entry.score,
                     TT_ALPHA if entry.score <= alpha => return alpha,
@@ -513,31 +1103,36 @@ impl SearchEngine {
             }
         }
 
-        let static_eval = Evaluator::evaluate(board);
+        // The hash entry remembers the static eval from an earlier visit
+        let static_eval = match tt_entry {
+            Some(entry) if entry.eval != NO_EVAL => entry.eval,
+            _ => self.evaluate(board, thread_data),
+        };
+        thread_data.static_evals[ply] = if in_check { -INFINITY } else { static_eval };
+        let improving = !in_check && Self::is_improving(thread_data, ply);
 
         // Reverse futility pruning
-        if !pv_node && !in_check && depth <= 7 {
-            let rfp_margin = 90 * depth as i32;
+        if !pv_node && !in_check && depth <= 7 && excluded_move.is_none() {
+            let rfp_margin = self.search_params.rfp_margin * (depth as i32 + !improving as i32);
             if static_eval - rfp_margin >= beta_new {
                 return static_eval - rfp_margin;
             }
         }
 
         // Null move pruning with verification
-        if !pv_node && !in_check && depth >= 3 && board.halfmove_clock < 90 {
+        if !pv_node && !in_check && depth >= 3 && board.halfmove_clock < 90 && excluded_move.is_none() {
             let has_pieces = (board.pieces[board.side_to_move as usize][2] 
                 | board.pieces[board.side_to_move as usize][3]
                 | board.pieces[board.side_to_move as usize][4]
                 | board.pieces[board.side_to_move as usize][5]) != 0;
 
             if has_pieces && static_eval >= beta_new {
-                let mut null_board = board.clone();
-                null_board.side_to_move = null_board.side_to_move.flip();
-                null_board.ep_square = None;
-                null_board.hash ^= crate::zobrist::ZOBRIST.side_key;
+                let undo = board.make_null_move();
+                thread_data.move_stack[ply] = None;
 
                 let r = 3 + (depth / 4) + ((static_eval - beta_new) / 200).clamp(0, 2) as u8;
-                let score = -self.pvs(&null_board, depth.saturating_sub(r), -beta_new, -beta_new + 1, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
+                let score = -self.pvs(board, depth.saturating_sub(r), -beta_new, -beta_new + 1, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
+                board.unmake_null_move(undo);
 
                 if score >= beta_new {
                     if depth < 12 {
@@ -553,22 +1148,38 @@ impl SearchEngine {
         }
 
         // Razoring
-        if depth <= 3 && !in_check && !pv_node {
-            let razor_margin = 350 + 200 * depth as i32;
+        if depth <= 3 && !in_check && !pv_node && excluded_move.is_none() {
+            let razor_margin = self.search_params.razor_base + self.search_params.razor_per_depth * depth as i32;
             if static_eval + razor_margin < alpha {
-                let q_score = self.quiescence(board, alpha, beta_new, 0, thread_data);
+                let q_score = self.quiescence(board, alpha, beta_new, 0, ply, thread_data);
                 if q_score < alpha {
                     return q_score.max(alpha - razor_margin);
                 }
             }
         }
 
+        // ProbCut: a good capture that beats beta by a margin at reduced
+        // depth will almost always beat beta at full depth too
+        let probcut_beta = beta_new + self.eval_params.probcut_margin;
+        if self.probcut
+            && !pv_node
+            && !in_check
+            && depth >= 5
+            && excluded_move.is_none()
+            && beta_new.abs() < MATE_SCORE - 100
+            && !tt_entry.as_ref().is_some_and(|e| e.depth.saturating_add(3) >= depth && e.score < probcut_beta)
+        {
+            if let Some(score) = self.probcut(board, depth, probcut_beta, static_eval, ply, thread_id, start_time, soft_limit, hard_limit, thread_data) {
+                return score;
+            }
+        }
+
         // Internal iterative deepening
         if tt_move.is_none() && depth >= 6 && pv_node {
             let iid_depth = depth.saturating_sub(2);
             self.pvs(board, iid_depth, alpha, beta_new, ply, true, thread_id, start_time, soft_limit, hard_limit, thread_data);
-            let entry = self.tt.read().probe(board.hash);
-            tt_move = entry.and_then(|e| e.best_move);
+            let entry = self.tt.probe(board.hash);
+            tt_move = entry.and_then(|e| e.best_move());
         }
 
         let mut moves = MoveGenerator::generate_legal_moves(board);
@@ -577,10 +1188,36 @@ impl SearchEngine {
             return if in_check {
                 -MATE_SCORE + ply as i32
             } else {
-                0
+                self.draw_score(ply)
             };
         }
 
+        // Only trust the hash move if it is legal in this position
+        let tt_move = tt_move.filter(|hash_mv| moves.contains(hash_mv));
+
+        if let Some(excluded) = excluded_move {
+            moves.retain(|mv| *mv != excluded);
+            if moves.is_empty() {
+                return alpha;
+            }
+        }
+
+        // Singular extension: if every alternative fails low against a margin
+        // below the hash score, the hash move is the only move here
+        let mut singular = false;
+        if let (Some(entry), Some(hash_mv)) = (&tt_entry, tt_move) {
+            if self.singular_extensions
+                && ply > 0
+                && depth >= 8
+                && excluded_move.is_none()
+                && entry.flag == TT_BETA
+                && entry.depth.saturating_add(3) >= depth
+                && entry.score.abs() < MATE_SCORE - 100
+            {
+                singular = self.is_singular(board, hash_mv, entry.score, depth, ply, thread_id, start_time, soft_limit, hard_limit, thread_data);
+            }
+        }
+
         self.order_moves_internal(board, &mut moves, tt_move, ply, thread_data);
 
         let mut best_score = -INFINITY;
@@ -589,8 +1226,6 @@ impl SearchEngine {
         let alpha_orig = alpha;
         let mut quiets_tried: Vec<Move> = Vec::new();
 
-        thread_data.pv_length[ply] = 0;
-
         for mv in moves {
             if self.check_time_abort(start_time, soft_limit, hard_limit) {
                 break;
@@ -598,28 +1233,33 @@ impl SearchEngine {
 
             let mut new_board = board.clone();
             new_board.make_move(&mv);
+            thread_data.move_stack[ply] = Some(mv);
+            let gives_check = new_board.is_in_check(new_board.side_to_move);
+
+            if !pv_node && !in_check && !gives_check && self.late_move_prunable(&mv, depth, move_count, tt_move, ply, thread_data) {
+                move_count += 1;
+                continue;
+            }
 
             // Futility pruning
             let futile = !in_check
-                && !new_board.is_in_check(new_board.side_to_move)
+                && !gives_check
                 && !mv.is_capture()
                 && !mv.is_promotion()
                 && move_count > 0
                 && depth <= 6;
 
-            if futile {
-                let futility_margin = 150 + 130 * depth as i32;
-                if static_eval + futility_margin <= alpha {
-                    move_count += 1;
-                    continue;
-                }
+            if futile && static_eval + self.futility_margin(depth, improving) <= alpha {
+                move_count += 1;
+                continue;
             }
 
-            let gives_check = new_board.is_in_check(new_board.side_to_move);
             let mut extension = 0;
 
-            // Passed pawn extension
-            if !gives_check && mv.from / 8 == 6 && board.side_to_move == Color::White && !mv.is_capture() {
+            // Singular hash move, otherwise passed pawn extension
+            if singular && Some(mv) == tt_move {
+                extension = 1;
+            } else if !gives_check && mv.from / 8 == 6 && board.side_to_move == Color::White && !mv.is_capture() {
                 let pawn_bb = board.pieces[0][1];
                 if (pawn_bb & (1u64 << mv.from)) != 0 {
                     extension = 1;
@@ -633,50 +1273,26 @@ impl SearchEngine {
 
             let score = if move_count == 0 {
                 // First move: full window PVS
-                -self.pvs(&new_board, depth.saturating_sub(1).saturating_add(extension), -beta_new, -alpha, ply + 1, pv_node, thread_id, start_time, soft_limit, hard_limit, thread_data)
+                -self.pvs(&mut new_board, depth.saturating_sub(1).saturating_add(extension), -beta_new, -alpha, ply + 1, pv_node, thread_id, start_time, soft_limit, hard_limit, thread_data)
             } else {
                 // Late move reductions
                 let reduction = if move_count >= 3 && depth >= 3 && !in_check && !gives_check && !mv.is_capture() && !mv.is_promotion() {
-                    let base = LMR_TABLE[depth.min(63) as usize][move_count.min(63)];
-                    let mut r = base;
-
-                    // Reduce less in PV nodes
-                    if pv_node {
-                        r = r.saturating_sub(1);
-                    }
-
-                    // Reduce less for killer moves
-                    let is_killer = thread_data.killer_moves[ply].iter().any(|k| {
-                        k.map_or(false, |killer| killer.from == mv.from && killer.to == mv.to)
-                    });
-                    if is_killer {
-                        r = r.saturating_sub(1);
-                    }
-
-                    // Reduce less for good history
-                    let history = thread_data.history_table[mv.from as usize][mv.to as usize];
-                    if history > 5000 {
-                        r = r.saturating_sub(1);
-                    } else if history < -5000 {
-                        r = r.saturating_add(1);
-                    }
-
-                    r.min(depth.saturating_sub(1))
+                    self.late_move_reduction(board, &mv, depth, move_count, pv_node, improving, ply, thread_data)
                 } else {
                     0
                 };
 
                 // Null window search with reduction
-                let mut score = -self.pvs(&new_board, depth.saturating_sub(reduction + 1).saturating_add(extension), -alpha - 1, -alpha, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
+                let mut score = -self.pvs(&mut new_board, depth.saturating_sub(reduction + 1).saturating_add(extension), -alpha - 1, -alpha, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
 
                 // Re-search if reduced and score beats alpha
                 if reduction > 0 && score > alpha {
-                    score = -self.pvs(&new_board, depth.saturating_sub(1).saturating_add(extension), -alpha - 1, -alpha, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
+                    score = -self.pvs(&mut new_board, depth.saturating_sub(1).saturating_add(extension), -alpha - 1, -alpha, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
                 }
 
                 // Re-search with full window if score is in (alpha, beta)
                 if score > alpha && score < beta_new && pv_node {
-                    score = -self.pvs(&new_board, depth.saturating_sub(1).saturating_add(extension), -beta_new, -alpha, ply + 1, true, thread_id, start_time, soft_limit, hard_limit, thread_data);
+                    score = -self.pvs(&mut new_board, depth.saturating_sub(1).saturating_add(extension), -beta_new, -alpha, ply + 1, true, thread_id, start_time, soft_limit, hard_limit, thread_data);
                 }
 
                 score
@@ -706,19 +1322,24 @@ impl SearchEngine {
             if score >= beta_new {
                 // Beta cutoff
                 if !mv.is_capture() {
+                    let bonus = (depth as i32) * (depth as i32);
                     self.update_killers_internal(mv, ply, thread_data);
+                    self.update_countermove(mv, ply, thread_data);
                     self.update_history_internal(mv, depth, thread_data);
+                    self.update_continuation_history(board, mv, bonus, ply, thread_data);
 
                     // History penalty for quiet moves that didn't cause cutoff
                     for quiet in &quiets_tried {
                         if quiet.from != mv.from || quiet.to != mv.to {
-                            let penalty = -(depth as i32) * (depth as i32);
-                            self.update_history_raw_internal(*quiet, penalty, thread_data);
+                            self.update_history_raw_internal(*quiet, -bonus, thread_data);
+                            self.update_continuation_history(board, *quiet, -bonus, ply, thread_data);
                         }
                     }
                 }
 
-                self.tt.write().store(board.hash, depth, beta_new, TT_BETA, Some(mv));
+                if excluded_move.is_none() {
+                    self.tt.store(board.hash, depth, beta_new, TT_BETA, Some(mv), static_eval);
+                }
                 return beta_new;
             }
 
@@ -733,97 +1354,274 @@ impl SearchEngine {
             TT_EXACT
         };
 
-        self.tt.write().store(board.hash, depth, best_score, flag, best_move);
+        if excluded_move.is_none() {
+            self.tt.store(board.hash, depth, best_score, flag, best_move, static_eval);
+        }
         best_score
     }
 
-    fn quiescence(&self, board: &BoardState, mut alpha: i32, beta: i32, depth: i8, thread_data: &mut ThreadData) -> i32 {
-        thread_data.nodes_searched += 1;
-
-        if depth < -10 {
-            return Evaluator::evaluate(board);
-        }
-
-        let stand_pat = Evaluator::evaluate(board);
-
-        if stand_pat >= beta {
-            return beta;
-        }
-
-        // Delta pruning
-        let delta = 950;
-        if stand_pat + delta < alpha {
-            return alpha;
-        }
-
-        if stand_pat > alpha {
-            alpha = stand_pat;
-        }
-
+    // Tries the best few captures that win enough material to beat
+    // `probcut_beta`, and returns the score of the first one that still does
+    // after a qsearch and a search four plies shallower
+    #[allow(clippy::too_many_arguments)]
+    fn probcut(
+        &self,
+        board: &BoardState,
+        depth: u8,
+        probcut_beta: i32,
+        static_eval: i32,
+        ply: usize,
+        thread_id: usize,
+        start_time: Instant,
+        soft_limit: Option<Duration>,
+        hard_limit: Option<Duration>,
+        thread_data: &mut ThreadData,
+    ) -> Option<i32> {
         let mut captures = MoveGenerator::generate_captures(board);
-
-        if captures.is_empty() {
-            return stand_pat;
-        }
-
         self.order_captures_internal(board, &mut captures);
 
+        let mut tried = 0;
         for mv in captures {
-            // Delta pruning with SEE
-            if depth < -4 && !self.see_capture(board, &mv, 0) {
+            if tried == PROBCUT_TRIES {
+                break;
+            }
+            if !self.see_capture(board, &mv, probcut_beta - static_eval) {
                 continue;
             }
+            tried += 1;
 
             let mut new_board = board.clone();
             new_board.make_move(&mv);
+            thread_data.move_stack[ply] = Some(mv);
 
-            let score = -self.quiescence(&new_board, -beta, -alpha, depth - 1, thread_data);
-
-            if score >= beta {
-                return beta;
+            // Cheap qsearch first, then confirm with the reduced search
+            let mut score = -self.quiescence(&new_board, -probcut_beta, -probcut_beta + 1, 0, ply + 1, thread_data);
+            if score >= probcut_beta {
+                score = -self.pvs(&mut new_board, depth - 4, -probcut_beta, -probcut_beta + 1, ply + 1, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
             }
 
-            if score > alpha {
-                alpha = score;
+            if score >= probcut_beta {
+                self.tt.store(board.hash, depth - 3, score, TT_BETA, Some(mv), static_eval);
+                return Some(score);
             }
         }
 
-        alpha
+        None
     }
 
-    fn order_moves_internal(&self, board: &BoardState, moves: &mut Vec<Move>, tt_move: Option<Move>, ply: usize, thread_data: &mut ThreadData) {
-        let killers = thread_data.killer_moves[ply];
-        let history = &thread_data.history_table;
+    // A quiet move this far below alpha in static eval is not worth searching
+    fn futility_margin(&self, depth: u8, improving: bool) -> i32 {
+        self.search_params.futility_base + self.search_params.futility_per_depth * (depth as i32 + !improving as i32)
+    }
 
-        moves.sort_by_cached_key(|mv| {
-            -self.score_move_internal(board, mv, tt_move, &killers, history)
-        });
+    // Late move pruning: this far down the list a quiet move at shallow
+    // depth is almost never the one that matters, unless ordering singled
+    // it out
+    fn late_move_prunable(&self, mv: &Move, depth: u8, move_count: usize, tt_move: Option<Move>, ply: usize, thread_data: &ThreadData) -> bool {
+        self.late_move_pruning
+            && !mv.is_capture()
+            && !mv.is_promotion()
+            && depth <= 4
+            && move_count >= 3 + (depth as usize) * (depth as usize)
+            && tt_move != Some(*mv)
+            && !thread_data.killer_moves[ply].contains(&Some(*mv))
+            && Self::countermove(ply, thread_data) != Some(*mv)
     }
 
-    fn score_move_internal(&self, board: &BoardState, mv: &Move, tt_move: Option<Move>, killers: &[Option<Move>; 2], history: &[[i32; 64]; 64]) -> i32 {
-        // TT move has highest priority
-        if let Some(hash_mv) = tt_move {
-            if mv.from == hash_mv.from && mv.to == hash_mv.to {
-                return 10_000_000;
-            }
+    // Searches every move but `hash_mv` at reduced depth against a margin
+    // below its hash score; when they all fail low, `hash_mv` is singular
+    #[allow(clippy::too_many_arguments)]
+    fn is_singular(
+        &self,
+        board: &mut BoardState,
+        hash_mv: Move,
+        hash_score: i32,
+        depth: u8,
+        ply: usize,
+        thread_id: usize,
+        start_time: Instant,
+        soft_limit: Option<Duration>,
+        hard_limit: Option<Duration>,
+        thread_data: &mut ThreadData,
+    ) -> bool {
+        let singular_beta = hash_score - 2 * depth as i32;
+        thread_data.excluded_move[ply] = Some(hash_mv);
+        let score = self.pvs(board, (depth - 1) / 2, singular_beta - 1, singular_beta, ply, false, thread_id, start_time, soft_limit, hard_limit, thread_data);
+        thread_data.excluded_move[ply] = None;
+        score < singular_beta
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn late_move_reduction(&self, board: &BoardState, mv: &Move, depth: u8, move_count: usize, pv_node: bool, improving: bool, ply: usize, thread_data: &ThreadData) -> u8 {
+        let base = LMR_TABLE[depth.min(63) as usize][move_count.min(63)];
+        let mut r = base;
+
+        // Reduce less in PV nodes
+        if pv_node {
+            r = r.saturating_sub(1);
         }
 
-        // Winning captures (MVV-LVA)
-        if mv.is_capture() {
-            let mut score = 9_000_000 + self.mvv_lva_score(board, mv);
-            // Bonus for captures that pass SEE
-            if self.see_capture(board, mv, 0) {
-                score += 100_000;
-            }
-            return score;
+        // Reduce less when the eval is no better than two plies ago
+        if !improving {
+            r = r.saturating_sub(1);
         }
 
-        // Promotions
-        if mv.is_promotion() {
-            return 8_000_000 + match mv.promotion_piece() {
-                Some(crate::board::Piece::Queen) => 4000,
-                Some(crate::board::Piece::Knight) => 3000,
-                Some(crate::board::Piece::Rook) => 2000,
+        // Reduce less for killer moves
+        let is_killer = thread_data.killer_moves[ply].iter().any(|k| {
+            k.map_or(false, |killer| killer.from == mv.from && killer.to == mv.to)
+        });
+        if is_killer {
+            r = r.saturating_sub(1);
+        }
+
+        // Reduce less for good history, including as a reply to the last move
+        let history = thread_data.history_table[mv.from as usize][mv.to as usize]
+            + Self::continuation_block(board, ply, thread_data).map_or(0, |block| Self::continuation_score(board, mv, block));
+        if history > 5000 {
+            r = r.saturating_sub(1);
+        } else if history < -5000 {
+            r = r.saturating_add(1);
+        }
+
+        r.min(depth.saturating_sub(1))
+    }
+
+    // Our static eval beats the one from our previous move. Counts as
+    // improving when that node was in check or is above the root.
+    fn is_improving(thread_data: &ThreadData, ply: usize) -> bool {
+        ply < 2 || thread_data.static_evals[ply] > thread_data.static_evals[ply - 2]
+    }
+
+    fn quiescence(&self, board: &BoardState, mut alpha: i32, beta: i32, depth: i8, ply: usize, thread_data: &mut ThreadData) -> i32 {
+        if self.out_of_nodes(thread_data) {
+            return 0;
+        }
+
+        thread_data.nodes_searched += 1;
+        thread_data.seldepth = thread_data.seldepth.max(ply);
+
+        if depth < -10 {
+            return self.evaluate(board, thread_data);
+        }
+
+        // In check: no standing pat, every legal evasion has to be searched
+        if board.is_in_check(board.side_to_move) {
+            let mut evasions = MoveGenerator::generate_evasions(board);
+
+            if evasions.is_empty() {
+                return -MATE_SCORE + ply as i32;
+            }
+
+            self.order_captures_internal(board, &mut evasions);
+
+            let mut best_score = -INFINITY;
+            for mv in evasions {
+                let mut new_board = board.clone();
+                new_board.make_move(&mv);
+
+                let score = -self.quiescence(&new_board, -beta, -alpha, depth - 1, ply + 1, thread_data);
+
+                if score > best_score {
+                    best_score = score;
+                }
+
+                if score >= beta {
+                    return beta;
+                }
+
+                if score > alpha {
+                    alpha = score;
+                }
+            }
+
+            return best_score;
+        }
+
+        let stand_pat = self.evaluate(board, thread_data);
+
+        if stand_pat >= beta {
+            return beta;
+        }
+
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures = MoveGenerator::generate_captures(board);
+        self.order_captures_internal(board, &mut captures);
+
+        // Quiet checks in the first plies catch shallow mating attacks
+        if depth > -2 {
+            captures.extend(MoveGenerator::generate_checks(board));
+        }
+
+        if captures.is_empty() {
+            return stand_pat;
+        }
+
+        // A pawn about to promote can gain far more than any victim
+        let delta_pruning = !Self::can_promote(board);
+
+        for mv in captures {
+            if delta_pruning && self.delta_prunes(board, &mv, stand_pat, alpha) {
+                continue;
+            }
+
+            // Skip captures that lose material in the exchange
+            if !self.see_capture(board, &mv, 0) {
+                continue;
+            }
+
+            let mut new_board = board.clone();
+            new_board.make_move(&mv);
+
+            let score = -self.quiescence(&new_board, -beta, -alpha, depth - 1, ply + 1, thread_data);
+
+            if score >= beta {
+                return beta;
+            }
+
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        alpha
+    }
+
+    fn order_moves_internal(&self, board: &BoardState, moves: &mut Vec<Move>, tt_move: Option<Move>, ply: usize, thread_data: &mut ThreadData) {
+        let killers = thread_data.killer_moves[ply];
+        let history = &thread_data.history_table;
+        let continuation = Self::continuation_block(board, ply, thread_data);
+
+        moves.sort_by_cached_key(|mv| {
+            -self.score_move_internal(board, mv, tt_move, &killers, history, continuation)
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn score_move_internal(&self, board: &BoardState, mv: &Move, tt_move: Option<Move>, killers: &[Option<Move>; 2], history: &[[i32; 64]; 64], continuation: Option<&[i32]>) -> i32 {
+        // TT move has highest priority
+        if tt_move == Some(*mv) {
+            return 10_000_000;
+        }
+
+        // Winning captures (MVV-LVA)
+        if mv.is_capture() {
+            let mut score = 9_000_000 + self.mvv_lva_score(board, mv);
+            // Bonus for captures that pass SEE
+            if self.see_capture(board, mv, 0) {
+                score += 100_000;
+            }
+            return score;
+        }
+
+        // Promotions
+        if mv.is_promotion() {
+            return 8_000_000 + match mv.promotion_piece() {
+                Some(crate::board::Piece::Queen) => 4000,
+                Some(crate::board::Piece::Knight) => 3000,
+                Some(crate::board::Piece::Rook) => 2000,
                 Some(crate::board::Piece::Bishop) => 1000,
                 _ => 0,
             };
@@ -843,8 +1641,26 @@ impl SearchEngine {
             }
         }
 
-        // History heuristic
-        history[mv.from as usize][mv.to as usize].clamp(-10_000, 10_000)
+        // History heuristic, plus how well this move answered the last one
+        let follow_up = continuation.map_or(0, |block| Self::continuation_score(board, mv, block));
+        (history[mv.from as usize][mv.to as usize] + follow_up).clamp(-10_000, 10_000)
+    }
+
+    // Continuation-history block for moves answering the one that reached
+    // `board`; None at the root and after a null move
+    fn continuation_block<'a>(board: &BoardState, ply: usize, thread_data: &'a ThreadData) -> Option<&'a [i32]> {
+        let start = Self::continuation_offset(board, ply, thread_data)?;
+        Some(&thread_data.continuation_history[start..start + CONTINUATION_BLOCK])
+    }
+
+    fn continuation_offset(board: &BoardState, ply: usize, thread_data: &ThreadData) -> Option<usize> {
+        let prev = thread_data.move_stack[ply.checked_sub(1)?]?;
+        let (piece, _) = board.piece_at(prev.to)?;
+        Some((piece as usize * 64 + prev.to as usize) * CONTINUATION_BLOCK)
+    }
+
+    fn continuation_score(board: &BoardState, mv: &Move, block: &[i32]) -> i32 {
+        board.piece_at(mv.from).map_or(0, |(piece, _)| block[piece as usize * 64 + mv.to as usize])
     }
 
     fn mvv_lva_score(&self, board: &BoardState, mv: &Move) -> i32 {
@@ -875,26 +1691,29 @@ impl SearchEngine {
         });
     }
 
+    // Delta pruning: even winning the victim for free leaves this capture
+    // short of alpha
+    fn delta_prunes(&self, board: &BoardState, mv: &Move, stand_pat: i32, alpha: i32) -> bool {
+        if !mv.is_capture() || mv.is_promotion() {
+            return false;
+        }
+
+        let victim = board.piece_at(mv.to).map_or(PIECE_VALUES[1], |(piece, _)| PIECE_VALUES[piece as usize]);
+        stand_pat + victim + self.search_params.delta_margin <= alpha
+    }
+
+    // The side to move has a pawn on its seventh rank
+    fn can_promote(board: &BoardState) -> bool {
+        let seventh = if board.side_to_move == Color::White { RANK_7 } else { RANK_2 };
+        board.pieces[board.side_to_move as usize][1] & seventh != 0
+    }
+
     fn see_capture(&self, board: &BoardState, mv: &Move, threshold: i32) -> bool {
         if !mv.is_capture() {
             return true;
         }
 
-        let victim_value = if let Some((piece, _)) = board.piece_at(mv.to) {
-            PIECE_VALUES[piece as usize]
-        } else {
-            100 // En passant
-        };
-
-        let attacker_value = if let Some((piece, _)) = board.piece_at(mv.from) {
-            PIECE_VALUES[piece as usize]
-        } else {
-            0
-        };
-
-        // Simple SEE approximation
-        let gain = victim_value - attacker_value;
-        gain >= threshold
+        Evaluator::see(board, mv) >= threshold
     }
 
     fn update_killers_internal(&self, mv: Move, ply: usize, thread_data: &mut ThreadData) {
@@ -916,23 +1735,129 @@ impl SearchEngine {
         ply_killers[0] = Some(mv);
     }
 
+    // Quiet move that last refuted the move played just before `ply`; None
+    // at the root and after a null move
+    fn countermove(ply: usize, thread_data: &ThreadData) -> Option<Move> {
+        let previous = thread_data.move_stack[ply.checked_sub(1)?]?;
+        thread_data.countermove_table[previous.from as usize][previous.to as usize]
+    }
+
+    fn update_countermove(&self, mv: Move, ply: usize, thread_data: &mut ThreadData) {
+        if let Some(previous) = ply.checked_sub(1).and_then(|p| thread_data.move_stack[p]) {
+            thread_data.countermove_table[previous.from as usize][previous.to as usize] = Some(mv);
+        }
+    }
+
+    fn update_continuation_history(&self, board: &BoardState, mv: Move, delta: i32, ply: usize, thread_data: &mut ThreadData) {
+        let (start, (piece, _)) = match (Self::continuation_offset(board, ply, thread_data), board.piece_at(mv.from)) {
+            (Some(start), Some(piece)) => (start, piece),
+            _ => return,
+        };
+
+        // Gravity: the closer an entry is to the bound, the less it moves
+        let entry = &mut thread_data.continuation_history[start + piece as usize * 64 + mv.to as usize];
+        *entry += delta - *entry * delta.abs() / HISTORY_MAX;
+    }
+
     fn update_history_internal(&self, mv: Move, depth: u8, thread_data: &mut ThreadData) {
         let bonus = (depth as i32) * (depth as i32);
         self.update_history_raw_internal(mv, bonus, thread_data);
     }
 
     fn update_history_raw_internal(&self, mv: Move, delta: i32, thread_data: &mut ThreadData) {
+        // Gravity, as for continuation history
         let entry = &mut thread_data.history_table[mv.from as usize][mv.to as usize];
-        *entry += delta;
+        *entry += delta - *entry * delta.abs() / HISTORY_MAX;
+    }
 
-        // Gravity: prevent values from growing too large
-        if entry.abs() > 10000 {
-            for from in 0..64 {
-                for to in 0..64 {
-                    thread_data.history_table[from][to] /= 2;
-                }
-            }
+    fn evaluate(&self, board: &BoardState, thread_data: &mut ThreadData) -> i32 {
+        self.evaluate_exact(board, thread_data) + self.eval_noise(board)
+    }
+
+    fn evaluate_exact(&self, board: &BoardState, thread_data: &mut ThreadData) -> i32 {
+        #[cfg(feature = "nnue")]
+        if let Some(accumulator) = &mut thread_data.accumulator {
+            return accumulator.evaluate_board(board);
+        }
+
+        Evaluator::new(&self.eval_params)
+            .with_pawn_table(&thread_data.pawn_table)
+            .with_incremental_psq(self.incremental_psq)
+            .evaluate(board)
+    }
+
+    // Misjudges positions by up to noise_amplitude. Keyed on the hash, so a
+    // position scores the same wherever the search meets it.
+    fn eval_noise(&self, board: &BoardState) -> i32 {
+        if self.noise_amplitude == 0 {
+            return 0;
+        }
+
+        // splitmix64 finalizer
+        let mut x = board.hash ^ self.noise_seed;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^= x >> 31;
+        (x % (2 * self.noise_amplitude as u64 + 1)) as i32 - self.noise_amplitude
+    }
+
+    // Draws cost the root side the contempt; even plies are the root side to move
+    fn draw_score(&self, ply: usize) -> i32 {
+        let contempt = if self.analyse_mode { 0 } else { self.contempt };
+        if ply.is_multiple_of(2) {
+            -contempt
+        } else {
+            contempt
+        }
+    }
+
+    // What the GUI should be told about a draw already on the board
+    fn draw_claim(board: &BoardState) -> Option<&'static str> {
+        if board.is_threefold_repetition() {
+            Some("Draw by threefold repetition can be claimed")
+        } else if board.can_claim_fifty_move() {
+            Some("Draw by fifty-move rule can be claimed")
+        } else if board.is_draw() {
+            Some("Position is drawn")
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "syzygy")]
+    fn tablebase_score(wdl: crate::tablebase::Wdl, ply: usize) -> i32 {
+        use crate::tablebase::Wdl;
+
+        match wdl {
+            Wdl::Win => TB_WIN_SCORE - ply as i32,
+            Wdl::Loss => -TB_WIN_SCORE + ply as i32,
+            // Fifty-move rule turns these into draws; nudge towards the better side
+            Wdl::CursedWin => 1,
+            Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+        }
+    }
+
+    // Publish this thread's not yet counted nodes to the shared counter
+    fn flush_nodes(&self, thread_data: &mut ThreadData) {
+        self.nodes.fetch_add(thread_data.nodes_searched - thread_data.nodes_flushed, Ordering::Relaxed);
+        thread_data.nodes_flushed = thread_data.nodes_searched;
+    }
+
+    // Raises the stop flag once the node budget is used up. The shared
+    // counter lags by this thread's unflushed nodes, so those are added.
+    fn out_of_nodes(&self, thread_data: &ThreadData) -> bool {
+        let Some(limit) = self.node_budget else {
+            return false;
+        };
+
+        let nodes = self.nodes.load(Ordering::Relaxed) + thread_data.nodes_searched - thread_data.nodes_flushed;
+        if nodes >= limit {
+            self.stop.store(true, Ordering::Relaxed);
+            return true;
         }
+
+        false
     }
 
     fn check_time_abort(&self, start_time: Instant, _soft_limit: Option<Duration>, hard_limit: Option<Duration>) -> bool {
@@ -940,8 +1865,8 @@ impl SearchEngine {
             return true;
         }
 
-        if let Some(hard) = hard_limit {
-            if start_time.elapsed() > hard {
+        if let (Some(hard), Some(elapsed)) = (hard_limit, self.clock(start_time)) {
+            if elapsed > hard {
                 self.stop.store(true, Ordering::Relaxed);
                 return true;
             }
@@ -951,7 +1876,7 @@ impl SearchEngine {
     }
 
     pub fn new_game(&mut self) {
-        self.tt.write().clear();
+        self.tt.clear();
         self.nodes.store(0, Ordering::Relaxed);
         
         for thread_data in self.thread_data.iter() {
@@ -959,6 +1884,10 @@ impl SearchEngine {
         }
     }
 
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
     pub fn set_threads(&mut self, threads: usize) {
         let new_threads = threads.clamp(1, MAX_THREADS);
         if new_threads == self.threads {
@@ -973,152 +1902,1075 @@ impl SearchEngine {
             new_thread_data.push(Mutex::new(ThreadData::new()));
         }
         self.thread_data = Arc::new(new_thread_data);
+        self.pool = Self::build_pool(new_threads);
     }
 
-    pub fn set_multi_pv(&mut self, count: usize) {
-        self.multi_pv = count.clamp(1, 5);
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.eval_params
     }
 
-    pub fn stop(&mut self) {
-        self.stop.store(true, Ordering::Relaxed);
+    pub fn search_params_mut(&mut self) -> &mut SearchParams {
+        &mut self.search_params
     }
 
-    pub fn clear_tt(&mut self) {
-        self.tt.write().clear();
+    /// Changes one eval weight by name, see `EvalParams::set`. Pawn
+    /// structure scores and hash entries from the old weights are dropped.
+    pub fn set_eval_param(&mut self, name: &str, value: i32) -> bool {
+        if !self.eval_params.set(name, value) {
+            return false;
+        }
+        self.incremental_psq = self.eval_params.has_builtin_psq();
+        self.eval_changed();
+        true
     }
 
-    pub fn set_hash_size(&mut self, size_mb: usize) {
-        self.tt.write().resize(size_mb);
+    /// Loads an NNUE file as the evaluation; an empty path goes back to the
+    /// handcrafted eval. On error the previous eval stays, hash included.
+    #[cfg(feature = "nnue")]
+    pub fn set_eval_file(&mut self, path: &str) -> Result<(), String> {
+        self.network = if path.is_empty() {
+            None
+        } else {
+            Some(Arc::new(crate::nnue::Network::from_file(path)?))
+        };
+        self.eval_changed();
+        Ok(())
     }
-}
-
-// Transposition Table Entry Flags
-const TT_EXACT: u8 = 0;
-const TT_ALPHA: u8 = 1;
-const TT_BETA: u8 = 2;
-
-#[derive(Clone)]
-struct TTEntry {
-    hash: u64,
-    depth: u8,
-    score: i32,
-    flag: u8,
-    best_move: Option<Move>,
-    age: u8,
-}
-
-pub struct TranspositionTable {
-    table: Vec<Option<TTEntry>>,
-    size: usize,
-    current_age: u8,
-}
 
-impl TranspositionTable {
-    fn new(size_mb: usize) -> Self {
-        let size = (size_mb * 1024 * 1024) / std::mem::size_of::<Option<TTEntry>>();
-        TranspositionTable {
-            table: vec![None; size],
-            size,
-            current_age: 0,
+    // The pawn tables and the static evals kept in the hash entries were
+    // computed by the old eval
+    fn eval_changed(&mut self) {
+        self.tt.clear();
+        for thread_data in self.thread_data.iter() {
+            thread_data.lock().pawn_table.clear();
         }
     }
 
-    fn probe(&self, hash: u64) -> Option<TTEntry> {
-        let index = (hash as usize) % self.size;
-        if let Some(entry) = &self.table[index] {
-            if entry.hash == hash {
-                return Some(entry.clone());
+    /// Applies a UCI option by name, case-insensitively, the way `setoption`
+    /// does; search margins are accepted under their `SearchParams` names.
+    /// Options that belong to the front end (move overhead, file paths)
+    /// aren't handled here. Returns a line describing the change.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<String, String> {
+        let flag = value.eq_ignore_ascii_case("true");
+        let invalid = || format!("invalid value for {}: {}", name, value);
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let size_mb = value.parse::<usize>().map_err(|_| invalid())?.clamp(MIN_HASH_MB, MAX_HASH_MB);
+                self.set_hash_size(size_mb);
+                Ok(format!("Hash table set to {} MB", size_mb))
+            }
+            "threads" => {
+                self.set_threads(value.parse().map_err(|_| invalid())?);
+                Ok(format!("Threads set to {}", self.threads))
+            }
+            "multipv" => {
+                self.set_multi_pv(value.parse().map_err(|_| invalid())?);
+                Ok(format!("MultiPV set to {}", self.multi_pv))
+            }
+            "contempt" => {
+                self.set_contempt(value.parse().map_err(|_| invalid())?);
+                Ok(format!("Contempt set to {}", self.contempt))
+            }
+            "uci_showwdl" => {
+                self.set_show_wdl(flag);
+                Ok(format!("UCI_ShowWDL set to {}", flag))
+            }
+            "ownbook" => {
+                self.set_own_book(flag);
+                Ok(format!("OwnBook set to {}", flag))
+            }
+            "bookbestmove" => {
+                self.set_book_best_move(flag);
+                Ok(format!("BookBestMove set to {}", flag))
+            }
+            "bookdepth" => {
+                self.set_book_depth(value.parse().map_err(|_| invalid())?);
+                Ok(format!("BookDepth set to {}", self.book_depth))
+            }
+            "bookseed" => {
+                let seed = value.parse().map_err(|_| invalid())?;
+                self.set_book_seed(seed);
+                Ok(format!("BookSeed set to {}", seed))
+            }
+            "skill level" => {
+                self.set_skill_level(value.parse().map_err(|_| invalid())?);
+                Ok(format!("Skill Level set to {}", self.skill_level))
+            }
+            "uci_limitstrength" => {
+                self.set_limit_strength(flag);
+                Ok(format!("UCI_LimitStrength set to {}", flag))
+            }
+            "uci_elo" => {
+                self.set_elo(value.parse().map_err(|_| invalid())?);
+                Ok(format!("UCI_Elo set to {}", self.elo))
+            }
+            "uci_analysemode" => {
+                self.set_analyse_mode(flag);
+                Ok(format!("UCI_AnalyseMode set to {}", flag))
+            }
+            "deterministic" => {
+                self.set_deterministic(flag);
+                Ok(format!("Deterministic set to {}", flag))
+            }
+            _ => {
+                let value = value.parse::<i32>().map_err(|_| invalid())?;
+                if self.search_params.set(name, value) {
+                    Ok(format!("{} set to {}", name, value))
+                } else {
+                    Err(format!("unknown option: {}", name))
+                }
             }
         }
-        None
     }
 
-    fn store(&mut self, hash: u64, depth: u8, score: i32, flag: u8, best_move: Option<Move>) {
-        let index = (hash as usize) % self.size;
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
 
-        // Replacement strategy
-        let should_replace = if let Some(entry) = &self.table[index] {
-            if entry.hash == hash {
-                // Always replace if same position
-                true
-            } else {
-                // Replace based on depth and age
-                let depth_diff = depth as i32 - entry.depth as i32;
-                let age_diff = self.current_age.wrapping_sub(entry.age);
-                
-                // Replace if:
-                // 1. Much deeper search
-                // 2. Old entry
-                // 3. Depth is similar and entry is old
-                depth_diff >= 3 || age_diff >= 4 || (depth_diff >= 0 && age_diff >= 2)
-            }
-        } else {
-            true
-        };
+    pub fn set_contempt(&mut self, contempt: i32) {
+        self.contempt = contempt.clamp(-100, 100);
+    }
 
-        if should_replace {
-            self.table[index] = Some(TTEntry {
-                hash,
-                depth,
-                score,
-                flag,
-                best_move,
-                age: self.current_age,
-            });
-        }
+    /// Sends search progress to `info` instead of printing UCI lines, and
+    /// hands back the previous reporter.
+    pub fn set_info(&mut self, info: Arc<dyn SearchInfo>) -> Arc<dyn SearchInfo> {
+        std::mem::replace(&mut self.info, info)
     }
 
-    fn clear(&mut self) {
-        self.table = vec![None; self.size];
-        self.current_age = 0;
+    pub fn set_show_wdl(&mut self, show_wdl: bool) {
+        self.show_wdl = show_wdl;
     }
 
-    fn resize(&mut self, size_mb: usize) {
-        self.size = (size_mb * 1024 * 1024) / std::mem::size_of::<Option<TTEntry>>();
-        self.table = vec![None; self.size];
-        self.current_age = 0;
+    pub fn set_own_book(&mut self, own_book: bool) {
+        self.own_book = own_book;
     }
 
-    #[allow(dead_code)]
-    fn increment_age(&mut self) {
-        self.current_age = self.current_age.wrapping_add(1);
+    pub fn set_book_best_move(&mut self, book_best_move: bool) {
+        self.book_best_move = book_best_move;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::board::BoardState;
+    /// Built-in book moves are played for game plies below `book_depth`;
+    /// a loaded Polyglot book is used as deep as it goes.
+    pub fn set_book_depth(&mut self, book_depth: u16) {
+        self.book_depth = book_depth.min(MAX_BOOK_DEPTH);
+    }
 
-    #[test]
-    fn test_search_basic() {
-        let board = BoardState::default();
-        let mut engine = SearchEngine::new(1);
-        let result = engine.search(board, 5, None);
-        assert!(result.best_move.is_some());
+    /// Restarts the weighted book picks from `seed`, so the same seed plays
+    /// the same book lines; 0 goes back to an unseeded generator.
+    pub fn set_book_seed(&mut self, seed: u64) {
+        self.book_rng = if seed == 0 { StdRng::from_entropy() } else { StdRng::seed_from_u64(seed) };
     }
 
-    #[test]
-    fn test_search_parallel() {
-        let board = BoardState::default();
-        let mut engine = SearchEngine::new(4);
-        let result = engine.search(board, 5, None);
-        assert!(result.best_move.is_some());
-        assert!(result.nodes > 0);
+    /// Analysis mode skips the book, scores draws as 0 whatever the
+    /// contempt, and always reports the deepest iteration's move.
+    pub fn set_analyse_mode(&mut self, analyse_mode: bool) {
+        self.analyse_mode = analyse_mode;
     }
 
-    #[test]
-    fn test_mate_in_one() {
-        // Scholar's mate setup: 1 move to checkmate
-        let fen = "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4";
-        let board = BoardState::from_fen(fen).unwrap();
-        let mut engine = SearchEngine::new(1);
-        let result = engine.search(board, 10, None);
+    /// 0 is the weakest level, `MAX_SKILL_LEVEL` full strength.
+    pub fn set_skill_level(&mut self, level: u8) {
+        self.skill_level = level.min(MAX_SKILL_LEVEL);
+    }
+
+    /// While on, the engine plays at the `set_elo` rating and the skill
+    /// level is ignored.
+    pub fn set_limit_strength(&mut self, limit_strength: bool) {
+        self.limit_strength = limit_strength;
+    }
+
+    pub fn set_elo(&mut self, elo: u32) {
+        self.elo = elo.clamp(MIN_ELO, MAX_ELO);
+    }
+
+    /// The skill level searches run at, taken from the Elo under
+    /// UCI_LimitStrength.
+    pub fn skill_level(&self) -> u8 {
+        if self.limit_strength {
+            ((self.elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 / (MAX_ELO - MIN_ELO)) as u8
+        } else {
+            self.skill_level
+        }
+    }
+
+    /// Caps each following search at `nodes` nodes over all threads, for
+    /// `go nodes`; None lifts the cap. A search cut short still returns
+    /// its last completed iteration.
+    pub fn set_node_limit(&mut self, nodes: Option<u64>) {
+        self.node_limit = nodes;
+    }
+
+    pub fn set_multi_pv(&mut self, count: usize) {
+        self.multi_pv = count.clamp(1, MAX_MULTI_PV);
+    }
+
+    /// Ends the current search. A search holds the engine for its whole
+    /// length, so a caller on another thread needs `stop_handle` instead.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Setting this flag ends the current search, from any thread.
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// Set before `search_ponder`; clearing it from any thread is ponderhit.
+    pub fn ponder_handle(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.pondering)
+    }
+
+    pub fn clear_tt(&mut self) {
+        self.tt.clear();
+    }
+
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.tt = Arc::new(TranspositionTable::new(size_mb.clamp(MIN_HASH_MB, MAX_HASH_MB)));
+    }
+}
+
+// Transposition Table Entry Flags
+const TT_EXACT: u8 = 0;
+const TT_ALPHA: u8 = 1;
+const TT_BETA: u8 = 2;
+const TT_EMPTY: u8 = 3;
+
+// Packed move value meaning "no move"; a1a1 can never be played
+const NO_MOVE: u16 = 0;
+
+// Static eval slot of an entry stored without one
+const NO_EVAL: i32 = i32::MIN;
+
+// Entries sharing one index
+const CLUSTER_SIZE: usize = 4;
+
+// Data word layout: move | eval | score | depth | flag
+const EVAL_SHIFT: u32 = 16;
+const SCORE_SHIFT: u32 = 32;
+const SCORE_BITS: u32 = 22;
+const DEPTH_SHIFT: u32 = 54;
+const FLAG_SHIFT: u32 = 62;
+
+// Low byte of the key word holds the age instead of hash bits; with a
+// power-of-two table those bits mostly pick the cluster anyway
+const AGE_MASK: u64 = 0xFF;
+
+/// An entry as read back out of the table.
+#[derive(Clone, Copy)]
+struct TTEntry {
+    depth: u8,
+    score: i32,
+    flag: u8,
+    best_move: u16,
+    age: u8,
+    // Static evaluation of the position, or NO_EVAL
+    eval: i32,
+}
+
+impl TTEntry {
+    fn is_empty(&self) -> bool {
+        self.flag == TT_EMPTY
+    }
+
+    fn best_move(&self) -> Option<Move> {
+        if self.best_move == NO_MOVE {
+            None
+        } else {
+            Some(Move::unpack(self.best_move))
+        }
+    }
+
+    fn pack(depth: u8, score: i32, flag: u8, best_move: u16, eval: i32) -> u64 {
+        let eval = if eval == NO_EVAL {
+            i16::MIN
+        } else {
+            eval.clamp(i16::MIN as i32 + 1, i16::MAX as i32) as i16
+        };
+        let score = score as u64 & ((1 << SCORE_BITS) - 1);
+
+        // The flag is stored XORed with TT_EMPTY so a zeroed slot reads as empty
+        best_move as u64
+            | (eval as u16 as u64) << EVAL_SHIFT
+            | score << SCORE_SHIFT
+            | (depth as u64) << DEPTH_SHIFT
+            | ((flag ^ TT_EMPTY) as u64) << FLAG_SHIFT
+    }
+
+    fn unpack(data: u64, age: u8) -> TTEntry {
+        let eval = (data >> EVAL_SHIFT) as u16 as i16;
+        // Sign-extend the score field
+        let score = ((data << (64 - SCORE_SHIFT - SCORE_BITS)) as i64 >> (64 - SCORE_BITS)) as i32;
+
+        TTEntry {
+            depth: (data >> DEPTH_SHIFT) as u8,
+            score,
+            flag: (data >> FLAG_SHIFT) as u8 ^ TT_EMPTY,
+            best_move: data as u16,
+            age,
+            eval: if eval == i16::MIN { NO_EVAL } else { eval as i32 },
+        }
+    }
+}
+
+/// One entry as two words: the packed data, and the hash XOR the data so a
+/// slot torn by two threads writing at once fails the hash check on probe.
+struct TTSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl TTSlot {
+    // The entry and the high hash bits it was stored under, if it is consistent
+    fn load(&self) -> (u64, TTEntry) {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        ((key ^ data) & !AGE_MASK, TTEntry::unpack(data, key as u8))
+    }
+
+    fn save(&self, hash: u64, data: u64, age: u8) {
+        self.data.store(data, Ordering::Relaxed);
+        self.key.store(((hash ^ data) & !AGE_MASK) | age as u64, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.key.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+// One cache line per cluster
+#[repr(align(64))]
+struct TTCluster {
+    slots: [TTSlot; CLUSTER_SIZE],
+}
+
+impl TTCluster {
+    fn new() -> Self {
+        TTCluster {
+            slots: std::array::from_fn(|_| TTSlot {
+                key: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+/// Shared by every search thread without a lock; a store racing another
+/// store or probe on the same slot costs at most that one entry.
+pub struct TranspositionTable {
+    table: Vec<TTCluster>,
+    size: usize,
+    current_age: AtomicU8,
+}
+
+impl TranspositionTable {
+    fn new(size_mb: usize) -> Self {
+        let size = Self::cluster_count(size_mb);
+        TranspositionTable {
+            table: (0..size).map(|_| TTCluster::new()).collect(),
+            size,
+            current_age: AtomicU8::new(0),
+        }
+    }
+
+    fn cluster_count(size_mb: usize) -> usize {
+        ((size_mb * 1024 * 1024) / std::mem::size_of::<TTCluster>()).max(1)
+    }
+
+    fn age(&self) -> u8 {
+        self.current_age.load(Ordering::Relaxed)
+    }
+
+    fn probe(&self, hash: u64) -> Option<TTEntry> {
+        let index = (hash as usize) % self.size;
+        self.table[index]
+            .slots
+            .iter()
+            .map(TTSlot::load)
+            .find(|(key, entry)| !entry.is_empty() && *key == hash & !AGE_MASK)
+            .map(|(_, entry)| entry)
+    }
+
+    fn store(&self, hash: u64, depth: u8, score: i32, flag: u8, best_move: Option<Move>, eval: i32) {
+        let index = (hash as usize) % self.size;
+        let current_age = self.age();
+        let slots = &self.table[index].slots;
+        let cluster: [(u64, TTEntry); CLUSTER_SIZE] = std::array::from_fn(|i| slots[i].load());
+
+        // An exact score from this search outranks a bound at no more depth,
+        // and any shallower entry
+        let protected = |e: &TTEntry| {
+            e.age == current_age && e.flag == TT_EXACT && (e.depth > depth || e.depth == depth && flag != TT_EXACT)
+        };
+
+        // Same position first, then an empty slot, then the least valuable
+        // entry by age and depth
+        let slot = if let Some(i) = cluster.iter().position(|(key, e)| !e.is_empty() && *key == hash & !AGE_MASK) {
+            if flag != TT_EXACT && protected(&cluster[i].1) {
+                return;
+            }
+            Some(i)
+        } else if let Some(i) = cluster.iter().position(|(_, e)| e.is_empty()) {
+            Some(i)
+        } else {
+            cluster
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, e))| !protected(e))
+                .min_by_key(|(_, (_, e))| {
+                    let age_diff = current_age.wrapping_sub(e.age) as i32;
+                    e.depth as i32 - 8 * age_diff
+                })
+                .map(|(i, _)| i)
+        };
+
+        if let Some(i) = slot {
+            let data = TTEntry::pack(depth, score, flag, best_move.map_or(NO_MOVE, |mv| mv.pack()), eval);
+            slots[i].save(hash, data, current_age);
+        }
+    }
+
+    /// Permille of sampled slots holding an entry from the current search.
+    fn hashfull(&self) -> usize {
+        let sample = self.size.min(1000);
+        let current_age = self.age();
+        let used = self.table[..sample]
+            .iter()
+            .flat_map(|cluster| cluster.slots.iter())
+            .map(|slot| slot.load().1)
+            .filter(|e| !e.is_empty() && e.age == current_age)
+            .count();
+        used * 1000 / (sample * CLUSTER_SIZE)
+    }
+
+    fn clear(&self) {
+        for slot in self.table.iter().flat_map(|cluster| cluster.slots.iter()) {
+            slot.reset();
+        }
+        self.current_age.store(0, Ordering::Relaxed);
+    }
+
+    fn increment_age(&self) {
+        self.current_age.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardState;
+
+    #[test]
+    fn test_search_basic() {
+        let board = BoardState::default();
+        let mut engine = SearchEngine::new(1);
+        let result = engine.search(board, 5, None);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_thread_nodes_sum_to_total() {
+        // Out of book so the threads actually search
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mut engine = SearchEngine::new(4);
+        let result = engine.search(board, 7, None);
+
+        assert_eq!(result.thread_nodes.len(), 4);
+        assert_eq!(result.thread_nodes.iter().sum::<u64>(), result.nodes);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn test_root_move_voting() {
+        let e4 = Move::new(12, 28, crate::movegen::DOUBLE_PAWN_PUSH);
+        let d4 = Move::new(11, 27, crate::movegen::DOUBLE_PAWN_PUSH);
+        let nf3 = Move::new(6, 21, crate::movegen::QUIET_MOVE);
+
+        // d4 has the single best score, but two threads agreeing on e4 outvote it
+        let results = vec![
+            (25, Some(e4), vec![e4], 9),
+            (35, Some(d4), vec![d4], 8),
+            (30, Some(e4), vec![e4, d4], 10),
+            (-INFINITY, None, vec![], 0),
+        ];
+        assert_eq!(SearchEngine::vote_root_move(&results), Some(2));
+
+        // Equal votes: the deeper thread's move wins
+        let results = vec![(2, Some(e4), vec![e4], 7), (0, Some(nf3), vec![nf3], 8)];
+        let index = SearchEngine::vote_root_move(&results).unwrap();
+        assert_eq!(results[index].1, Some(nf3));
+
+        assert_eq!(SearchEngine::vote_root_move(&[(-INFINITY, None, vec![], 0)]), None);
+    }
+
+    #[test]
+    fn test_deterministic_search_repeats() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mut engine = SearchEngine::new(4);
+        engine.set_deterministic(true);
+
+        let first = engine.search(board.clone(), 6, None);
+        let second = engine.search(board, 6, None);
+
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.thread_nodes[1..].iter().sum::<u64>(), 0);
+    }
+
+    #[test]
+    fn test_singular_extension_decision() {
+        // Rxd5 wins a rook; every other move just keeps the balance
+        let mut board = BoardState::from_fen("6k1/pp3ppp/8/3r4/8/8/PP3PPP/3R2K1 w - - 0 1").unwrap();
+        let capture = Move::new(3, 35, crate::movegen::CAPTURE);
+        let quiet = Move::new(3, 4, crate::movegen::QUIET_MOVE);
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+
+        let mut singular = |mv: Move, hash_score: i32| {
+            engine.is_singular(&mut board, mv, hash_score, 8, 1, 0, Instant::now(), None, None, &mut thread_data)
+        };
+
+        assert!(singular(capture, 400));
+        // Re1 is no better than the capture left in the search
+        assert!(!singular(quiet, 0));
+        assert_eq!(thread_data.excluded_move[1], None);
+    }
+
+    #[test]
+    fn test_probcut_decision() {
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+        let mut probcut = |board: &BoardState, probcut_beta: i32| {
+            let static_eval = engine.evaluate(board, &mut thread_data);
+            engine.probcut(board, 6, probcut_beta, static_eval, 1, 0, Instant::now(), None, None, &mut thread_data)
+        };
+
+        // Rxd5 wins a whole rook, which clears a beta around equality
+        let board = BoardState::from_fen("6k1/pp3ppp/8/3r4/8/8/PP3PPP/3R2K1 w - - 0 1").unwrap();
+        assert!(probcut(&board, 100).is_some_and(|score| score >= 100));
+        let entry = engine.tt.probe(board.hash).unwrap();
+        assert_eq!(entry.best_move().map(|mv| mv.to_uci()), Some("d1d5".to_string()));
+        assert_eq!(entry.flag, TT_BETA);
+
+        // but not one above a rook, and no capture at all clears anything
+        assert_eq!(probcut(&board, 1000), None);
+        assert_eq!(probcut(&BoardState::default(), -100), None);
+    }
+
+    #[test]
+    fn test_futility_margin_from_search_params() {
+        let mut engine = SearchEngine::new(1);
+        let defaults = SearchParams::default();
+        assert_eq!(engine.futility_margin(3, true), defaults.futility_base + 3 * defaults.futility_per_depth);
+        // One more depth's worth when the eval is not improving
+        assert_eq!(engine.futility_margin(3, false), defaults.futility_base + 4 * defaults.futility_per_depth);
+
+        assert!(engine.search_params_mut().set("FutilityBase", 40));
+        assert!(engine.search_params_mut().set("FutilityPerDepth", 100));
+        assert_eq!(engine.futility_margin(2, true), 240);
+        assert!(!SearchParams::default().set("NoSuchMargin", 1));
+    }
+
+    #[test]
+    fn test_late_move_pruning_decision() {
+        let mut engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+        let quiet = Move::new(12, 20, crate::movegen::QUIET_MOVE);
+        let capture = Move::new(12, 21, crate::movegen::CAPTURE);
+
+        // At depth 2 the first 7 moves are always searched
+        assert!(engine.late_move_prunable(&quiet, 2, 7, None, 3, &thread_data));
+        assert!(!engine.late_move_prunable(&quiet, 2, 6, None, 3, &thread_data));
+        assert!(!engine.late_move_prunable(&quiet, 5, 40, None, 3, &thread_data));
+        assert!(!engine.late_move_prunable(&capture, 2, 7, None, 3, &thread_data));
+
+        // Hash, killer and counter moves are kept however late they come
+        assert!(!engine.late_move_prunable(&quiet, 2, 7, Some(quiet), 3, &thread_data));
+        engine.update_killers_internal(quiet, 3, &mut thread_data);
+        assert!(!engine.late_move_prunable(&quiet, 2, 7, None, 3, &thread_data));
+        assert!(engine.late_move_prunable(&quiet, 2, 7, None, 4, &thread_data));
+
+        thread_data.move_stack[3] = Some(Move::new(52, 36, crate::movegen::DOUBLE_PAWN_PUSH));
+        engine.update_countermove(quiet, 4, &mut thread_data);
+        assert!(!engine.late_move_prunable(&quiet, 2, 7, None, 4, &thread_data));
+        // A null move before the node has no countermove
+        thread_data.move_stack[3] = None;
+        assert!(engine.late_move_prunable(&quiet, 2, 7, None, 4, &thread_data));
+
+        engine.late_move_pruning = false;
+        assert!(!engine.late_move_prunable(&quiet, 2, 7, None, 4, &thread_data));
+    }
+
+    #[test]
+    fn test_contempt_avoids_repetition() {
+        // Knight and king can't win, so every non-repeating line scores 0.
+        // Ng1-f3 now brings about the position after Nf3 a third time.
+        let mut board = BoardState::from_fen("4k3/8/8/8/8/8/8/4K1N1 w - - 0 1").unwrap();
+        for _ in 0..2 {
+            for uci in ["g1f3", "e8d8", "f3g1", "d8e8"] {
+                assert!(board.make_move_uci(uci).unwrap());
+            }
+        }
+        let repeat = Move::new(6, 21, crate::movegen::QUIET_MOVE);
+
+        let mut engine = SearchEngine::new(1);
+        engine.set_contempt(50);
+        let result = engine.search(board.clone(), 3, None);
+        assert_ne!(result.best_move, Some(repeat));
+        assert_eq!(result.score, 0);
+
+        // Negative contempt: the repetition is now the best line
+        let mut engine = SearchEngine::new(1);
+        engine.set_contempt(-50);
+        let result = engine.search(board, 3, None);
+        assert_eq!(result.best_move, Some(repeat));
+        assert_eq!(result.score, 50);
+    }
+
+    #[test]
+    fn test_perpetual_check_is_a_draw() {
+        // Two rooks down, but Qh5+ Kg8 Qe8+ Kh7 checks forever
+        let board = BoardState::from_fen("8/6pk/8/8/8/8/rr6/3Q2K1 w - - 0 1").unwrap();
+        let mut engine = SearchEngine::new(1);
+        let result = engine.search(board.clone(), 6, None);
+        assert_eq!(result.score, 0);
+
+        let mut after = board;
+        after.make_move(&result.best_move.unwrap());
+        assert!(after.is_in_check(after.side_to_move));
+    }
+
+    #[test]
+    fn test_set_option_contempt_changes_draw_score() {
+        // Perpetual check is the best white has, so the score is the draw score
+        let board = BoardState::from_fen("8/6pk/8/8/8/8/rr6/3Q2K1 w - - 0 1").unwrap();
+        let mut engine = SearchEngine::new(1);
+        assert_eq!(engine.set_option("Contempt", "50"), Ok("Contempt set to 50".to_string()));
+        assert_eq!(engine.search(board, 6, None).score, -50);
+
+        assert!(engine.set_option("Contempt", "lots").is_err());
+        assert!(engine.set_option("NoSuchOption", "1").is_err());
+        assert!(engine.set_option("futilitybase", "200").is_ok());
+        assert_eq!(engine.search_params.futility_base, 200);
+    }
+
+    #[test]
+    fn test_set_eval_param_drops_incremental_psq() {
+        let mut engine = SearchEngine::new(1);
+        assert!(engine.incremental_psq);
+        assert!(engine.set_eval_param("Rook_Open_File", 40));
+        assert_eq!(engine.eval_params().rook_open_file, 40);
+        assert!(engine.incremental_psq);
+
+        // New piece values no longer match the totals kept on the board
+        assert!(engine.set_eval_param("pawn_value", 120));
+        assert!(!engine.incremental_psq);
+        assert!(!engine.set_eval_param("pawn_pst_mg", 1));
+    }
+
+    #[test]
+    fn test_set_eval_param_clears_hash() {
+        let board = BoardState::default();
+        let mut engine = SearchEngine::new(1);
+        engine.set_own_book(false);
+        let stored = |engine: &SearchEngine| {
+            MoveGenerator::generate_legal_moves(&board).iter().any(|mv| {
+                let mut child = board.clone();
+                child.make_move(mv);
+                engine.tt.probe(child.hash).is_some()
+            })
+        };
+
+        engine.search(board.clone(), 4, None);
+        assert!(stored(&engine));
+
+        // Those entries' static evals were scored with the old weights
+        assert!(engine.set_eval_param("knight_value", 350));
+        assert!(!stored(&engine));
+
+        // An unknown name changes nothing
+        engine.search(board.clone(), 4, None);
+        assert!(!engine.set_eval_param("no_such_weight", 1));
+        assert!(stored(&engine));
+    }
+
+    #[cfg(feature = "nnue")]
+    #[test]
+    fn test_eval_file_scores_with_the_net() {
+        // One hidden unit, all weights zero: every position scores the
+        // output bias, 400cp at this scale
+        let mut bytes = b"RCNN".to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(vec![0u8; 2 * (4 * 2 * 6 * 64 + 1 + 2)]);
+        bytes.extend((255i32 * 64).to_le_bytes());
+        let path = std::env::temp_dir().join("chess_engine_eval_file_test.nnue");
+        std::fs::write(&path, bytes).unwrap();
+
+        let board = BoardState::default();
+        let mut engine = SearchEngine::new(2);
+        engine.set_own_book(false);
+        let evals = |engine: &SearchEngine| {
+            MoveGenerator::generate_legal_moves(&board)
+                .iter()
+                .filter_map(|mv| {
+                    let mut child = board.clone();
+                    child.make_move(mv);
+                    engine.tt.probe(child.hash).map(|entry| entry.eval).filter(|&eval| eval != NO_EVAL)
+                })
+                .collect::<Vec<i32>>()
+        };
+
+        engine.set_eval_file(path.to_str().unwrap()).unwrap();
+        engine.search(board.clone(), 4, None);
+        let with_net = evals(&engine);
+        assert!(!with_net.is_empty());
+        assert!(with_net.iter().all(|&eval| eval == 400));
+
+        engine.set_eval_file("").unwrap();
+        engine.search(board.clone(), 4, None);
+        assert!(evals(&engine).iter().any(|&eval| eval != 400));
+
+        assert!(engine.set_eval_file("no/such/file.nnue").is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_own_book_off_searches() {
+        let mut engine = SearchEngine::new(1);
+        engine.set_own_book(false);
+        let result = engine.search(BoardState::default(), 3, None);
+        assert!(result.best_move.is_some());
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn test_book_best_move_is_stable() {
+        let mut engine = SearchEngine::new(1);
+        engine.set_book_best_move(true);
+        for _ in 0..10 {
+            let result = engine.search(BoardState::default(), 3, None);
+            assert_eq!(result.best_move.map(|mv| mv.to_uci()).as_deref(), Some("e2e4"));
+            assert_eq!(result.nodes, 0);
+        }
+    }
+
+    #[test]
+    fn test_book_depth_replaces_fullmove_cutoff() {
+        // The position after 1.e4, with clocks putting it at move 16
+        let board = BoardState::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 16").unwrap();
+        let mut engine = SearchEngine::new(1);
+        engine.set_book_best_move(true);
+
+        let result = engine.search(board.clone(), 3, None);
+        assert!(result.nodes > 0);
+
+        assert!(engine.set_option("BookDepth", "40").is_ok());
+        let result = engine.search(board.clone(), 3, None);
+        assert_eq!(result.nodes, 0);
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), opening_book::probe_book_best(&board));
+
+        // Depth 0 turns the book off from the first move
+        engine.set_book_depth(0);
+        assert!(engine.search(BoardState::default(), 3, None).nodes > 0);
+    }
+
+    #[test]
+    fn test_book_depth_leaves_polyglot_book_alone() {
+        let _book = opening_book::tests::POLYGLOT_LOCK.lock();
+        // Move 40, far past the default BookDepth
+        let board = BoardState::from_fen("r4rk1/pp3ppp/2n5/3p4/3P4/2N5/PP3PPP/R4RK1 w - - 0 40").unwrap();
+        let path = std::env::temp_dir().join("chess_engine_book_depth_test.bin");
+        std::fs::write(&path, opening_book::tests::polyglot_entry(&board, "c3", "b5", 1)).unwrap();
+        assert_eq!(opening_book::load_polyglot(path.to_str().unwrap()), Ok(1));
+
+        let mut engine = SearchEngine::new(1);
+        engine.set_book_best_move(true);
+        let result = engine.search(board.clone(), 3, None);
+        opening_book::unload_polyglot();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()).as_deref(), Some("c3b5"));
+        assert_eq!(result.nodes, 0);
+    }
+
+    #[test]
+    fn test_book_seed_repeats_book_lines() {
+        let book_line = |seed: u64| {
+            let mut engine = SearchEngine::new(1);
+            assert!(engine.set_option("BookSeed", &seed.to_string()).is_ok());
+            (0..30)
+                .map(|_| engine.search(BoardState::default(), 3, None).best_move.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(book_line(7), book_line(7));
+        assert_ne!(book_line(7), book_line(8));
+
+        // Reseeding starts the sequence over
+        let mut engine = SearchEngine::new(1);
+        engine.set_book_seed(7);
+        let first = engine.search(BoardState::default(), 3, None).best_move;
+        engine.set_book_seed(7);
+        assert_eq!(engine.search(BoardState::default(), 3, None).best_move, first);
+        assert_eq!(first, book_line(7).first().copied());
+    }
+
+    #[test]
+    fn test_analyse_mode_ignores_book_and_contempt() {
+        let mut engine = SearchEngine::new(1);
+        engine.set_book_best_move(true);
+        engine.set_analyse_mode(true);
+        let result = engine.search(BoardState::default(), 3, None);
+        assert!(result.best_move.is_some());
+        assert!(result.nodes > 0);
+
+        engine.set_contempt(50);
+        assert_eq!(engine.draw_score(0), 0);
+        engine.set_analyse_mode(false);
+        assert_eq!(engine.draw_score(0), -50);
+    }
+
+    // Keeps what a search reports instead of printing it
+    #[derive(Default)]
+    struct RecordingInfo {
+        depths: Mutex<Vec<u8>>,
+        // (depth, multipv, first move) of each reported line
+        lines: Mutex<Vec<(u8, usize, Move)>>,
+        currmoves: Mutex<Vec<(u8, Move, usize)>>,
+    }
+
+    impl SearchInfo for RecordingInfo {
+        fn on_iteration(&self, info: &IterationInfo) {
+            if info.multipv == 1 {
+                self.depths.lock().push(info.depth);
+            }
+            self.lines.lock().push((info.depth, info.multipv, info.pv[0]));
+        }
+
+        fn on_currmove(&self, _root: &BoardState, depth: u8, mv: Move, move_number: usize) {
+            self.currmoves.lock().push((depth, mv, move_number));
+        }
+
+        fn on_string(&self, _msg: &str) {}
+    }
+
+    #[test]
+    fn test_multipv_reports_distinct_lines() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let info = Arc::new(RecordingInfo::default());
+        let mut engine = SearchEngine::new(1);
+        engine.set_info(info.clone());
+        assert!(engine.set_option("MultiPV", "3").is_ok());
+
+        let result = engine.search(board, 4, None);
+        let lines = info.lines.lock();
+        let last: Vec<_> = lines.iter().filter(|line| line.0 == 4).collect();
+        assert_eq!(last.iter().map(|line| line.1).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(last[0].2 != last[1].2 && last[0].2 != last[2].2 && last[1].2 != last[2].2);
+
+        assert_eq!(result.pv_lines.len(), 3);
+        assert_eq!(result.best_move, Some(result.pv_lines[0].0));
+        assert!(result.pv_lines[0].1 >= result.pv_lines[1].1);
+        assert!(result.pv_lines[1].1 >= result.pv_lines[2].1);
+    }
+
+    #[test]
+    fn test_pv_is_playable() {
+        // Lines copied up from cut-off children used to carry moves left over
+        // from an earlier sibling
+        for fen in [
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+        ] {
+            let mut engine = SearchEngine::new(1);
+            engine.set_multi_pv(3);
+            let result = engine.search(BoardState::from_fen(fen).unwrap(), 6, None);
+
+            let mut board = BoardState::from_fen(fen).unwrap();
+            for mv in &result.pv {
+                assert!(
+                    MoveGenerator::generate_legal_moves(&board).contains(mv),
+                    "{} in {:?} from {}",
+                    mv.to_uci(),
+                    result.pv.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>(),
+                    fen
+                );
+                board.make_move(mv);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_info_gets_each_depth() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let info = Arc::new(RecordingInfo::default());
+        let mut engine = SearchEngine::new(1);
+        engine.set_info(info.clone());
+
+        let result = engine.search(board, 5, None);
+        assert_eq!(*info.depths.lock(), [1, 2, 3, 4, 5]);
+        assert_eq!(info.lines.lock().last().map(|line| line.2), result.best_move);
+        // Too quick for currmove reports
+        assert!(info.currmoves.lock().is_empty());
+    }
+
+    #[test]
+    fn test_currmove_reports() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let info = Arc::new(RecordingInfo::default());
+        let mut engine = SearchEngine::new(1);
+        engine.set_info(info.clone());
+        engine.currmove_delay = Duration::ZERO;
+
+        engine.search(board, 3, None);
+        let currmoves = info.currmoves.lock();
+        assert!(!currmoves.is_empty());
+        // Numbered from one at each depth
+        assert_eq!(currmoves[0].0, 1);
+        assert_eq!(currmoves[0].2, 1);
+        assert!(currmoves.iter().any(|&(depth, _, number)| depth == 3 && number > 1));
+    }
+
+    #[test]
+    fn test_stop_handle_ends_search() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mut engine = SearchEngine::new(1);
+        let stop = engine.stop_handle();
+
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            stop.store(true, Ordering::Relaxed);
+        });
+
+        let start = Instant::now();
+        let result = engine.search(board, 64, None);
+        stopper.join().unwrap();
+
+        assert!(result.best_move.is_some());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // stop() raises the same flag; the next search clears it again
+        engine.stop();
+        assert!(engine.stop_handle().load(Ordering::Relaxed));
+        assert!(engine.search(BoardState::default(), 2, None).best_move.is_some());
+        assert!(!engine.stop_handle().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_infinite_search_runs_until_stop() {
+        // Mate in one: every iteration finishes at once, yet the search
+        // still waits to be stopped
+        let board = BoardState::from_fen("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1").unwrap();
+        let info = Arc::new(RecordingInfo::default());
+        let mut engine = SearchEngine::new(1);
+        engine.set_info(info.clone());
+        let stop = engine.stop_handle();
+
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            stop.store(true, Ordering::Relaxed);
+        });
+
+        let start = Instant::now();
+        let result = engine.search_infinite(board);
+        stopper.join().unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("d1d8".to_string()));
+        assert!(!info.depths.lock().is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_searches_keep_their_own_nodes() {
+        let fens = [
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8",
+            "6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1",
+        ];
+        let solo = |fen: &str| {
+            let mut engine = SearchEngine::new(1);
+            engine.set_deterministic(true);
+            engine.search(BoardState::from_fen(fen).unwrap(), 6, None).nodes
+        };
+        let expected: Vec<u64> = fens.iter().map(|fen| solo(fen)).collect();
+
+        let mut engine = SearchEngine::new(1);
+        engine.set_deterministic(true);
+        let engine = Arc::new(Mutex::new(engine));
+
+        let handles: Vec<_> = fens
+            .iter()
+            .map(|fen| {
+                let engine = Arc::clone(&engine);
+                let board = BoardState::from_fen(fen).unwrap();
+                std::thread::spawn(move || engine.lock().search(board, 6, None).nodes)
+            })
+            .collect();
+        let nodes: Vec<u64> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+
+        assert_eq!(nodes, expected);
+    }
+
+    #[test]
+    fn test_pool_follows_threads() {
+        let mut engine = SearchEngine::new(4);
+        assert_eq!(engine.pool.current_num_threads(), 4);
+
+        engine.set_own_book(false);
+
+        // Below the Lazy SMP depth the root is searched on the caller's
+        // thread; from there on, only on the pool's, which is the size
+        // set_threads asked for
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        for threads in [2, 3] {
+            engine.set_threads(threads);
+            let pool_ids: std::collections::HashSet<_> =
+                engine.pool.broadcast(|_| std::thread::current().id()).into_iter().collect();
+            assert_eq!(pool_ids.len(), threads);
+
+            engine.root_workers.lock().clear();
+            assert!(engine.search(board.clone(), 7, None).best_move.is_some());
+            let mut workers = engine.root_workers.lock().clone();
+            assert!(workers.remove(&std::thread::current().id()));
+            assert!(!workers.is_empty());
+            assert!(workers.is_subset(&pool_ids), "{:?} not in {:?}", workers, pool_ids);
+        }
+    }
+
+    #[test]
+    fn test_search_parallel() {
+        let board = BoardState::default();
+        let mut engine = SearchEngine::new(4);
+        let result = engine.search(board, 5, None);
+        assert!(result.best_move.is_some());
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn test_mate_in_one() {
+        // Scholar's mate setup: 1 move to checkmate
+        let fen = "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4";
+        let board = BoardState::from_fen(fen).unwrap();
+        let mut engine = SearchEngine::new(1);
+        let result = engine.search(board, 10, None);
         
         // Should find a defensive move or recognize it's mate
         assert!(result.score < -MATE_SCORE + 100 || result.best_move.is_some());
     }
 
+    #[test]
+    fn test_only_move_mating_gets_mate_score() {
+        // In check from the a8 bishop, Rxa8 is the one legal reply and mates
+        let board = BoardState::from_fen("b5k1/5ppp/8/8/8/8/4n2P/R6K w - - 0 1").unwrap();
+        assert_eq!(MoveGenerator::generate_legal_moves(&board).len(), 1);
+        let mut engine = SearchEngine::new(1);
+        let result = engine.search(board, 20, None);
+
+        assert_eq!(result.best_move.map(|mv| mv.to_uci()), Some("a1a8".to_string()));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
     #[test]
     fn test_time_management() {
         let board = BoardState::default();
@@ -1134,16 +2986,245 @@ mod tests {
 
     #[test]
     fn test_transposition_table() {
-        let mut tt = TranspositionTable::new(16);
+        let tt = TranspositionTable::new(16);
         let test_move = Move::new(12, 20, 0);
         
-        tt.store(12345, 5, 100, TT_EXACT, Some(test_move));
+        tt.store(12345, 5, 100, TT_EXACT, Some(test_move), NO_EVAL);
         
         let entry = tt.probe(12345);
         assert!(entry.is_some());
         assert_eq!(entry.unwrap().score, 100);
     }
 
+    #[test]
+    fn test_tt_same_position_overwrite() {
+        let tt = TranspositionTable::new(1);
+        tt.store(42, 8, 100, TT_BETA, None, NO_EVAL);
+        tt.store(42, 2, -50, TT_ALPHA, None, NO_EVAL);
+
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.score, -50);
+        assert_eq!(entry.depth, 2);
+    }
+
+    #[test]
+    fn test_tt_exact_entry_kept_over_bounds() {
+        let tt = TranspositionTable::new(1);
+        tt.store(42, 8, 100, TT_EXACT, None, NO_EVAL);
+
+        // A bound at equal or lower depth leaves it alone
+        tt.store(42, 8, -50, TT_BETA, None, NO_EVAL);
+        tt.store(42, 2, -50, TT_ALPHA, None, NO_EVAL);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!((entry.score, entry.depth, entry.flag), (100, 8, TT_EXACT));
+
+        // A deeper bound or another exact score replaces it
+        tt.store(42, 9, -50, TT_BETA, None, NO_EVAL);
+        assert_eq!(tt.probe(42).unwrap().flag, TT_BETA);
+        tt.store(43, 8, 100, TT_EXACT, None, NO_EVAL);
+        tt.store(43, 3, 20, TT_EXACT, None, NO_EVAL);
+        assert_eq!(tt.probe(43).unwrap().score, 20);
+
+        // So does anything once the entry is from an earlier search
+        tt.store(44, 8, 100, TT_EXACT, None, NO_EVAL);
+        tt.increment_age();
+        tt.store(44, 2, -50, TT_ALPHA, None, NO_EVAL);
+        assert_eq!(tt.probe(44).unwrap().flag, TT_ALPHA);
+    }
+
+    #[test]
+    fn test_tt_static_eval_round_trip() {
+        let tt = TranspositionTable::new(16);
+        tt.store(555, 4, 30, TT_BETA, None, -37);
+        assert_eq!(tt.probe(555).unwrap().eval, -37);
+
+        tt.store(556, 4, 30, TT_BETA, None, NO_EVAL);
+        assert_eq!(tt.probe(556).unwrap().eval, NO_EVAL);
+    }
+
+    #[test]
+    fn test_tt_depth_preferred_replacement() {
+        let tt = TranspositionTable::new(1);
+        let size = tt.size as u64;
+
+        // Fill one cluster with bound entries of depth 1..=4
+        for i in 0..CLUSTER_SIZE as u64 {
+            tt.store(5 + i * size, i as u8 + 1, 0, TT_BETA, None, NO_EVAL);
+        }
+        tt.store(5 + 4 * size, 3, 0, TT_BETA, None, NO_EVAL);
+
+        // The shallowest entry makes room
+        assert!(tt.probe(5).is_none());
+        for i in 1..=4u64 {
+            assert!(tt.probe(5 + i * size).is_some());
+        }
+    }
+
+    #[test]
+    fn test_tt_deep_exact_entry_survives() {
+        let tt = TranspositionTable::new(1);
+        let size = tt.size as u64;
+
+        for i in 0..CLUSTER_SIZE as u64 {
+            tt.store(9 + i * size, 12, 0, TT_EXACT, None, NO_EVAL);
+        }
+        tt.store(9 + 4 * size, 2, 0, TT_ALPHA, None, NO_EVAL);
+
+        assert!(tt.probe(9 + 4 * size).is_none());
+        for i in 0..CLUSTER_SIZE as u64 {
+            assert_eq!(tt.probe(9 + i * size).unwrap().depth, 12);
+        }
+
+        // Entries from an older search are fair game
+        tt.increment_age();
+        tt.store(9 + 4 * size, 2, 0, TT_ALPHA, None, NO_EVAL);
+        assert!(tt.probe(9 + 4 * size).is_some());
+    }
+
+    #[test]
+    fn test_tt_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for hash in 0..tt.size as u64 {
+            tt.store(hash, 1, 0, TT_EXACT, None, NO_EVAL);
+        }
+        assert_eq!(tt.hashfull(), 250);
+    }
+
+    #[test]
+    fn test_tt_move_round_trip() {
+        let tt = TranspositionTable::new(16);
+        let promo = Move::new(52, 61, crate::movegen::QUEEN_PROMO_CAPTURE);
+
+        tt.store(777, 4, 0, TT_BETA, Some(promo), NO_EVAL);
+        assert_eq!(tt.probe(777).unwrap().best_move(), Some(promo));
+
+        tt.store(778, 4, 0, TT_ALPHA, None, NO_EVAL);
+        assert_eq!(tt.probe(778).unwrap().best_move(), None);
+    }
+
+    #[test]
+    fn test_tt_concurrent_entries_stay_consistent() {
+        // Every field is a function of the hash, so a torn entry shows up as
+        // a mismatch
+        fn mix(mut x: u64) -> u64 {
+            x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+            x ^ (x >> 31)
+        }
+        fn fields(hash: u64) -> (u8, i32, u8, Option<Move>, i32) {
+            let depth = (hash >> 8) as u8;
+            let score = ((hash >> 16) % (2 * INFINITY as u64)) as i32 - INFINITY;
+            let flag = ((hash >> 40) % 3) as u8;
+            let from = (hash >> 44) as u8 % 64;
+            let mv = Move::new(from, (from + 1 + (hash >> 50) as u8 % 63) % 64, crate::movegen::QUIET_MOVE);
+            let eval = ((hash >> 32) % 60000) as i32 - 30000;
+            (depth, score, flag, Some(mv), eval)
+        }
+
+        let tt = TranspositionTable::new(1);
+        // More positions than slots, so threads keep overwriting each other
+        let positions = (tt.size * CLUSTER_SIZE * 2) as u64;
+
+        std::thread::scope(|s| {
+            for t in 0..4u64 {
+                let tt = &tt;
+                s.spawn(move || {
+                    for i in 0..200_000u64 {
+                        let hash = mix(mix(t * 1_000_003 + i) % positions);
+                        let (depth, score, flag, mv, eval) = fields(hash);
+                        tt.store(hash, depth, score, flag, mv, eval);
+
+                        let probed = mix(mix(t + i * 7) % positions);
+                        if let Some(entry) = tt.probe(probed) {
+                            let (depth, score, flag, mv, eval) = fields(probed);
+                            assert_eq!(entry.depth, depth);
+                            assert_eq!(entry.score, score);
+                            assert_eq!(entry.flag, flag);
+                            assert_eq!(entry.best_move(), mv);
+                            assert_eq!(entry.eval, eval);
+                        }
+                    }
+                });
+            }
+        });
+
+        assert!(tt.hashfull() > 0);
+    }
+
+    #[test]
+    fn test_ponder_clock_starts_at_ponderhit() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mut engine = SearchEngine::new(1);
+        let ponder = engine.ponder_handle();
+
+        // 100ms of our own time, after 400ms on the opponent's
+        ponder.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        let ponderhit = std::thread::spawn({
+            let ponder = Arc::clone(&ponder);
+            move || {
+                std::thread::sleep(Duration::from_millis(400));
+                ponder.store(false, Ordering::Relaxed);
+            }
+        });
+        let result = engine.search_ponder(board.clone(), MAX_SEARCH_DEPTH, Some(100));
+        ponderhit.join().unwrap();
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400), "returned after {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1500), "returned after {:?}", elapsed);
+        assert!(result.best_move.is_some());
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+
+        // Without a ponderhit only stop ends it
+        ponder.store(true, Ordering::Relaxed);
+        let stop = engine.stop_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        engine.search_ponder(board, MAX_SEARCH_DEPTH, Some(50));
+        stopper.join().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert!(!ponder.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_delta_pruning_per_capture() {
+        let engine = SearchEngine::new(1);
+        // White can take either the queen on d5 or the pawn on a6
+        let board = BoardState::from_fen("4k3/8/p7/3q4/8/8/B3Q3/4K3 w - - 0 1").unwrap();
+        let moves = MoveGenerator::generate_captures(&board);
+        let take = |to: &str| *moves.iter().find(|mv| mv.to_uci().ends_with(to)).unwrap();
+        let (queen, pawn) = (take("d5"), take("a6"));
+
+        // Needing 500 over the stand pat, the queen is still worth a look
+        let margin = engine.search_params.delta_margin;
+        assert!(!engine.delta_prunes(&board, &queen, 0, 500));
+        assert!(engine.delta_prunes(&board, &pawn, 0, 500));
+        // The margin is what separates a small capture from a hopeless one
+        assert!(!engine.delta_prunes(&board, &pawn, 0, 100 + margin - 1));
+        assert!(engine.delta_prunes(&board, &queen, 0, 900 + margin));
+        assert!(!SearchEngine::can_promote(&board));
+    }
+
+    #[test]
+    fn test_qsearch_not_delta_pruned_before_promotion() {
+        // A queen and rook down, but gxh8=Q+ takes the queen and promotes:
+        // more than any flat delta allows for
+        let board = BoardState::from_fen("r3k2q/6P1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(SearchEngine::can_promote(&board));
+        assert!(SearchEngine::can_promote(&board.mirrored()));
+
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+        let stand_pat = engine.evaluate(&board, &mut thread_data);
+        let alpha = stand_pat + 1000;
+        assert!(engine.quiescence(&board, alpha, alpha + 1, 0, 0, &mut thread_data) > alpha);
+    }
+
     #[test]
     fn test_thread_scaling() {
         let board = BoardState::default();
@@ -1165,6 +3246,135 @@ mod tests {
         assert!(time4 < time1);
     }
 
+    #[test]
+    fn test_stop_keeps_completed_move() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let board = BoardState::from_fen(fen).unwrap();
+        let legal = MoveGenerator::generate_legal_moves(&board);
+        let mut engine = SearchEngine::new(1);
+
+        let stop = engine.stop.clone();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            stop.store(true, Ordering::Relaxed);
+        });
+
+        let result = engine.search(board, 64, None);
+        stopper.join().unwrap();
+
+        let best = result.best_move.expect("stopped search must still return a move");
+        assert!(legal.contains(&best));
+    }
+
+    #[test]
+    fn test_node_count_is_exact() {
+        // Out of book so the search actually runs
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let board = BoardState::from_fen(fen).unwrap();
+        let mut engine = SearchEngine::new(2);
+        let result = engine.search(board, 6, None);
+
+        let per_thread: u64 = engine.thread_data.iter()
+            .map(|thread_data| thread_data.lock().nodes_searched)
+            .sum();
+        assert!(result.nodes > 0);
+        assert_eq!(result.nodes, per_thread);
+    }
+
+    #[test]
+    fn test_node_limit_ends_search() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let board = BoardState::from_fen(fen).unwrap();
+        let legal = MoveGenerator::generate_legal_moves(&board);
+        let mut engine = SearchEngine::new(1);
+        engine.set_deterministic(true);
+        engine.set_node_limit(Some(20_000));
+
+        // Depth alone would run for minutes
+        let first = engine.search(board.clone(), 64, None);
+        assert_eq!(first.nodes, 20_000);
+        assert!(legal.contains(&first.best_move.unwrap()));
+
+        // Same budget, same answer
+        let second = engine.search(board.clone(), 64, None);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.pv, second.pv);
+
+        engine.set_node_limit(None);
+        assert!(engine.search(board, 6, None).nodes > 20_000);
+    }
+
+    #[test]
+    fn test_skill_level_from_elo() {
+        let mut engine = SearchEngine::new(1);
+        assert_eq!(engine.skill_level(), MAX_SKILL_LEVEL);
+
+        engine.set_skill_level(7);
+        assert_eq!(engine.skill_level(), 7);
+
+        // The Elo only counts under UCI_LimitStrength
+        engine.set_elo(MIN_ELO);
+        assert_eq!(engine.skill_level(), 7);
+        engine.set_limit_strength(true);
+        assert_eq!(engine.skill_level(), 0);
+        engine.set_elo(MAX_ELO + 500);
+        assert_eq!(engine.skill_level(), MAX_SKILL_LEVEL);
+    }
+
+    #[test]
+    fn test_low_skill_varies_its_move() {
+        let fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8";
+        let board = BoardState::from_fen(fen).unwrap();
+        let legal = MoveGenerator::generate_legal_moves(&board);
+        let mut engine = SearchEngine::new(1);
+        engine.set_own_book(false);
+        engine.set_skill_level(0);
+
+        let mut moves = Vec::new();
+        for _ in 0..20 {
+            let result = engine.search(board.clone(), 64, None);
+            assert!(result.nodes <= 1000);
+            assert!(legal.contains(&result.best_move.unwrap()));
+            moves.push(result.best_move);
+        }
+        moves.sort_by_key(|mv| mv.map(|mv| mv.to_uci()));
+        moves.dedup();
+        assert!(moves.len() > 1);
+
+        // Deterministic mode repeats even a weakened pick
+        engine.set_deterministic(true);
+        let first = engine.search(board.clone(), 64, None);
+        let second = engine.search(board, 64, None);
+        assert_eq!(first.best_move, second.best_move);
+    }
+
+    #[test]
+    fn test_weakened_search_keeps_pv_and_hash_clean() {
+        let board = BoardState::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8").unwrap();
+        let mut engine = SearchEngine::new(1);
+        engine.set_own_book(false);
+        engine.set_skill_level(10);
+
+        // The picked move brings its own line along, ponder move included
+        for _ in 0..5 {
+            let result = engine.search(board.clone(), 64, None);
+            assert_eq!(result.pv.first(), result.best_move.as_ref());
+            assert!(result.pv.len() > 1);
+        }
+
+        // Back at full strength no noisy eval is left in the hash
+        engine.set_skill_level(MAX_SKILL_LEVEL);
+        engine.search(board.clone(), 2, None);
+        let mut thread_data = ThreadData::new();
+        for mv in MoveGenerator::generate_legal_moves(&board) {
+            let mut child = board.clone();
+            child.make_move(&mv);
+            if let Some(entry) = engine.tt.probe(child.hash).filter(|entry| entry.eval != NO_EVAL) {
+                assert_eq!(entry.eval, engine.evaluate(&child, &mut thread_data));
+            }
+        }
+    }
+
     #[test]
     fn test_lmr_table() {
         // Verify LMR table is reasonable
@@ -1173,6 +3383,28 @@ mod tests {
         assert!(LMR_TABLE[20][30] < 20);
     }
 
+    #[test]
+    fn test_improving_reduces_more() {
+        let engine = SearchEngine::new(1);
+        let board = BoardState::default();
+        let mut thread_data = ThreadData::new();
+        let quiet = Move::new(12, 20, 0);
+
+        // Eval climbing from our previous move: 20 -> 60
+        thread_data.static_evals[2] = 20;
+        thread_data.static_evals[4] = 60;
+        assert!(SearchEngine::is_improving(&thread_data, 4));
+        let improving = engine.late_move_reduction(&board, &quiet, 12, 20, false, true, 4, &thread_data);
+
+        // Eval sliding: 60 -> 20
+        thread_data.static_evals[2] = 60;
+        thread_data.static_evals[4] = 20;
+        assert!(!SearchEngine::is_improving(&thread_data, 4));
+        let not_improving = engine.late_move_reduction(&board, &quiet, 12, 20, false, false, 4, &thread_data);
+
+        assert!(improving > not_improving);
+    }
+
     #[test]
     fn test_mvv_lva() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -1191,6 +3423,51 @@ mod tests {
         println!("Pawn capture: {}, Knight capture: {}", score1, score2);
     }
 
+    #[test]
+    fn test_bogus_tt_move_is_ignored() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut board = BoardState::from_fen(fen).unwrap();
+        let engine = SearchEngine::new(1);
+
+        // a1 -> h8 "capture" is not legal here; a stale entry must not be played
+        let bogus = Move::new(0, 63, crate::movegen::CAPTURE);
+        engine.tt.store(board.hash, 1, 0, TT_EXACT, Some(bogus), NO_EVAL);
+
+        let mut thread_data = ThreadData::new();
+        engine.pvs(&mut board, 3, -INFINITY, INFINITY, 1, true, 0, Instant::now(), None, None, &mut thread_data);
+
+        let legal = MoveGenerator::generate_legal_moves(&board);
+        let stored = engine.tt.probe(board.hash).and_then(|e| e.best_move());
+        assert!(stored.is_some());
+        assert_ne!(stored, Some(bogus));
+        assert!(legal.contains(&stored.unwrap()));
+    }
+
+    #[test]
+    fn test_quiescence_detects_mate_in_check() {
+        // Back-rank mate: the side to move is up material but has no evasion
+        let fen = "4k3/8/8/PPP5/8/8/5PPP/r5K1 w - - 0 1";
+        let board = BoardState::from_fen(fen).unwrap();
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+
+        let score = engine.quiescence(&board, -INFINITY, INFINITY, 0, 0, &mut thread_data);
+        assert_eq!(score, -MATE_SCORE);
+    }
+
+    #[test]
+    fn test_quiescence_searches_quiet_evasion() {
+        // Only Kh2 (a quiet king move) gets out of check
+        let fen = "4k3/8/8/3Q4/8/8/5PP1/r5K1 w - - 0 1";
+        let board = BoardState::from_fen(fen).unwrap();
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+
+        let score = engine.quiescence(&board, -INFINITY, INFINITY, 0, 0, &mut thread_data);
+        assert!(score > -MATE_SCORE + 100);
+        assert!(score > 0, "a queen against a rook should still be winning, got {}", score);
+    }
+
     #[test]
     fn test_killer_moves() {
         let mut thread_data = ThreadData::new();
@@ -1202,6 +3479,33 @@ mod tests {
         assert_eq!(thread_data.killer_moves[0][0], Some(test_move));
     }
 
+    #[test]
+    fn test_continuation_history_boosts_reply() {
+        let engine = SearchEngine::new(1);
+        let mut thread_data = ThreadData::new();
+
+        // After 1.e4, reward ...Nf6 as the answer to it
+        let mut board = BoardState::default();
+        assert!(board.make_move_uci("e2e4").unwrap());
+        let e4 = Move::new(12, 28, crate::movegen::DOUBLE_PAWN_PUSH);
+        let nf6 = Move::new(62, 45, crate::movegen::QUIET_MOVE);
+        let nc6 = Move::new(57, 42, crate::movegen::QUIET_MOVE);
+        thread_data.move_stack[0] = Some(e4);
+
+        let score = |thread_data: &ThreadData, mv: &Move| {
+            let continuation = SearchEngine::continuation_block(&board, 1, thread_data);
+            engine.score_move_internal(&board, mv, None, &[None; 2], &thread_data.history_table, continuation)
+        };
+        assert_eq!(score(&thread_data, &nf6), score(&thread_data, &nc6));
+
+        engine.update_continuation_history(&board, nf6, 400, 1, &mut thread_data);
+        assert!(score(&thread_data, &nf6) > score(&thread_data, &nc6));
+
+        // The reward belongs to the reply to e4, not to Nf6 everywhere
+        thread_data.move_stack[0] = None;
+        assert_eq!(score(&thread_data, &nf6), score(&thread_data, &nc6));
+    }
+
     #[test]
     fn test_history_table() {
         let mut thread_data = ThreadData::new();
@@ -1213,4 +3517,28 @@ mod tests {
         let score = thread_data.history_table[12][20];
         assert!(score > 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_history_saturates_without_sweeping() {
+        let mut thread_data = ThreadData::new();
+        let engine = SearchEngine::new(1);
+        let cutoff = Move::new(12, 28, 0);
+        let other = Move::new(6, 21, 0);
+        engine.update_history_raw_internal(other, 500, &mut thread_data);
+
+        let mut previous = 0;
+        for _ in 0..200 {
+            engine.update_history_raw_internal(cutoff, 4000, &mut thread_data);
+            let value = thread_data.history_table[12][28];
+            assert!(value >= previous && value <= HISTORY_MAX);
+            previous = value;
+        }
+        assert!(previous > HISTORY_MAX * 9 / 10);
+
+        // Penalties pull it back down just as boundedly
+        engine.update_history_raw_internal(cutoff, -4000, &mut thread_data);
+        assert!(thread_data.history_table[12][28] < previous);
+
+        assert_eq!(thread_data.history_table[6][21], 500);
+    }
+}
@@ -0,0 +1,1315 @@
+use crate::bitboard::*;
+use crate::board::{BoardState, Color, Piece};
+use crate::movegen::{Move, MoveGenerator};
+use memmap2::Mmap;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+// ══════════════════════════════════════════════════════════════════════════════
+// SYZYGY TABLEBASES
+// ══════════════════════════════════════════════════════════════════════════════
+//
+// Probing follows the reference layout of the Syzygy format: WDL tables
+// (.rtbw) hold win/draw/loss for both sides to move, DTZ tables (.rtbz) the
+// distance to the next zeroing move for one side. Files are memory mapped
+// on first use.
+
+const TB_PIECES: usize = 7;
+const WDL_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+const DTZ_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+
+// Per-table flags
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            1 => Wdl::CursedWin,
+            2 => Wdl::Win,
+            _ => Wdl::Draw,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_value(-(self as i32))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ProbeState {
+    Ok,
+    Fail,
+    // Best move zeroes the fifty-move counter, so DTZ can't be trusted
+    ZeroingBestMove,
+    // DTZ table only stores the other side to move
+    ChangeStm,
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// INDEXING TABLES
+// ══════════════════════════════════════════════════════════════════════════════
+
+struct Encoding {
+    binomial: [[u64; 64]; TB_PIECES],
+    map_b1h1h7: [u64; 64],
+    map_a1d1d4: [u64; 64],
+    map_kk: [[u64; 64]; 10],
+    map_pawns: [usize; 64],
+    lead_pawn_idx: [[u64; 64]; TB_PIECES],
+    lead_pawns_size: [[u64; 4]; TB_PIECES],
+}
+
+lazy_static::lazy_static! {
+    static ref ENCODING: Encoding = Encoding::new();
+    static ref TABLEBASES: RwLock<Tablebases> = RwLock::new(Tablebases::default());
+}
+
+fn off_a1h8(sq: u8) -> i32 {
+    (sq / 8) as i32 - (sq % 8) as i32
+}
+
+impl Encoding {
+    fn new() -> Self {
+        let mut enc = Encoding {
+            binomial: [[0; 64]; TB_PIECES],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; TB_PIECES],
+            lead_pawns_size: [[0; 4]; TB_PIECES],
+        };
+
+        // b1-h1-h7 triangle below the long diagonal, 0..27
+        let mut code = 0;
+        for sq in 0..64u8 {
+            if off_a1h8(sq) < 0 {
+                enc.map_b1h1h7[sq as usize] = code;
+                code += 1;
+            }
+        }
+
+        // a1-d1-d4 triangle, diagonal squares last, 0..9
+        let mut diagonal = Vec::new();
+        code = 0;
+        for sq in 0..=27u8 {
+            if off_a1h8(sq) < 0 && sq % 8 <= 3 {
+                enc.map_a1d1d4[sq as usize] = code;
+                code += 1;
+            } else if off_a1h8(sq) == 0 && sq % 8 <= 3 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            enc.map_a1d1d4[sq as usize] = code;
+            code += 1;
+        }
+
+        // The 462 legal king pairs with the first king in the a1-d1-d4
+        // triangle; with both on the diagonal they are numbered last
+        let tables = &ATTACK_TABLES;
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..=27u8 {
+                if enc.map_a1d1d4[s1 as usize] != idx as u64 || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64u8 {
+                    // Skip touching kings, and the upper half when the first is on the diagonal
+                    let touching = (tables.king_attacks[s1 as usize] | (1u64 << s1)) & (1u64 << s2) != 0;
+                    if touching || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        enc.map_kk[idx][s2 as usize] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            enc.map_kk[idx][s2 as usize] = code;
+            code += 1;
+        }
+
+        enc.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..TB_PIECES.min(n + 1) {
+                enc.binomial[k][n] = if k > 0 { enc.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { enc.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // Pawn squares a2-h7 ranked so the leading pawn (closest to the edge,
+        // lowest rank) has the highest value
+        let mut available = 47;
+        for lead_pawns in 1..TB_PIECES - 1 {
+            for file in 0..4u8 {
+                let mut idx = 0;
+                for rank in 1..7u8 {
+                    let sq = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        enc.map_pawns[sq as usize] = available;
+                        available -= 1;
+                        enc.map_pawns[(sq ^ 7) as usize] = available;
+                        available = available.saturating_sub(1);
+                    }
+                    enc.lead_pawn_idx[lead_pawns][sq as usize] = idx;
+                    idx += enc.binomial[lead_pawns - 1][enc.map_pawns[sq as usize]];
+                }
+                enc.lead_pawns_size[lead_pawns][file as usize] = idx;
+            }
+        }
+
+        enc
+    }
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// TABLE REGISTRY
+// ══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Default)]
+struct Tablebases {
+    wdl: HashMap<u64, Arc<Table>>,
+    dtz: HashMap<u64, Arc<Table>>,
+    max_pieces: usize,
+}
+
+struct Table {
+    kind: TableKind,
+    path: PathBuf,
+    key: u64,
+    key2: u64,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // Pawns of the leading colour first
+    pawn_count: [usize; 2],
+    data: OnceLock<Option<TableData>>,
+}
+
+struct TableData {
+    mmap: Mmap,
+    // [side][file]
+    items: Vec<Vec<PairsData>>,
+    map: usize,
+}
+
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    min_sym_len: u8,
+    block_size: u64,
+    span: u64,
+    num_blocks: u64,
+    sparse_index_size: u64,
+    block_length_size: u64,
+    lowest_sym: usize,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    pieces: [u8; TB_PIECES],
+    group_len: [usize; TB_PIECES + 1],
+    group_idx: [u64; TB_PIECES + 1],
+    map_idx: [u16; 4],
+}
+
+/// Scans `path` (directories separated by ':' or ';') for Syzygy files and
+/// makes them available for probing. An empty path or `<empty>` disables
+/// probing. Returns the number of WDL tables found.
+pub fn init(path: &str) -> usize {
+    let mut tablebases = Tablebases::default();
+    let mut wdl_tables = 0;
+
+    if !path.is_empty() && path != "<empty>" {
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        for dir in path.split(separator).filter(|d| !d.is_empty()) {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for entry in entries.flatten() {
+                let file_path = entry.path();
+                let kind = match file_path.extension().and_then(|e| e.to_str()) {
+                    Some("rtbw") => TableKind::Wdl,
+                    Some("rtbz") => TableKind::Dtz,
+                    _ => continue,
+                };
+                let name = match file_path.file_stem().and_then(|s| s.to_str()) {
+                    Some(name) => name,
+                    None => continue,
+                };
+
+                if let Some(table) = Table::new(name, kind, file_path.clone()) {
+                    let table = Arc::new(table);
+                    if kind == TableKind::Wdl {
+                        tablebases.max_pieces = tablebases.max_pieces.max(table.piece_count);
+                        wdl_tables += 1;
+                    }
+                    let registry = match kind {
+                        TableKind::Wdl => &mut tablebases.wdl,
+                        TableKind::Dtz => &mut tablebases.dtz,
+                    };
+                    registry.insert(table.key, Arc::clone(&table));
+                    registry.insert(table.key2, table);
+                }
+            }
+        }
+    }
+
+    *TABLEBASES.write() = tablebases;
+    wdl_tables
+}
+
+/// Largest piece count covered by the loaded WDL tables, 0 when none.
+pub fn max_pieces() -> usize {
+    TABLEBASES.read().max_pieces
+}
+
+// Material signature: one nibble per (colour, piece) count
+fn material_key(counts: &[[usize; 7]; 2]) -> u64 {
+    let mut key = 0u64;
+    for (color, side) in counts.iter().enumerate() {
+        for (piece, &count) in side.iter().enumerate().skip(1) {
+            key |= (count as u64 & 0xF) << (4 * (color * 6 + piece - 1));
+        }
+    }
+    key
+}
+
+fn board_material_key(board: &BoardState) -> u64 {
+    let mut counts = [[0usize; 7]; 2];
+    for (side, pieces) in counts.iter_mut().zip(&board.pieces) {
+        for (count, &bb) in side.iter_mut().zip(pieces) {
+            *count = count_bits(bb) as usize;
+        }
+    }
+    material_key(&counts)
+}
+
+fn parse_side(side: &str) -> Option<[usize; 7]> {
+    let mut counts = [0usize; 7];
+    for ch in side.chars() {
+        let piece = match ch {
+            'P' => Piece::Pawn,
+            'N' => Piece::Knight,
+            'B' => Piece::Bishop,
+            'R' => Piece::Rook,
+            'Q' => Piece::Queen,
+            'K' => Piece::King,
+            _ => return None,
+        };
+        counts[piece as usize] += 1;
+    }
+    if counts[Piece::King as usize] != 1 {
+        return None;
+    }
+    Some(counts)
+}
+
+impl Table {
+    fn new(name: &str, kind: TableKind, path: PathBuf) -> Option<Self> {
+        let (white, black) = name.split_once('v')?;
+        let counts = [parse_side(white)?, parse_side(black)?];
+        let piece_count: usize = counts.iter().flatten().sum();
+        if piece_count > TB_PIECES {
+            return None;
+        }
+
+        let pawn = Piece::Pawn as usize;
+        let has_pawns = counts[0][pawn] + counts[1][pawn] > 0;
+        let has_unique_pieces = counts.iter().any(|side| (1..6).any(|piece| side[piece] == 1));
+
+        // With pawns on both sides the side with fewer pawns leads
+        let white_leads = counts[1][pawn] == 0 || (counts[0][pawn] > 0 && counts[1][pawn] >= counts[0][pawn]);
+        let pawn_count = if white_leads {
+            [counts[0][pawn], counts[1][pawn]]
+        } else {
+            [counts[1][pawn], counts[0][pawn]]
+        };
+
+        Some(Table {
+            kind,
+            path,
+            key: material_key(&counts),
+            key2: material_key(&[counts[1], counts[0]]),
+            piece_count,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+            data: OnceLock::new(),
+        })
+    }
+
+    fn sides(&self) -> usize {
+        if self.kind == TableKind::Wdl && self.key != self.key2 { 2 } else { 1 }
+    }
+
+    fn data(&self) -> Option<&TableData> {
+        self.data.get_or_init(|| self.load()).as_ref()
+    }
+
+    fn load(&self) -> Option<TableData> {
+        let file = File::open(&self.path).ok()?;
+        // SAFETY: tablebase files are treated as read-only for the lifetime
+        // of the process
+        let mmap = unsafe { Mmap::map(&file).ok()? };
+
+        let magic = if self.kind == TableKind::Wdl { WDL_MAGIC } else { DTZ_MAGIC };
+        if mmap.len() < 5 || mmap.len() % 64 != 16 || mmap[..4] != magic {
+            return None;
+        }
+
+        let mut data = TableData { mmap, items: Vec::new(), map: 0 };
+        self.parse(&mut data)?;
+        Some(data)
+    }
+
+    // Reads the per-file piece orders, Huffman headers and section offsets
+    fn parse(&self, td: &mut TableData) -> Option<()> {
+        let bytes = &td.mmap[..];
+        let mut pos = 4;
+
+        let header = *bytes.get(pos)?;
+        if (header & 2 != 0) != self.has_pawns || (self.kind == TableKind::Wdl && (header & 1 != 0) != (self.key != self.key2)) {
+            return None;
+        }
+        pos += 1;
+
+        let sides = self.sides();
+        let files = if self.has_pawns { 4 } else { 1 };
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        td.items = vec![vec![PairsData::default(); files]; sides];
+
+        for f in 0..files {
+            let b0 = *bytes.get(pos)?;
+            let b1 = if pp { *bytes.get(pos + 1)? } else { 0xFF };
+            let order = [[b0 & 0xF, if pp { b1 & 0xF } else { 0xF }], [b0 >> 4, if pp { b1 >> 4 } else { 0xF }]];
+            pos += 1 + pp as usize;
+
+            for k in 0..self.piece_count {
+                let byte = *bytes.get(pos)?;
+                for (i, side) in td.items.iter_mut().enumerate() {
+                    side[f].pieces[k] = if i == 1 { byte >> 4 } else { byte & 0xF };
+                }
+                pos += 1;
+            }
+
+            for (i, side) in td.items.iter_mut().enumerate() {
+                self.set_groups(&mut side[f], order[i], f);
+            }
+        }
+
+        pos += pos & 1;
+
+        for f in 0..files {
+            for side in td.items.iter_mut() {
+                pos = set_sizes(&mut side[f], bytes, pos)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            td.map = pos;
+            for f in 0..files {
+                let d = &mut td.items[0][f];
+                if d.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                if d.flags & FLAG_WIDE != 0 {
+                    pos += pos & 1;
+                    for i in 0..4 {
+                        d.map_idx[i] = ((pos - td.map) / 2 + 1) as u16;
+                        pos += 2 * read_u16_le(bytes, pos)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = (pos - td.map + 1) as u16;
+                        pos += *bytes.get(pos)? as usize + 1;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for f in 0..files {
+            for side in td.items.iter_mut() {
+                side[f].sparse_index = pos;
+                pos += side[f].sparse_index_size as usize * 6;
+            }
+        }
+
+        for f in 0..files {
+            for side in td.items.iter_mut() {
+                side[f].block_length = pos;
+                pos += side[f].block_length_size as usize * 2;
+            }
+        }
+
+        for f in 0..files {
+            for side in td.items.iter_mut() {
+                pos = (pos + 0x3F) & !0x3F;
+                side[f].data = pos;
+                pos += (side[f].num_blocks * side[f].block_size) as usize;
+            }
+        }
+
+        if pos > bytes.len() {
+            return None;
+        }
+        Some(())
+    }
+
+    // Splits the piece sequence into groups and works out each group's
+    // multiplier in the position index
+    fn set_groups(&self, d: &mut PairsData, order: [u8; 2], file: usize) {
+        let enc = &*ENCODING;
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        d.group_len[n] = 1;
+
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+
+        let mut k = 0u8;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    enc.lead_pawns_size[d.group_len[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= enc.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= enc.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+
+        d.group_idx[n] = idx;
+    }
+}
+
+fn read_u16_le(bytes: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32_le(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn read_u32_be(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn read_u64_be(bytes: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+// Binary tree node of the recursive pairing: 12-bit left and right symbols
+fn btree_left(bytes: &[u8], d: &PairsData, sym: usize) -> usize {
+    let node = &bytes[d.btree + 3 * sym..];
+    (((node[1] & 0xF) as usize) << 8) | node[0] as usize
+}
+
+fn btree_right(bytes: &[u8], d: &PairsData, sym: usize) -> usize {
+    let node = &bytes[d.btree + 3 * sym..];
+    ((node[2] as usize) << 4) | (node[1] >> 4) as usize
+}
+
+fn set_sizes(d: &mut PairsData, bytes: &[u8], mut pos: usize) -> Option<usize> {
+    d.flags = *bytes.get(pos)?;
+    pos += 1;
+
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        d.num_blocks = 0;
+        d.span = 0;
+        d.sparse_index_size = 0;
+        d.min_sym_len = *bytes.get(pos)?;
+        return Some(pos + 1);
+    }
+
+    let groups = d.group_len.iter().position(|&len| len == 0).unwrap_or(TB_PIECES);
+    let tb_size = d.group_idx[groups];
+
+    d.block_size = 1u64 << *bytes.get(pos)?;
+    d.span = 1u64 << *bytes.get(pos + 1)?;
+    d.sparse_index_size = tb_size.div_ceil(d.span);
+    let padding = *bytes.get(pos + 2)? as u64;
+    d.num_blocks = read_u32_le(bytes, pos + 3)? as u64;
+    d.block_length_size = d.num_blocks + padding;
+    let max_sym_len = *bytes.get(pos + 7)?;
+    d.min_sym_len = *bytes.get(pos + 8)?;
+    pos += 9;
+
+    if max_sym_len < d.min_sym_len {
+        return None;
+    }
+
+    d.lowest_sym = pos;
+    let lengths = (max_sym_len - d.min_sym_len + 1) as usize;
+    d.base64 = vec![0; lengths];
+
+    // Canonical Huffman: longer codes have lower values, so base64 is
+    // non-increasing once every entry is left-aligned to 64 bits
+    for i in (0..lengths - 1).rev() {
+        let lowest_i = read_u16_le(bytes, d.lowest_sym + 2 * i)? as u64;
+        let lowest_next = read_u16_le(bytes, d.lowest_sym + 2 * (i + 1))? as u64;
+        d.base64[i] = (d.base64[i + 1] + lowest_i).wrapping_sub(lowest_next) / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        let shift = 64 - i as u32 - d.min_sym_len as u32;
+        *base = base.checked_shl(shift).unwrap_or(0);
+    }
+
+    pos += lengths * 2;
+    let sym_count = read_u16_le(bytes, pos)? as usize;
+    pos += 2;
+    d.btree = pos;
+    if bytes.len() < pos + 3 * sym_count {
+        return None;
+    }
+
+    d.symlen = vec![0; sym_count];
+    let mut visited = vec![false; sym_count];
+    for sym in 0..sym_count {
+        if !visited[sym] {
+            d.symlen[sym] = set_symlen(d, bytes, sym, &mut visited);
+        }
+    }
+
+    Some(pos + sym_count * 3 + (sym_count & 1))
+}
+
+fn set_symlen(d: &mut PairsData, bytes: &[u8], sym: usize, visited: &mut [bool]) -> u8 {
+    visited[sym] = true;
+    let right = btree_right(bytes, d, sym);
+    if right == 0xFFF {
+        return 0;
+    }
+    let left = btree_left(bytes, d, sym);
+
+    if left < visited.len() && !visited[left] {
+        d.symlen[left] = set_symlen(d, bytes, left, visited);
+    }
+    if right < visited.len() && !visited[right] {
+        d.symlen[right] = set_symlen(d, bytes, right, visited);
+    }
+
+    let left_len = d.symlen.get(left).copied().unwrap_or(0);
+    let right_len = d.symlen.get(right).copied().unwrap_or(0);
+    left_len.wrapping_add(right_len).wrapping_add(1)
+}
+
+// Value stored at position `idx` of one table section
+fn decompress_pairs(d: &PairsData, bytes: &[u8], idx: u64) -> Option<i32> {
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        return Some(d.min_sym_len as i32);
+    }
+
+    // Closest sparse-index checkpoint, then walk blocks to the one holding idx
+    let k = (idx / d.span) as usize;
+    let entry = d.sparse_index + 6 * k;
+    let mut block = read_u32_le(bytes, entry)? as usize;
+    let mut offset = read_u16_le(bytes, entry + 4)? as i64;
+    offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+    let block_length = |block: usize| read_u16_le(bytes, d.block_length + 2 * block).map(|len| len as i64);
+    while offset < 0 {
+        block = block.checked_sub(1)?;
+        offset += block_length(block)? + 1;
+    }
+    while offset > block_length(block)? {
+        offset -= block_length(block)? + 1;
+        block += 1;
+    }
+
+    let mut ptr = d.data + block * d.block_size as usize;
+    let mut buf64 = read_u64_be(bytes, ptr)?;
+    ptr += 8;
+    let mut buf64_size = 64;
+    let mut sym;
+
+    loop {
+        let mut len = 0;
+        while len < d.base64.len() - 1 && buf64 < d.base64[len] {
+            len += 1;
+        }
+
+        sym = (buf64 - d.base64[len]).checked_shr((64 - len - d.min_sym_len as usize) as u32).unwrap_or(0) as usize;
+        sym += read_u16_le(bytes, d.lowest_sym + 2 * len)? as usize;
+
+        let run = *d.symlen.get(sym)? as i64 + 1;
+        if offset < run {
+            break;
+        }
+
+        offset -= run;
+        let consumed = len + d.min_sym_len as usize;
+        buf64 = buf64.checked_shl(consumed as u32).unwrap_or(0);
+        buf64_size -= consumed as i32;
+
+        if buf64_size <= 32 {
+            buf64_size += 32;
+            buf64 |= (read_u32_be(bytes, ptr)? as u64) << (64 - buf64_size);
+            ptr += 4;
+        }
+    }
+
+    // Expand the pair symbol down to the single value at `offset`
+    while *d.symlen.get(sym)? != 0 {
+        let left = btree_left(bytes, d, sym);
+        let left_run = *d.symlen.get(left)? as i64 + 1;
+        if offset < left_run {
+            sym = left;
+        } else {
+            offset -= left_run;
+            sym = btree_right(bytes, d, sym);
+        }
+    }
+
+    Some(btree_left(bytes, d, sym) as i32)
+}
+
+// ══════════════════════════════════════════════════════════════════════════════
+// PROBING
+// ══════════════════════════════════════════════════════════════════════════════
+
+fn piece_code(board: &BoardState, sq: u8) -> u8 {
+    match board.piece_at(sq) {
+        Some((piece, color)) => piece as u8 + 8 * color as u8,
+        None => 0,
+    }
+}
+
+fn map_score(table: &Table, td: &TableData, file: usize, value: i32, wdl: Wdl) -> i32 {
+    if table.kind == TableKind::Wdl {
+        return value - 2;
+    }
+
+    const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+    let d = &td.items[0][file];
+    let bytes = &td.mmap[..];
+    let mut value = value;
+
+    if d.flags & FLAG_MAPPED != 0 {
+        let idx = d.map_idx[WDL_MAP[(wdl as i32 + 2) as usize]] as usize + value as usize;
+        value = if d.flags & FLAG_WIDE != 0 {
+            read_u16_le(bytes, td.map + 2 * idx).unwrap_or(0) as i32
+        } else {
+            bytes.get(td.map + idx).copied().unwrap_or(0) as i32
+        };
+    }
+
+    // Convert moves to plies where the table stores moves
+    if (wdl == Wdl::Win && d.flags & FLAG_WIN_PLIES == 0)
+        || (wdl == Wdl::Loss && d.flags & FLAG_LOSS_PLIES == 0)
+        || wdl == Wdl::CursedWin
+        || wdl == Wdl::BlessedLoss
+    {
+        value *= 2;
+    }
+
+    value + 1
+}
+
+fn probe_table(board: &BoardState, kind: TableKind, wdl: Wdl, state: &mut ProbeState) -> i32 {
+    if count_bits(board.all_pieces) == 2 {
+        return 0;
+    }
+
+    let key = board_material_key(board);
+    let table = {
+        let tablebases = TABLEBASES.read();
+        let registry = if kind == TableKind::Wdl { &tablebases.wdl } else { &tablebases.dtz };
+        registry.get(&key).cloned()
+    };
+
+    match table.as_deref().and_then(|table| table.data().map(|td| (table, td))) {
+        Some((table, td)) => match do_probe_table(board, key, table, td, wdl, state) {
+            Some(value) => value,
+            None => {
+                *state = ProbeState::Fail;
+                0
+            }
+        },
+        None => {
+            *state = ProbeState::Fail;
+            0
+        }
+    }
+}
+
+fn do_probe_table(board: &BoardState, key: u64, table: &Table, td: &TableData, wdl: Wdl, state: &mut ProbeState) -> Option<i32> {
+    let enc = &*ENCODING;
+    let mut squares = [0u8; TB_PIECES];
+    let mut pieces = [0u8; TB_PIECES];
+    let mut size = 0;
+    let mut lead_pawns_cnt = 0;
+    let mut lead_pawns: Bitboard = 0;
+    let mut tb_file = 0;
+
+    // Tables are stored with white as the stronger side (and white to move
+    // when both sides are equal); otherwise swap colours and mirror ranks
+    let symmetric_black_to_move = table.key == table.key2 && board.side_to_move == Color::Black;
+    let black_stronger = key != table.key;
+    let flip = symmetric_black_to_move || black_stronger;
+    let flip_color = if flip { 8 } else { 0 };
+    let flip_squares = if flip { 56 } else { 0 };
+    let stm = flip as usize ^ board.side_to_move as usize;
+
+    if table.has_pawns {
+        let lead_piece = td.items[0][0].pieces[0] ^ flip_color;
+        let lead_color = (lead_piece >> 3) as usize;
+        lead_pawns = board.pieces[lead_color][Piece::Pawn as usize];
+
+        let mut bb = lead_pawns;
+        while bb != 0 {
+            let (new_bb, sq) = pop_lsb(bb);
+            bb = new_bb;
+            squares[size] = sq? ^ flip_squares;
+            size += 1;
+        }
+        lead_pawns_cnt = size;
+
+        // Leading pawn is the one with the highest map_pawns value (first on ties)
+        let mut lead = 0;
+        for i in 1..lead_pawns_cnt {
+            if enc.map_pawns[squares[i] as usize] > enc.map_pawns[squares[lead] as usize] {
+                lead = i;
+            }
+        }
+        squares.swap(0, lead);
+
+        let file = (squares[0] % 8) as usize;
+        tb_file = file.min(7 - file);
+    }
+
+    if table.kind == TableKind::Dtz {
+        let flags = td.items[0][tb_file].flags;
+        let stored_stm = (flags & FLAG_STM) as usize;
+        let symmetric_pawnless = table.key == table.key2 && !table.has_pawns;
+        if stored_stm != stm && !symmetric_pawnless {
+            *state = ProbeState::ChangeStm;
+            return Some(0);
+        }
+    }
+
+    let mut bb = board.all_pieces ^ lead_pawns;
+    while bb != 0 {
+        let (new_bb, sq) = pop_lsb(bb);
+        bb = new_bb;
+        let sq = sq?;
+        squares[size] = sq ^ flip_squares;
+        pieces[size] = piece_code(board, sq) ^ flip_color;
+        size += 1;
+    }
+
+    let d = &td.items[stm % table.sides()][tb_file];
+    let bytes = &td.mmap[..];
+
+    // Put the pieces in the order the table was encoded with
+    for i in lead_pawns_cnt..size.saturating_sub(1) {
+        for j in i + 1..size {
+            if d.pieces[i] == pieces[j] {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+                break;
+            }
+        }
+    }
+
+    // Lead piece goes to the a-d files
+    if squares[0] % 8 > 3 {
+        for sq in squares.iter_mut().take(size) {
+            *sq ^= 7;
+        }
+    }
+
+    let mut idx: u64;
+    if table.has_pawns {
+        idx = enc.lead_pawn_idx[lead_pawns_cnt][squares[0] as usize];
+        squares[1..lead_pawns_cnt].sort_by_key(|&sq| enc.map_pawns[sq as usize]);
+        for (i, &sq) in squares.iter().enumerate().take(lead_pawns_cnt).skip(1) {
+            idx += enc.binomial[i][enc.map_pawns[sq as usize]];
+        }
+    } else {
+        // Pawnless: lead piece also below rank 5 and under the a1-h8 diagonal
+        if squares[0] / 8 > 3 {
+            for sq in squares.iter_mut().take(size) {
+                *sq ^= 56;
+            }
+        }
+
+        let leading = d.group_len[0];
+        if let Some(i) = squares[..leading].iter().position(|&sq| off_a1h8(sq) != 0) {
+            if off_a1h8(squares[i]) > 0 {
+                for sq in squares.iter_mut().take(size).skip(i) {
+                    *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                }
+            }
+        }
+
+        if table.has_unique_pieces {
+            let adjust1 = (squares[1] > squares[0]) as u64 + (squares[2] > squares[0]) as u64;
+            let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+            let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+
+            idx = if off_a1h8(s0) != 0 {
+                (enc.map_a1d1d4[s0 as usize] * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+            } else if off_a1h8(s1) != 0 {
+                (6 * 63 + (s0 / 8) as u64 * 28 + enc.map_b1h1h7[s1 as usize]) * 62 + s2 as u64 - adjust2
+            } else if off_a1h8(s2) != 0 {
+                6 * 63 * 62 + 4 * 28 * 62
+                    + (s0 / 8) as u64 * 7 * 28
+                    + ((s1 / 8) as u64 - adjust1) * 28
+                    + enc.map_b1h1h7[s2 as usize]
+            } else {
+                6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28
+                    + (s0 / 8) as u64 * 7 * 6
+                    + ((s1 / 8) as u64 - adjust1) * 6
+                    + ((s2 / 8) as u64 - adjust2)
+            };
+        } else {
+            idx = enc.map_kk[enc.map_a1d1d4[squares[0] as usize] as usize][squares[1] as usize];
+        }
+    }
+
+    // Remaining groups, each as a combination of its sorted squares
+    idx *= d.group_idx[0];
+    let mut group_start = d.group_len[0];
+    let mut remaining_pawns = table.has_pawns && table.pawn_count[1] > 0;
+    let mut next = 1;
+
+    while d.group_len[next] != 0 {
+        let len = d.group_len[next];
+        squares[group_start..group_start + len].sort_unstable();
+
+        let mut n = 0u64;
+        for i in 0..len {
+            let sq = squares[group_start + i] as usize;
+            let adjust = squares[..group_start].iter().filter(|&&s| (s as usize) < sq).count();
+            n += enc.binomial[i + 1][sq - adjust - 8 * remaining_pawns as usize];
+        }
+
+        remaining_pawns = false;
+        idx += n * d.group_idx[next];
+        group_start += len;
+        next += 1;
+    }
+
+    let value = decompress_pairs(d, bytes, idx)?;
+    Some(map_score(table, td, tb_file, value, wdl))
+}
+
+fn is_zeroing(board: &BoardState, mv: &Move) -> bool {
+    mv.is_capture() || board.piece_at(mv.from).map(|(piece, _)| piece) == Some(Piece::Pawn)
+}
+
+// Resolves captures (and optionally pawn moves) before trusting the table:
+// tables ignore en passant, and a zeroing move may beat the stored value
+fn search(board: &BoardState, check_zeroing_moves: bool, state: &mut ProbeState) -> Wdl {
+    let mut best = Wdl::Loss;
+    let moves = MoveGenerator::generate_legal_moves(board);
+    let mut searched = 0;
+
+    for mv in &moves {
+        let pawn_move = board.piece_at(mv.from).map(|(piece, _)| piece) == Some(Piece::Pawn);
+        if !(mv.is_capture() || (check_zeroing_moves && pawn_move)) {
+            continue;
+        }
+        searched += 1;
+
+        let mut next = board.clone();
+        next.make_move(mv);
+        let value = -search(&next, false, state);
+
+        if *state == ProbeState::Fail {
+            return Wdl::Draw;
+        }
+
+        if value > best {
+            best = value;
+            if value >= Wdl::Win {
+                *state = ProbeState::ZeroingBestMove;
+                return value;
+            }
+        }
+    }
+
+    let no_more_moves = searched > 0 && searched == moves.len();
+    let value = if no_more_moves {
+        best
+    } else {
+        let value = Wdl::from_value(probe_table(board, TableKind::Wdl, Wdl::Draw, state));
+        if *state == ProbeState::Fail {
+            return Wdl::Draw;
+        }
+        value
+    };
+
+    if best >= value {
+        *state = if best > Wdl::Draw || no_more_moves { ProbeState::ZeroingBestMove } else { ProbeState::Ok };
+        return best;
+    }
+
+    *state = ProbeState::Ok;
+    value
+}
+
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+fn probeable(board: &BoardState) -> bool {
+    let max = max_pieces();
+    max > 0 && board.castling_rights == 0 && count_bits(board.all_pieces) as usize <= max
+}
+
+/// Win/draw/loss for the side to move, or `None` when no table covers the
+/// position (or it still has castling rights).
+pub fn probe_wdl(board: &BoardState) -> Option<Wdl> {
+    if !probeable(board) {
+        return None;
+    }
+
+    let mut state = ProbeState::Ok;
+    let wdl = search(board, false, &mut state);
+    (state != ProbeState::Fail).then_some(wdl)
+}
+
+/// Distance to the next zeroing move in plies, signed by the result for the
+/// side to move (±101 and beyond for cursed wins / blessed losses).
+pub fn probe_dtz(board: &BoardState) -> Option<i32> {
+    if !probeable(board) {
+        return None;
+    }
+
+    let mut state = ProbeState::Ok;
+    let dtz = dtz(board, &mut state);
+    (state != ProbeState::Fail).then_some(dtz)
+}
+
+fn dtz(board: &BoardState, state: &mut ProbeState) -> i32 {
+    *state = ProbeState::Ok;
+    let wdl = search(board, true, state);
+
+    if *state == ProbeState::Fail || wdl == Wdl::Draw {
+        return 0;
+    }
+    if *state == ProbeState::ZeroingBestMove {
+        return dtz_before_zeroing(wdl);
+    }
+
+    let value = probe_table(board, TableKind::Dtz, wdl, state);
+    if *state == ProbeState::Fail {
+        return 0;
+    }
+    if *state != ProbeState::ChangeStm {
+        let cursed = matches!(wdl, Wdl::BlessedLoss | Wdl::CursedWin);
+        return (value + if cursed { 100 } else { 0 }) * wdl.signum();
+    }
+
+    // Table holds the other side to move: one ply search for the best DTZ
+    let mut min_dtz = 0xFFFF;
+    for mv in MoveGenerator::generate_legal_moves(board) {
+        let zeroing = is_zeroing(board, &mv);
+        let mut next = board.clone();
+        next.make_move(&mv);
+
+        let mut value = if zeroing {
+            -dtz_before_zeroing(search(&next, false, state))
+        } else {
+            -dtz(&next, state)
+        };
+
+        if value == 1 && next.is_in_check(next.side_to_move) && MoveGenerator::generate_legal_moves(&next).is_empty() {
+            min_dtz = 1;
+        }
+
+        if !zeroing {
+            value += value.signum();
+        }
+
+        if value < min_dtz && value.signum() == wdl.signum() {
+            min_dtz = value;
+        }
+
+        if *state == ProbeState::Fail {
+            return 0;
+        }
+    }
+
+    if min_dtz == 0xFFFF { -1 } else { min_dtz }
+}
+
+/// Picks the root move that keeps the best tablebase result, converting
+/// wins by the shortest DTZ and dragging out losses. Returns the move and
+/// the result for the side to move, with the fifty-move rule applied from
+/// the board's halfmove clock.
+pub fn probe_root(board: &BoardState) -> Option<(Move, Wdl)> {
+    if !probeable(board) {
+        return None;
+    }
+
+    let mut best: Option<(Move, Wdl, i32)> = None;
+    for mv in MoveGenerator::generate_legal_moves(board) {
+        let mut next = board.clone();
+        next.make_move(&mv);
+
+        let wdl = -probe_wdl(&next)?;
+        let dtz = if next.halfmove_clock == 0 {
+            dtz_before_zeroing(wdl)
+        } else {
+            let dtz = -probe_dtz(&next)?;
+            dtz + dtz.signum()
+        };
+
+        // Within the same result a lower DTZ is better, and mate beats all
+        let mates = next.is_in_check(next.side_to_move) && MoveGenerator::generate_legal_moves(&next).is_empty();
+
+        // A win or loss the clock runs out on before the next zeroing move
+        // is only a draw, as for the reference root_probe; mate ends the
+        // game first
+        let moves_left = 100 - board.halfmove_clock as i32;
+        let wdl = match wdl {
+            Wdl::Win if dtz > moves_left && !mates => Wdl::CursedWin,
+            Wdl::Loss if -dtz > moves_left => Wdl::BlessedLoss,
+            wdl => wdl,
+        };
+        let rank = if mates { i32::MAX } else { -dtz };
+
+        if best.is_none_or(|(_, best_wdl, best_rank)| (wdl, rank) > (best_wdl, best_rank)) {
+            best = Some((mv, wdl, rank));
+        }
+    }
+
+    best.map(|(mv, wdl, _)| (mv, wdl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The loaded tables are global; tests that call init hold this
+    static TABLES_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+    // Reference files the decoder is checked against, from any Syzygy
+    // mirror: KQvK, KPvK, KBNvK and KNNvK, .rtbw and .rtbz each. Looked up
+    // in SYZYGY_TEST_PATH, else tests/syzygy. The tests using them are
+    // ignored by default; run them with `cargo test --features syzygy --
+    // --ignored` once the files are in place.
+    const REFERENCE_TABLES: [&str; 4] = ["KQvK", "KPvK", "KBNvK", "KNNvK"];
+
+    fn reference_path() -> String {
+        let path = std::env::var("SYZYGY_TEST_PATH")
+            .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy").to_string());
+        for name in REFERENCE_TABLES {
+            for ext in ["rtbw", "rtbz"] {
+                let file = PathBuf::from(&path).join(format!("{}.{}", name, ext));
+                assert!(file.is_file(), "Syzygy reference table {} is missing", file.display());
+            }
+        }
+        path
+    }
+
+    #[test]
+    fn test_encoding_tables() {
+        let enc = &*ENCODING;
+
+        // 462 distinct king pairs
+        let mut codes: Vec<u64> = Vec::new();
+        for idx in 0..10 {
+            for s1 in 0..=27u8 {
+                if enc.map_a1d1d4[s1 as usize] == idx as u64 && (idx != 0 || s1 == 1) {
+                    for s2 in 0..64usize {
+                        if (ATTACK_TABLES.king_attacks[s1 as usize] | (1u64 << s1)) & (1u64 << s2) == 0
+                            && !(off_a1h8(s1) == 0 && off_a1h8(s2 as u8) > 0)
+                        {
+                            codes.push(enc.map_kk[idx][s2]);
+                        }
+                    }
+                }
+            }
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), 462);
+        assert_eq!(*codes.last().unwrap(), 461);
+
+        assert_eq!(enc.binomial[2][5], 10);
+        assert_eq!(enc.binomial[3][48], 17296);
+        assert_eq!(enc.map_pawns[8], 47); // a2
+        assert_eq!(enc.map_pawns[15], 46); // h2
+    }
+
+    #[test]
+    fn test_table_names() {
+        let table = Table::new("KRPvKR", TableKind::Wdl, PathBuf::new()).unwrap();
+        assert_eq!(table.piece_count, 5);
+        assert!(table.has_pawns);
+        assert_eq!(table.pawn_count, [1, 0]);
+        assert_ne!(table.key, table.key2);
+
+        let board = BoardState::from_fen("8/8/4k3/8/2r5/4P3/4K3/4R3 w - - 0 1").unwrap();
+        assert_eq!(board_material_key(&board), table.key);
+
+        let symmetric = Table::new("KRvKR", TableKind::Wdl, PathBuf::new()).unwrap();
+        assert_eq!(symmetric.key, symmetric.key2);
+
+        assert!(Table::new("KRvR", TableKind::Wdl, PathBuf::new()).is_none());
+        assert!(Table::new("KXvK", TableKind::Wdl, PathBuf::new()).is_none());
+    }
+
+    #[test]
+    fn test_probe_without_tables() {
+        let _tables = TABLES_LOCK.lock();
+        assert_eq!(init(""), 0);
+        let board = BoardState::from_fen("8/8/4k3/8/8/4P3/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(probe_wdl(&board), None);
+        assert_eq!(probe_dtz(&board), None);
+        assert!(probe_root(&board).is_none());
+    }
+
+    #[test]
+    #[ignore = "needs the Syzygy reference tables"]
+    fn test_reference_wdl() {
+        let _tables = TABLES_LOCK.lock();
+        let path = reference_path();
+        // The directory may hold more than the reference set
+        assert!(init(&path) >= REFERENCE_TABLES.len());
+        assert!(max_pieces() >= 4);
+
+        let cases = [
+            // Either side to move, with and without pawns
+            ("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1", Wdl::Win),
+            ("k7/8/8/8/8/8/8/4K2Q b - - 0 1", Wdl::Loss),
+            ("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", Wdl::Win),
+            ("8/4P3/8/8/8/8/k7/4K3 b - - 0 1", Wdl::Loss),
+            // Rook pawn with the defending king in the corner
+            ("k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw),
+            ("7k/8/8/8/8/8/8/KBN5 w - - 0 1", Wdl::Win),
+            ("7k/8/8/8/8/8/8/KNN5 w - - 0 1", Wdl::Draw),
+            // Black takes the queen
+            ("8/8/8/8/8/8/1Q6/1k5K b - - 0 1", Wdl::Draw),
+        ];
+        for (fen, expected) in cases {
+            assert_eq!(probe_wdl(&BoardState::from_fen(fen).unwrap()), Some(expected), "{}", fen);
+        }
+
+        // Positions with castling rights are never probed
+        assert_eq!(probe_wdl(&BoardState::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap()), None);
+        init("");
+    }
+
+    #[test]
+    #[ignore = "needs the Syzygy reference tables"]
+    fn test_reference_dtz_and_root() {
+        let _tables = TABLES_LOCK.lock();
+        let path = reference_path();
+        init(&path);
+
+        // Mate in one and a winning promotion are both one ply from zeroing
+        let mate_in_one = BoardState::from_fen("7k/5Q2/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        assert_eq!(probe_dtz(&mate_in_one), Some(1));
+        assert_eq!(probe_dtz(&BoardState::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap()), Some(1));
+        assert!(probe_dtz(&BoardState::from_fen("k7/8/8/8/8/8/8/4K2Q b - - 0 1").unwrap()).is_some_and(|dtz| dtz < 0));
+        assert_eq!(probe_dtz(&BoardState::from_fen("k7/8/8/8/8/8/P7/K7 w - - 0 1").unwrap()), Some(0));
+
+        // The root move mates rather than merely keeping the win
+        let (mv, wdl) = probe_root(&mate_in_one).unwrap();
+        assert_eq!(wdl, Wdl::Win);
+        let mut after = mate_in_one.clone();
+        after.make_move(&mv);
+        assert!(after.is_in_check(after.side_to_move));
+        assert!(MoveGenerator::generate_legal_moves(&after).is_empty());
+
+        // A KBNvK win needs many moves. The root move keeps it and brings
+        // zeroing a ply closer; tables may store DTZ rounded by one ply.
+        let mut board = BoardState::from_fen("7k/8/8/8/8/8/8/KBN5 w - - 0 1").unwrap();
+        let dtz = probe_dtz(&board).unwrap();
+        assert!(dtz > 1 && dtz <= 100, "{}", dtz);
+        let (mv, wdl) = probe_root(&board).unwrap();
+        assert_eq!(wdl, Wdl::Win);
+        board.make_move(&mv);
+        assert_eq!(probe_wdl(&board), Some(Wdl::Loss));
+        let reply_dtz = probe_dtz(&board).unwrap();
+        assert!(reply_dtz < 0 && (-reply_dtz - (dtz - 1)).abs() <= 1, "{} then {}", dtz, reply_dtz);
+
+        // The same win with the fifty-move count nearly used up is a draw,
+        // but a mate in one still mates
+        let late = BoardState::from_fen("7k/8/8/8/8/8/8/KBN5 w - - 99 80").unwrap();
+        assert_eq!(probe_root(&late).unwrap().1, Wdl::CursedWin);
+        let late_mate = BoardState::from_fen("7k/5Q2/6K1/8/8/8/8/8 w - - 99 80").unwrap();
+        assert_eq!(probe_root(&late_mate).unwrap().1, Wdl::Win);
+        init("");
+    }
+}
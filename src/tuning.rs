@@ -0,0 +1,252 @@
+use crate::board::{BoardState, Color};
+use crate::eval::{EvalParams, Evaluator};
+use crate::movegen::MoveGenerator;
+use rayon::prelude::*;
+use std::fs;
+
+// Full passes over the parameter vector before giving up on convergence
+const MAX_PASSES: usize = 20;
+
+// ══════════════════════════════════════════════════════════════════════════════
+// TEXEL TUNING
+// ══════════════════════════════════════════════════════════════════════════════
+
+/// Reads `FEN;result` lines, where result is 1.0, 0.5 or 0.0 from white's
+/// point of view. Blank lines and lines starting with `#` are skipped.
+pub fn load_positions(path: &str) -> Result<Vec<(BoardState, f64)>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut positions = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (fen, result) = line
+            .rsplit_once(';')
+            .ok_or_else(|| format!("line {}: expected FEN;result", number + 1))?;
+        let board = BoardState::from_fen(fen.trim()).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        let result: f64 = result
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: bad result '{}'", number + 1, result.trim()))?;
+
+        if !(0.0..=1.0).contains(&result) {
+            return Err(format!("line {}: result must be between 0.0 and 1.0", number + 1));
+        }
+
+        positions.push((board, result));
+    }
+
+    Ok(positions)
+}
+
+/// Local search over every weight in `params`, stepping each one by ±1 and
+/// keeping the change whenever the mean squared error against the game
+/// results drops. `k` scales centipawns into the sigmoid.
+pub fn tune(positions: &[(BoardState, f64)], params: &mut EvalParams, k: f64) {
+    if positions.is_empty() {
+        return;
+    }
+
+    let mut best_error = mean_squared_error(positions, params, k);
+    let param_count = params_mut(params).len();
+
+    for _ in 0..MAX_PASSES {
+        let mut improved = false;
+
+        for index in 0..param_count {
+            for step in [1, -1] {
+                *params_mut(params)[index] += step;
+                let error = mean_squared_error(positions, params, k);
+
+                if error < best_error {
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+
+                *params_mut(params)[index] -= step;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}
+
+pub fn mean_squared_error(positions: &[(BoardState, f64)], params: &EvalParams, k: f64) -> f64 {
+    let total: f64 = positions
+        .par_iter()
+        .map(|(board, result)| {
+            let score = quiescence(&Evaluator::new(params), board, -30000, 30000);
+            let white_score = if board.side_to_move == Color::White { score } else { -score };
+            let error = result - sigmoid(white_score, k);
+            error * error
+        })
+        .sum();
+
+    total / positions.len() as f64
+}
+
+fn sigmoid(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+// Captures-only search so tactics in the sample positions don't skew the error
+fn quiescence(evaluator: &Evaluator, board: &BoardState, mut alpha: i32, beta: i32) -> i32 {
+    let stand_pat = evaluator.evaluate(board);
+
+    if stand_pat >= beta {
+        return beta;
+    }
+    if stand_pat > alpha {
+        alpha = stand_pat;
+    }
+
+    for mv in MoveGenerator::generate_captures(board) {
+        if Evaluator::see(board, &mv) < 0 {
+            continue;
+        }
+
+        let mut new_board = board.clone();
+        new_board.make_move(&mv);
+
+        let score = -quiescence(evaluator, &new_board, -beta, -alpha);
+
+        if score >= beta {
+            return beta;
+        }
+        if score > alpha {
+            alpha = score;
+        }
+    }
+
+    alpha
+}
+
+// Flat view of every tunable weight, in declaration order
+fn params_mut(params: &mut EvalParams) -> Vec<&mut i32> {
+    let mut values = vec![
+        &mut params.pawn_value,
+        &mut params.knight_value,
+        &mut params.bishop_value,
+        &mut params.rook_value,
+        &mut params.queen_value,
+        &mut params.absolute_pin_penalty,
+        &mut params.relative_pin_penalty,
+        &mut params.fork_bonus,
+        &mut params.skewer_bonus,
+        &mut params.discovered_attack_bonus,
+        &mut params.trapped_piece,
+        &mut params.threat_bonus,
+        &mut params.tactical_safety_weight,
+        &mut params.bishop_pair_bonus,
+        &mut params.rook_open_file,
+        &mut params.rook_semi_open,
+        &mut params.rook_seventh_rank,
+        &mut params.connected_rooks,
+        &mut params.rook_behind_passer,
+        &mut params.doubled_rooks,
+        &mut params.knight_outpost,
+        &mut params.bad_bishop_penalty,
+        &mut params.fianchetto_bonus,
+        &mut params.trapped_bishop,
+        &mut params.doubled_pawn,
+        &mut params.isolated_pawn,
+        &mut params.backward_pawn,
+        &mut params.pawn_chain_bonus,
+        &mut params.outside_passer,
+        &mut params.pawn_storm_bonus,
+        &mut params.pawn_shield_bonus,
+        &mut params.open_file_near_king,
+        &mut params.castling_rights_bonus,
+        &mut params.king_tropism,
+        &mut params.space_bonus,
+        &mut params.knight_mobility,
+        &mut params.bishop_mobility,
+        &mut params.rook_mobility,
+        &mut params.queen_mobility,
+        &mut params.tempo_bonus,
+        &mut params.imbalance_bishop_pair,
+        &mut params.imbalance_knight_pawn,
+        &mut params.imbalance_rook_pawn,
+        &mut params.imbalance_rook_pair,
+        &mut params.imbalance_queen_rook,
+        &mut params.imbalance_rooks_vs_queen,
+        &mut params.imbalance_exchange,
+    ];
+
+    values.extend(params.passed_pawn_bonus.iter_mut());
+    values.extend(params.protected_passed_pawn.iter_mut());
+    values.extend(params.candidate_passed.iter_mut());
+    values.extend(params.connected_passer.iter_mut());
+    values.extend(params.king_attack_weight.iter_mut());
+
+    for table in [
+        &mut params.pawn_pst_mg,
+        &mut params.pawn_pst_eg,
+        &mut params.knight_pst_mg,
+        &mut params.knight_pst_eg,
+        &mut params.bishop_pst_mg,
+        &mut params.bishop_pst_eg,
+        &mut params.rook_pst_mg,
+        &mut params.rook_pst_eg,
+        &mut params.queen_pst_mg,
+        &mut params.queen_pst_eg,
+        &mut params.king_pst_mg,
+        &mut params.king_pst_eg,
+    ] {
+        values.extend(table.iter_mut());
+    }
+
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_positions() {
+        let path = std::env::temp_dir().join("chess_engine_tuning_positions.txt");
+        fs::write(
+            &path,
+            "# comment\n4k3/8/8/8/8/8/8/2N1K3 w - - 0 1; 1.0\n\n4k3/8/8/8/8/8/8/4K3 b - - 0 1;0.5\n",
+        )
+        .unwrap();
+
+        let positions = load_positions(path.to_str().unwrap()).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].1, 1.0);
+        assert_eq!(positions[1].1, 0.5);
+
+        fs::write(&path, "4k3/8/8/8/8/8/8/4K3 w - - 0 1;2.0\n").unwrap();
+        assert!(load_positions(path.to_str().unwrap()).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tuning_rewards_winning_material() {
+        // Every game with the extra knight was won, so a knight is worth more
+        // than the default weights claim
+        let positions: Vec<(BoardState, f64)> = [
+            ("4k3/4p3/8/8/8/8/4P3/2N1K3 w - - 0 1", 1.0),
+            ("4k3/4p3/8/8/8/8/4P3/4KN2 b - - 0 1", 1.0),
+            ("2n1k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1", 0.0),
+        ]
+        .iter()
+        .map(|(fen, result)| (BoardState::from_fen(fen).unwrap(), *result))
+        .collect();
+
+        let mut params = EvalParams::default();
+        let before = mean_squared_error(&positions, &params, 1.0);
+        tune(&positions, &mut params, 1.0);
+
+        assert!(mean_squared_error(&positions, &params, 1.0) < before);
+        assert!(params.knight_value > EvalParams::default().knight_value);
+    }
+}
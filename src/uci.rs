@@ -1,20 +1,73 @@
-use crate::board::BoardState;
-use crate::search::SearchEngine;
+use crate::board::{BoardState, Color};
+use crate::search::{
+    SearchEngine, DEFAULT_BOOK_DEPTH, MAX_BOOK_DEPTH, MAX_ELO, MAX_HASH_MB, MAX_MULTI_PV, MAX_SKILL_LEVEL, MAX_THREADS,
+    MIN_ELO, MIN_HASH_MB,
+};
+use crate::eval::Evaluator;
 use crate::movegen::{Move, MoveGenerator};
+use parking_lot::Mutex;
 use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Fixed suite for `bench`: openings, middlegames and endgames, all searched
+const BENCH_POSITIONS: [&str; 15] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+];
+
+const BENCH_DEPTH: u8 = 8;
 
 pub struct UCIEngine {
     board: BoardState,
-    search_engine: SearchEngine,
+    // Locked by the search thread for as long as it searches, so commands
+    // that need the engine wait for the search to finish
+    search_engine: Arc<Mutex<SearchEngine>>,
+    // The engine's stop and ponder flags, usable while it is locked
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+    // Runs `go`; it prints its own bestmove
+    search_thread: Option<JoinHandle<()>>,
     debug: bool,
+    move_overhead: u64,
+    // UCI_Chess960: castling is read and written as king takes rook
+    chess960: bool,
+    // Best move of the last search from the current position, repeated to
+    // a `stop` that arrives after it finished
+    last_best_move: Arc<Mutex<Option<Move>>>,
+    // Set by `go` and cleared once its bestmove is printed
+    bestmove_pending: Arc<AtomicBool>,
 }
 
 impl UCIEngine {
     pub fn new() -> Self {
+        let search_engine = SearchEngine::new(4);
         UCIEngine {
             board: BoardState::default(),
-            search_engine: SearchEngine::new(4),
+            stop: search_engine.stop_handle(),
+            ponder: search_engine.ponder_handle(),
+            search_engine: Arc::new(Mutex::new(search_engine)),
+            search_thread: None,
             debug: false,
+            move_overhead: 30,
+            chess960: false,
+            last_best_move: Arc::new(Mutex::new(None)),
+            bestmove_pending: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -32,9 +85,13 @@ impl UCIEngine {
                 }
             }
         }
+
+        // Closed input is a quit; either way a running search is cut short
+        // but still gets to print its bestmove
+        self.stop_search();
     }
 
-    fn handle_command(&mut self, command: &str) -> bool {
+    pub fn handle_command(&mut self, command: &str) -> bool {
         let parts: Vec<&str> = command.split_whitespace().collect();
         if parts.is_empty() {
             return true;
@@ -47,7 +104,15 @@ impl UCIEngine {
             "position" => self.position(&parts[1..]),
             "go" => self.go(&parts[1..]),
             "stop" => self.stop(),
-            "quit" => return false,
+            // The predicted move was played: the ponder search carries on
+            // as a normal one, its clock starting now
+            "ponderhit" => self.ponder.store(false, Ordering::Relaxed),
+            // Nothing to register
+            "register" => println!("registration ok"),
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
             "debug" => {
                 if parts.len() > 1 {
                     self.debug = parts[1] == "on";
@@ -55,6 +120,9 @@ impl UCIEngine {
             }
             "setoption" => self.setoption(&parts[1..]),
             "d" => self.display(),
+            "eval" => self.eval(),
+            "bench" => self.bench(&parts[1..]),
+            "perft" => self.perft(&parts[1..]),
             _ => {
                 if self.debug {
                     println!("info string Unknown command: {}", command);
@@ -68,10 +136,29 @@ impl UCIEngine {
     fn uci(&self) {
         println!("id name RustChessEngine Ultimate v6.0 (Stockfish-Level)");
         println!("id author Enhanced Rust Team");
-        println!("option name Hash type spin default 512 min 16 max 32768");
-        println!("option name Threads type spin default 4 min 1 max 256");
+        println!("option name Hash type spin default 512 min {} max {}", MIN_HASH_MB, MAX_HASH_MB);
+        println!("option name Threads type spin default 4 min 1 max {}", MAX_THREADS);
         println!("option name ClearHash type button");
-        println!("option name MultiPV type spin default 1 min 1 max 5");
+        println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV);
+        println!("option name Deterministic type check default false");
+        println!("option name Contempt type spin default 0 min -100 max 100");
+        println!("option name Move Overhead type spin default 30 min 0 max 5000");
+        println!("option name Ponder type check default false");
+        println!("option name UCI_ShowWDL type check default false");
+        println!("option name UCI_Chess960 type check default false");
+        println!("option name Skill Level type spin default {0} min 0 max {0}", MAX_SKILL_LEVEL);
+        println!("option name UCI_LimitStrength type check default false");
+        println!("option name UCI_Elo type spin default {0} min {0} max {1}", MIN_ELO, MAX_ELO);
+        println!("option name OwnBook type check default true");
+        println!("option name BookBestMove type check default false");
+        println!("option name BookDepth type spin default {} min 0 max {}", DEFAULT_BOOK_DEPTH, MAX_BOOK_DEPTH);
+        println!("option name BookSeed type spin default 0 min 0 max 2147483647");
+        println!("option name UCI_AnalyseMode type check default false");
+        println!("option name BookFile type string default <empty>");
+        #[cfg(feature = "nnue")]
+        println!("option name EvalFile type string default <empty>");
+        #[cfg(feature = "syzygy")]
+        println!("option name SyzygyPath type string default <empty>");
         println!("uciok");
     }
 
@@ -80,8 +167,9 @@ impl UCIEngine {
     }
 
     fn ucinewgame(&mut self) {
-        self.search_engine.new_game();
+        self.search_engine.lock().new_game();
         self.board = BoardState::default();
+        *self.last_best_move.lock() = None;
         if self.debug {
             println!("info string New game started");
         }
@@ -91,6 +179,7 @@ impl UCIEngine {
         if args.is_empty() {
             return;
         }
+        *self.last_best_move.lock() = None;
 
         let mut move_idx = 1;
 
@@ -116,15 +205,12 @@ impl UCIEngine {
         } else {
             return;
         }
+        self.board.chess960 = self.chess960;
 
         // Apply moves
         if move_idx < args.len() && args[move_idx] == "moves" {
-            for move_str in &args[move_idx + 1..] {
-                if let Some(mv) = self.parse_uci_move(move_str) {
-                    self.board.make_move(&mv);
-                } else if self.debug {
-                    println!("info string Invalid move: {}", move_str);
-                }
+            if let Err(e) = self.apply_moves(&args[move_idx + 1..]) {
+                println!("info string {}", e);
             }
         }
 
@@ -133,6 +219,21 @@ impl UCIEngine {
         }
     }
 
+    /// Plays the `moves` list of a position command. Stops at the first
+    /// move that isn't legal, since everything after it was meant for a
+    /// different position than ours.
+    fn apply_moves(&mut self, moves: &[&str]) -> Result<(), String> {
+        for move_str in moves {
+            match self.parse_uci_move(move_str) {
+                Some(mv) => {
+                    self.board.make_move(&mv);
+                }
+                None => return Err(format!("illegal move {} in position command", move_str)),
+            }
+        }
+        Ok(())
+    }
+
     fn go(&mut self, args: &[&str]) {
         let mut depth = 64;
         let mut time_ms = None;
@@ -141,6 +242,9 @@ impl UCIEngine {
         let mut winc = 0;
         let mut binc = 0;
         let mut movestogo = 40;
+        let mut nodes = None;
+        let mut infinite = false;
+        let mut ponder = false;
 
         let mut i = 0;
         while i < args.len() {
@@ -201,9 +305,20 @@ impl UCIEngine {
                         i += 1;
                     }
                 }
+                "nodes" => {
+                    if i + 1 < args.len() {
+                        nodes = args[i + 1].parse().ok();
+                        i += 2;
+                    } else {
+                        i += 1;
+                    }
+                }
                 "infinite" => {
-                    depth = 100;
-                    time_ms = None;
+                    infinite = true;
+                    i += 1;
+                }
+                "ponder" => {
+                    ponder = true;
                     i += 1;
                 }
                 _ => i += 1,
@@ -213,75 +328,143 @@ impl UCIEngine {
         // Enhanced time management
         if time_ms.is_none() {
             if let (Some(wt), Some(bt)) = (wtime, btime) {
-                let my_time: u64 = if self.board.side_to_move == crate::board::Color::White {
-                    wt as u64
-                } else {
-                    bt as u64
-                };
-                let my_inc = if self.board.side_to_move == crate::board::Color::White {
-                    winc
+                let (my_time, my_inc) = if self.board.side_to_move == crate::board::Color::White {
+                    (wt, winc)
                 } else {
-                    binc
+                    (bt, binc)
                 };
 
-                // Sophisticated time allocation
-                let time_fraction = if movestogo > 0 {
-                    1.0 / (movestogo + 8) as f64
-                } else {
-                    let moves_remaining = (50 - self.board.fullmove_number).max(25);
-                    1.0 / moves_remaining as f64
-                };
-
-                let mut allocated = (my_time as f64 * time_fraction + my_inc as f64 * 0.75) as u64;
-
-                // Game phase adjustments
-                if self.board.fullmove_number < 10 {
-                    allocated = (allocated as f64 * 0.7) as u64;
-                } else if self.board.fullmove_number > 40 {
-                    allocated = (allocated as f64 * 1.4) as u64;
-                }
+                let allocated = self.allocate_time(
+                    (my_time as i64).max(0) as u64,
+                    (my_inc as i64).max(0) as u64,
+                    (movestogo as i64).max(0) as u64,
+                );
+                time_ms = Some(allocated);
 
-                // Critical position bonus
-                if self.board.is_in_check(self.board.side_to_move) {
-                    allocated = (allocated as f64 * 1.5) as u64;
+                if self.debug {
+                    println!("info string Allocated {}ms for this move", allocated);
                 }
+            }
+        } else {
+            time_ms = time_ms.map(|t: u64| t.saturating_sub(self.move_overhead).max(1));
+        }
 
-                // Safety margin
-                let safety_margin = (my_time / 15).max(2000);
-                allocated = allocated.min(my_time.saturating_sub(safety_margin));
+        // Search on a thread of its own so stop and ponderhit are read
+        // while it runs. Pondering is flagged here, not on the thread, so a
+        // quick ponderhit can't be missed.
+        self.wait_for_search();
+        if ponder {
+            self.ponder.store(true, Ordering::Relaxed);
+        }
+        self.bestmove_pending.store(true, Ordering::Relaxed);
 
-                // Absolute bounds
-                allocated = allocated.max(100).min(150000);
+        let search_engine = Arc::clone(&self.search_engine);
+        let last_best_move = Arc::clone(&self.last_best_move);
+        let bestmove_pending = Arc::clone(&self.bestmove_pending);
+        let board = self.board.clone();
+        let debug = self.debug;
 
-                time_ms = Some(allocated);
+        self.search_thread = Some(std::thread::spawn(move || {
+            let mut engine = search_engine.lock();
+            engine.set_node_limit(nodes);
+            let start_time = Instant::now();
+            let result = if infinite {
+                engine.search_infinite(board.clone())
+            } else if ponder {
+                engine.search_ponder(board.clone(), depth, time_ms)
+            } else {
+                engine.search(board.clone(), depth, time_ms)
+            };
 
-                if self.debug {
-                    println!("info string Allocated {}ms for this move", allocated);
+            if debug {
+                let elapsed_ms = start_time.elapsed().as_millis().max(1);
+                for (thread_id, nodes) in result.thread_nodes.iter().enumerate() {
+                    println!(
+                        "info string thread {} nodes {} nps {}",
+                        thread_id,
+                        nodes,
+                        *nodes as u128 * 1000 / elapsed_ms
+                    );
                 }
             }
+
+            *last_best_move.lock() = result.best_move;
+            println!("{}", bestmove_line(&board, result.best_move, result.pv.get(1).copied()));
+            bestmove_pending.store(false, Ordering::Relaxed);
+        }));
+    }
+
+    fn last_bestmove_line(&self) -> String {
+        bestmove_line(&self.board, *self.last_best_move.lock(), None)
+    }
+
+    // Blocks until the running search, if any, has printed its bestmove
+    fn wait_for_search(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            handle.join().expect("search thread panicked");
         }
+    }
 
-        // Check for draw
-        if self.board.is_draw() && self.debug {
-            println!("info string Position is drawn");
+    // Budget for one move out of the remaining clock, with Move Overhead
+    // already taken off so lag on the GUI side can't flag us
+    fn allocate_time(&self, my_time: u64, my_inc: u64, movestogo: u64) -> u64 {
+        // Sophisticated time allocation
+        let time_fraction = if movestogo > 0 {
+            1.0 / (movestogo + 8) as f64
+        } else {
+            let moves_remaining = 50u16.saturating_sub(self.board.fullmove_number).max(25);
+            1.0 / moves_remaining as f64
+        };
+
+        let mut allocated = (my_time as f64 * time_fraction + my_inc as f64 * 0.75) as u64;
+
+        // Game phase adjustments
+        if self.board.fullmove_number < 10 {
+            allocated = (allocated as f64 * 0.7) as u64;
+        } else if self.board.fullmove_number > 40 {
+            allocated = (allocated as f64 * 1.4) as u64;
         }
 
-        // Search
-        let result = self.search_engine.search(
-            self.board.clone(),
-            depth,
-            time_ms,
-        );
+        // Critical position bonus
+        if self.board.is_in_check(self.board.side_to_move) {
+            allocated = (allocated as f64 * 1.5) as u64;
+        }
+
+        allocated = allocated.saturating_sub(self.move_overhead);
+
+        // Safety margin, on top of the overhead the GUI will eat anyway
+        let safety_margin = (my_time / 15).max(2000) + self.move_overhead;
+        allocated = allocated.min(my_time.saturating_sub(safety_margin));
+
+        // Absolute bounds
+        allocated.max(100).min(150000)
+    }
+
+    fn searching(&self) -> bool {
+        self.search_thread.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
 
-        if let Some(best_move) = result.best_move {
-            println!("bestmove {}", best_move.to_uci());
+    // A GUI waits for a bestmove after every stop. A `go` still waiting
+    // for its own gets it from the search; otherwise the last one is
+    // repeated, or `0000` if there is none.
+    fn stop(&mut self) {
+        if self.bestmove_pending.load(Ordering::Relaxed) {
+            self.stop_search();
         } else {
-            println!("bestmove 0000");
+            self.wait_for_search();
+            println!("{}", self.last_bestmove_line());
         }
     }
 
-    fn stop(&mut self) {
-        self.search_engine.stop();
+    // Ends the running search, infinite and ponder searches included, once
+    // it has printed its bestmove. The flag is raised until it finishes: a
+    // search only just starting clears it on entry.
+    fn stop_search(&mut self) {
+        while self.searching() {
+            self.stop.store(true, Ordering::Relaxed);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        self.wait_for_search();
     }
 
     fn setoption(&mut self, args: &[&str]) {
@@ -302,7 +485,7 @@ impl UCIEngine {
         // Button option
         if value_idx >= args.len() {
             if name == "clearhash" {
-                self.search_engine.clear_tt();
+                self.search_engine.lock().clear_tt();
                 if self.debug {
                     println!("info string Hash table cleared");
                 }
@@ -317,39 +500,144 @@ impl UCIEngine {
         let value = args[value_idx + 1];
 
         match name.as_str() {
-            "hash" => {
-                if let Ok(size_mb) = value.parse::<usize>() {
-                    self.search_engine.set_hash_size(size_mb);
+            "move overhead" => {
+                if let Ok(overhead) = value.parse::<u64>() {
+                    self.move_overhead = overhead.min(5000);
                     if self.debug {
-                        println!("info string Hash table set to {} MB", size_mb);
+                        println!("info string Move Overhead set to {}ms", self.move_overhead);
                     }
                 }
             }
-            "threads" => {
-                if let Ok(threads) = value.parse::<usize>() {
-                    self.search_engine.set_threads(threads);
-                    if self.debug {
-                        println!("info string Threads set to {}", threads);
+            // The GUI decides whether to send `go ponder`; nothing to set
+            "ponder" => {}
+            "uci_chess960" => {
+                self.chess960 = value.eq_ignore_ascii_case("true");
+                self.board.chess960 = self.chess960;
+            }
+            "bookfile" => {
+                let path = args[value_idx + 1..].join(" ");
+                if path.is_empty() || path == "<empty>" {
+                    crate::opening_book::unload_polyglot();
+                } else {
+                    match crate::opening_book::load_polyglot(&path) {
+                        Ok(count) => println!("info string Loaded {} book entries from {}", count, path),
+                        Err(e) => println!("info string Failed to load book: {}", e),
                     }
                 }
             }
-            "multipv" => {
-                if let Ok(count) = value.parse::<usize>() {
-                    self.search_engine.set_multi_pv(count);
-                    if self.debug {
-                        println!("info string MultiPV set to {}", count);
+            #[cfg(feature = "nnue")]
+            "evalfile" => {
+                // Paths may contain spaces
+                let path = args[value_idx + 1..].join(" ");
+                let path = if path == "<empty>" { String::new() } else { path };
+                match self.search_engine.lock().set_eval_file(&path) {
+                    Ok(()) if path.is_empty() => {}
+                    Ok(()) => println!("info string Loaded NNUE {}", path),
+                    Err(e) => println!("info string Failed to load NNUE: {}", e),
+                }
+            }
+            #[cfg(feature = "syzygy")]
+            "syzygypath" => {
+                let path = args[value_idx + 1..].join(" ");
+                let found = crate::tablebase::init(&path);
+                println!(
+                    "info string Found {} tablebases, up to {} pieces",
+                    found,
+                    crate::tablebase::max_pieces()
+                );
+            }
+            // Everything the search engine owns, including the search
+            // tuning knobs left out of the `uci` option list
+            _ => {
+                let result = self.search_engine.lock().set_option(&name, value);
+                if self.debug {
+                    match result {
+                        Ok(msg) => println!("info string {}", msg),
+                        Err(e) => println!("info string {}", e),
                     }
                 }
             }
-            _ => {}
         }
     }
 
+    fn bench(&self, args: &[&str]) {
+        let depth = args.first().and_then(|d| d.parse().ok()).unwrap_or(BENCH_DEPTH);
+        let start = Instant::now();
+        let nodes = Self::run_bench(depth);
+        let elapsed_ms = start.elapsed().as_millis().max(1) as u64;
+
+        println!("{} nodes {} nps", nodes, nodes * 1000 / elapsed_ms);
+    }
+
+    // Total nodes over the bench suite. Uses its own single-threaded,
+    // deterministic engine so the count doesn't depend on the UCI options.
+    fn run_bench(depth: u8) -> u64 {
+        let mut engine = SearchEngine::new(1);
+        engine.set_hash_size(16);
+        engine.set_deterministic(true);
+        engine.set_own_book(false);
+
+        BENCH_POSITIONS
+            .iter()
+            .map(|fen| {
+                let board = BoardState::from_fen(fen).expect("invalid bench FEN");
+                engine.search(board, depth, None).nodes
+            })
+            .sum()
+    }
+
+    // `perft <depth>` or `perft divide <depth>` on the current position
+    fn perft(&self, args: &[&str]) {
+        let (divide, depth) = match args {
+            ["divide", rest @ ..] => (true, rest.first()),
+            _ => (false, args.first()),
+        };
+        let Some(depth) = depth.and_then(|d| d.parse::<u32>().ok()).filter(|&d| d > 0) else {
+            println!("info string Usage: perft [divide] <depth>");
+            return;
+        };
+
+        let start = Instant::now();
+        let report = self.perft_report(depth, divide);
+        println!("{}", report);
+        println!("Time: {} ms", start.elapsed().as_millis());
+    }
+
+    // Leaf count, preceded with `divide` by one "move: nodes" line per root
+    // move, sorted by move so it lines up against other engines' output
+    fn perft_report(&self, depth: u32, divide: bool) -> String {
+        if !divide {
+            return format!("Nodes searched: {}", MoveGenerator::perft(&self.board, depth));
+        }
+
+        let mut counts: Vec<(String, u64)> = MoveGenerator::perft_divide(&self.board, depth)
+            .into_iter()
+            .map(|(mv, nodes)| (mv.to_uci_for(&self.board), nodes))
+            .collect();
+        counts.sort();
+
+        let total: u64 = counts.iter().map(|(_, nodes)| nodes).sum();
+        let mut report: String = counts.iter().map(|(mv, nodes)| format!("{}: {}\n", mv, nodes)).collect();
+        report.push_str(&format!("\nNodes searched: {}", total));
+        report
+    }
+
     fn display(&self) {
         println!("\n{}", self.board.to_fen());
         println!();
     }
 
+    fn eval(&self) {
+        println!("\n{}\n", self.eval_report());
+    }
+
+    // Term breakdown followed by the score from the side to move and from white
+    fn eval_report(&self) -> String {
+        let trace = Evaluator::new(self.search_engine.lock().eval_params()).evaluate_trace(&self.board);
+        let white_score = if self.board.side_to_move == Color::White { trace.score } else { -trace.score };
+        format!("{}\nWhite side: {}\nFinal evaluation: {:+.2} (white side)", trace, white_score, white_score as f64 / 100.0)
+    }
+
     fn parse_uci_move(&self, uci: &str) -> Option<Move> {
         if uci.len() < 4 {
             return None;
@@ -361,7 +649,7 @@ impl UCIEngine {
         let legal_moves = MoveGenerator::generate_legal_moves(&self.board);
         
         for mv in legal_moves {
-            if mv.from == from && mv.to == to {
+            if mv.from == from && self.board.uci_to_square(&mv) == to {
                 if uci.len() == 5 {
                     let promo_char = uci.chars().nth(4)?;
                     let promo_piece = mv.promotion_piece()?;
@@ -387,6 +675,18 @@ impl UCIEngine {
     }
 }
 
+// `bestmove` with the reply we expect, for GUIs that ponder; `0000` when
+// there's no move to give. Moves are written as `board`'s game writes them.
+fn bestmove_line(board: &BoardState, best_move: Option<Move>, ponder_move: Option<Move>) -> String {
+    match (best_move, ponder_move) {
+        (Some(best_move), Some(ponder_move)) => {
+            format!("bestmove {} ponder {}", best_move.to_uci_for(board), ponder_move.to_uci_for(board))
+        }
+        (Some(best_move), None) => format!("bestmove {}", best_move.to_uci_for(board)),
+        (None, _) => "bestmove 0000".to_string(),
+    }
+}
+
 fn parse_square(s: &str) -> Option<u8> {
     if s.len() != 2 {
         return None;
@@ -405,4 +705,345 @@ fn parse_square(s: &str) -> Option<u8> {
 pub fn main() {
     let mut engine = UCIEngine::new();
     engine.run();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{IterationInfo, SearchInfo};
+
+    #[test]
+    fn test_move_overhead_reduces_allocation() {
+        let mut engine = UCIEngine::new();
+        engine.move_overhead = 0;
+        let without = engine.allocate_time(60000, 0, 40);
+
+        engine.move_overhead = 300;
+        let with = engine.allocate_time(60000, 0, 40);
+
+        assert_eq!(without - with, 300);
+    }
+
+    #[test]
+    fn test_eval_startpos_is_small() {
+        let engine = UCIEngine::new();
+        let report = engine.eval_report();
+
+        let side_to_move: i32 = report
+            .lines()
+            .find_map(|line| line.strip_prefix("Side to move: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        let white_side: i32 = report
+            .lines()
+            .find_map(|line| line.strip_prefix("White side: "))
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        assert!(side_to_move.abs() <= 50, "startpos eval {}", side_to_move);
+        assert_eq!(white_side, side_to_move);
+    }
+
+    #[test]
+    fn test_bench_counts_nodes() {
+        let nodes = UCIEngine::run_bench(3);
+        assert!(nodes > 0);
+        assert_eq!(UCIEngine::run_bench(3), nodes);
+    }
+
+    #[test]
+    fn test_perft_report() {
+        let mut engine = UCIEngine::new();
+        assert_eq!(engine.perft_report(3, false), "Nodes searched: 8902");
+
+        engine.handle_command("position startpos moves e2e4");
+        let report = engine.perft_report(2, true);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 22);
+        assert_eq!(lines[0], "a7a5: 30");
+        assert_eq!(lines[21], "Nodes searched: 600");
+    }
+
+    #[test]
+    fn test_perft_divide_chess960_castling() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("setoption name UCI_Chess960 value true");
+        engine.handle_command("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        // Castling shows up as the king taking its rook
+        let report = engine.perft_report(1, true);
+        assert!(report.contains("e1h1: 1"));
+        assert!(report.contains("e1a1: 1"));
+        assert!(report.ends_with("Nodes searched: 26"));
+    }
+
+    #[test]
+    fn test_setoption_threads() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("setoption name Threads value 16");
+        assert_eq!(engine.search_engine.lock().threads(), 16);
+
+        engine.handle_command("setoption name Threads value 100000");
+        assert_eq!(engine.search_engine.lock().threads(), MAX_THREADS);
+    }
+
+    #[test]
+    fn test_setoption_multipv() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("setoption name MultiPV value 3");
+        engine.handle_command("position fen r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8");
+
+        let result = engine.search_engine.lock().search(engine.board.clone(), 4, None);
+        assert_eq!(result.pv_lines.len(), 3);
+        assert_eq!(result.best_move, Some(result.pv_lines[0].0));
+    }
+
+    #[test]
+    fn test_chess960_castling_notation() {
+        let mut engine = UCIEngine::new();
+        let opening = "position startpos moves e2e4 e7e5 g1f3 g8f6 f1c4 f8c5";
+        let castled = "rnbqk2r/pppp1ppp/5n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4";
+
+        // Off, castling is the king's two-square step
+        engine.handle_command(&format!("{} e1h1", opening));
+        assert_eq!(engine.board.side_to_move, Color::White);
+        engine.handle_command(&format!("{} e1g1", opening));
+        assert_eq!(engine.board.to_fen(), castled);
+
+        // On, it is the king taking its rook, read and written
+        engine.handle_command("setoption name UCI_Chess960 value true");
+        engine.handle_command(&format!("{} e1h1", opening));
+        assert_eq!(engine.board.to_fen(), castled);
+
+        let castle = MoveGenerator::generate_legal_moves(&engine.board).into_iter().find(|mv| mv.is_castle());
+        assert_eq!(bestmove_line(&engine.board, castle, None), "bestmove e8h8");
+    }
+
+    #[test]
+    fn test_setoption_strength() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("setoption name Skill Level value 5");
+        assert_eq!(engine.search_engine.lock().skill_level(), 5);
+
+        engine.handle_command("setoption name UCI_LimitStrength value true");
+        engine.handle_command("setoption name UCI_Elo value 2850");
+        assert_eq!(engine.search_engine.lock().skill_level(), MAX_SKILL_LEVEL);
+        engine.handle_command("setoption name UCI_Elo value 1350");
+        assert_eq!(engine.search_engine.lock().skill_level(), 0);
+    }
+
+    #[test]
+    fn test_kingless_position_is_ignored() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("position fen 8/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(engine.board.to_fen(), BoardState::default().to_fen());
+    }
+
+    // Keeps the info strings a search sends
+    #[derive(Default)]
+    struct StringsInfo(Mutex<Vec<String>>);
+
+    impl SearchInfo for StringsInfo {
+        fn on_iteration(&self, _info: &IterationInfo) {}
+
+        fn on_string(&self, msg: &str) {
+            self.0.lock().push(msg.to_string());
+        }
+    }
+
+    #[test]
+    fn test_threefold_is_reported_and_still_searched() {
+        let mut engine = UCIEngine::new();
+        let info = Arc::new(StringsInfo::default());
+        engine.search_engine.lock().set_info(info.clone());
+        engine.handle_command("setoption name OwnBook value false");
+        let claim = "Draw by threefold repetition can be claimed";
+
+        engine.handle_command("position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+        engine.handle_command("go depth 4");
+        engine.wait_for_search();
+        assert!(!info.0.lock().iter().any(|msg| msg == claim));
+
+        engine.handle_command("position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+        engine.handle_command("go depth 4");
+        engine.wait_for_search();
+        assert!(info.0.lock().iter().any(|msg| msg == claim));
+        let best_move = engine.last_best_move.lock().expect("no move from a claimable draw");
+        assert!(MoveGenerator::generate_legal_moves(&engine.board).contains(&best_move));
+
+        info.0.lock().clear();
+        engine.handle_command("position fen 4k3/8/8/8/8/8/8/R3K3 w - - 100 80");
+        engine.handle_command("go depth 2");
+        engine.wait_for_search();
+        assert!(info.0.lock().iter().any(|msg| msg == "Draw by fifty-move rule can be claimed"));
+        assert!(engine.last_best_move.lock().is_some());
+    }
+
+    #[test]
+    fn test_illegal_move_halts_position() {
+        let mut engine = UCIEngine::new();
+        assert_eq!(
+            engine.apply_moves(&["e2e4", "e7e5", "e4e5", "g1f3"]),
+            Err("illegal move e4e5 in position command".to_string())
+        );
+
+        // Moves before the bad one are kept, the rest are dropped
+        let mut expected = BoardState::default();
+        for uci in ["e2e4", "e7e5"] {
+            assert!(expected.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(engine.board.to_fen(), expected.to_fen());
+
+        engine.handle_command("position startpos moves d2d4 d7d5 d4d5 b1c3");
+        let mut expected = BoardState::default();
+        for uci in ["d2d4", "d7d5"] {
+            assert!(expected.make_move_uci(uci).unwrap());
+        }
+        assert_eq!(engine.board.to_fen(), expected.to_fen());
+    }
+
+    #[test]
+    fn test_stop_without_search_gives_bestmove() {
+        let mut engine = UCIEngine::new();
+        assert!(engine.handle_command("stop"));
+        assert!(!engine.bestmove_pending.load(Ordering::Relaxed));
+        assert_eq!(engine.last_bestmove_line(), "bestmove 0000");
+
+        // After a search, stop repeats its move until the position changes
+        engine.handle_command("position fen 6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
+        engine.handle_command("go depth 3");
+        engine.wait_for_search();
+        assert!(!engine.bestmove_pending.load(Ordering::Relaxed));
+        assert!(engine.handle_command("stop"));
+        assert_eq!(engine.last_bestmove_line(), "bestmove d1d8");
+
+        engine.handle_command("position startpos");
+        assert_eq!(engine.last_bestmove_line(), "bestmove 0000");
+    }
+
+    #[test]
+    fn test_go_ponder_waits_for_ponderhit() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("position fen r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8");
+
+        // A 200ms budget doesn't run out while pondering
+        engine.handle_command("go ponder movetime 200");
+        std::thread::sleep(Duration::from_millis(600));
+        assert!(!engine.search_thread.as_ref().unwrap().is_finished());
+
+        // After ponderhit it does, counted from the ponderhit
+        let ponderhit = Instant::now();
+        engine.handle_command("ponderhit");
+        engine.wait_for_search();
+        assert!(ponderhit.elapsed() < Duration::from_millis(2000));
+        assert!(engine.last_best_move.lock().is_some());
+
+        // Stop ends a ponder search too
+        engine.handle_command("go ponder movetime 200");
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(engine.handle_command("stop"));
+        assert!(engine.search_thread.is_none());
+    }
+
+    #[test]
+    fn test_go_nodes_limits_search() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("setoption name OwnBook value false");
+        engine.handle_command("position fen r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8");
+
+        // No depth or clock: only the node budget ends it
+        let start = Instant::now();
+        engine.handle_command("go nodes 5000");
+        engine.wait_for_search();
+        assert!(start.elapsed() < Duration::from_millis(2000));
+        assert!(engine.last_best_move.lock().is_some());
+
+        // The budget belongs to that go only
+        engine.handle_command("go depth 1");
+        engine.wait_for_search();
+        engine.handle_command("ucinewgame");
+        let result = engine.search_engine.lock().search(engine.board.clone(), 5, None);
+        assert!(result.nodes > 5000);
+    }
+
+    #[test]
+    fn test_commands_are_read_during_search() {
+        let mut engine = UCIEngine::new();
+        engine.handle_command("position fen r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8");
+
+        // go returns at once and the loop keeps taking commands
+        let start = Instant::now();
+        engine.handle_command("go infinite");
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert!(engine.handle_command("isready"));
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(engine.searching());
+
+        // stop ends it straight away, with a move
+        let start = Instant::now();
+        assert!(engine.handle_command("stop"));
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        assert!(!engine.searching());
+        assert!(engine.last_best_move.lock().is_some());
+
+        // So does quit, and a stop sent right after go isn't lost
+        engine.handle_command("go infinite");
+        assert!(!engine.handle_command("quit"));
+        assert!(engine.search_thread.is_none());
+        engine.handle_command("go infinite");
+        engine.handle_command("stop");
+        assert!(engine.search_thread.is_none());
+    }
+
+    #[test]
+    fn test_go_infinite_waits_for_stop() {
+        let mut engine = UCIEngine::new();
+        // Mate in one: every iteration after the first is instant
+        engine.handle_command("position fen 7k/5Q2/6K1/8/8/8/8/8 w - - 0 1");
+
+        engine.handle_command("go infinite");
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(engine.searching());
+        assert!(engine.bestmove_pending.load(Ordering::Relaxed));
+        assert!(engine.last_best_move.lock().is_none());
+
+        // The bestmove comes only once stop is read
+        assert!(engine.handle_command("stop"));
+        assert!(!engine.searching());
+        assert!(!engine.bestmove_pending.load(Ordering::Relaxed));
+        let best = engine.last_best_move.lock().map(|mv| mv.to_uci());
+        assert!(matches!(best.as_deref(), Some("f7g7" | "f7f8")), "{:?}", best);
+    }
+
+    #[test]
+    fn test_bestmove_line_names_ponder_move() {
+        let e2e4 = Move::new(12, 28, crate::movegen::DOUBLE_PAWN_PUSH);
+        let e7e5 = Move::new(52, 36, crate::movegen::DOUBLE_PAWN_PUSH);
+        let board = BoardState::default();
+        assert_eq!(bestmove_line(&board, Some(e2e4), Some(e7e5)), "bestmove e2e4 ponder e7e5");
+        assert_eq!(bestmove_line(&board, Some(e2e4), None), "bestmove e2e4");
+        assert_eq!(bestmove_line(&board, None, None), "bestmove 0000");
+    }
+
+    #[test]
+    fn test_register_and_ponderhit_keep_running() {
+        let mut engine = UCIEngine::new();
+        assert!(engine.handle_command("register later"));
+        assert!(engine.handle_command("register name Someone code 1234"));
+        assert!(engine.handle_command("ponderhit"));
+        assert!(engine.handle_command("isready"));
+        assert!(!engine.handle_command("quit"));
+    }
+
+    #[test]
+    fn test_allocation_late_in_game() {
+        let mut engine = UCIEngine::new();
+        engine.board.fullmove_number = 80;
+
+        // No movestogo takes the moves-remaining estimate
+        let allocated = engine.allocate_time(60000, 0, 0);
+        assert!(allocated >= 100 && allocated < 60000);
+    }
+}
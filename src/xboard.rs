@@ -0,0 +1,403 @@
+use crate::board::{BoardState, Color, GameResult};
+use crate::search::{IterationInfo, SearchEngine, SearchInfo, MATE_SCORE};
+use crate::movegen::Move;
+use std::io::{self, BufRead};
+use std::sync::Arc;
+
+// ══════════════════════════════════════════════════════════════════════════════
+// XBOARD / CECP PROTOCOL
+// ══════════════════════════════════════════════════════════════════════════════
+//
+// Same board and search as the UCI front end, driven the CECP way: the GUI
+// sends moves one at a time and the engine answers with `move <uci>` whenever
+// it is its turn, unless force mode is on. Moves use coordinate notation,
+// which is what `usermove` sends once we ask for it in `protover`.
+
+// Per-move budget when neither a clock, `st` nor `sd` bounds the search
+const DEFAULT_MOVE_TIME_MS: u64 = 5_000;
+
+// Thinking output: `ply score time nodes pv`, time in centiseconds and mates
+// as 100000 + moves. The protocol has one line per ply, so only the main PV.
+struct XBoardInfo;
+
+impl SearchInfo for XBoardInfo {
+    fn on_iteration(&self, info: &IterationInfo) {
+        if info.multipv > 1 {
+            return;
+        }
+        let score = if info.score.abs() > MATE_SCORE - 100 {
+            let mate_in = (MATE_SCORE - info.score.abs() + 1) / 2;
+            if info.score > 0 { 100000 + mate_in } else { -100000 - mate_in }
+        } else {
+            info.score
+        };
+        let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci()).collect();
+        println!("{} {} {} {} {}", info.depth, score, info.time_ms / 10, info.nodes, pv.join(" "));
+    }
+
+    fn on_currmove(&self, _root: &BoardState, _depth: u8, _mv: Move, _move_number: usize) {}
+
+    fn on_string(&self, msg: &str) {
+        println!("# {}", msg);
+    }
+}
+
+pub struct XBoardEngine {
+    board: BoardState,
+    search_engine: SearchEngine,
+    // Only record moves, never reply
+    force: bool,
+    engine_side: Color,
+    // Our clock in centiseconds, as the `time` command sends it
+    time_left: Option<u64>,
+    // `level`: moves per time control (0 for the whole game) and the
+    // increment in milliseconds
+    moves_per_session: u64,
+    increment_ms: u64,
+    // `st` seconds per move and `sd` depth limit
+    move_time: Option<u64>,
+    max_depth: u8,
+    // Positions before each move played, for undo and remove
+    history: Vec<BoardState>,
+}
+
+impl XBoardEngine {
+    pub fn new() -> Self {
+        let mut search_engine = SearchEngine::new(4);
+        search_engine.set_info(Arc::new(XBoardInfo));
+
+        XBoardEngine {
+            board: BoardState::default(),
+            search_engine,
+            force: false,
+            engine_side: Color::Black,
+            time_left: None,
+            moves_per_session: 0,
+            increment_ms: 0,
+            move_time: None,
+            max_depth: 64,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let reader = stdin.lock();
+
+        for line in reader.lines().map_while(Result::ok) {
+            let command = line.trim();
+            if !command.is_empty() && !self.handle_command(command) {
+                break;
+            }
+        }
+    }
+
+    pub fn handle_command(&mut self, command: &str) -> bool {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        if parts.is_empty() {
+            return true;
+        }
+
+        match parts[0] {
+            // Thinking output is always on
+            "xboard" | "accepted" | "rejected" | "random" | "post" | "nopost" | "hard" | "easy" | "computer" => {}
+            "protover" => self.protover(),
+            "new" => self.new_game(),
+            "force" => self.force = true,
+            "go" => {
+                self.force = false;
+                self.engine_side = self.board.side_to_move;
+                self.think();
+            }
+            "playother" => {
+                self.force = false;
+                self.engine_side = self.board.side_to_move.flip();
+            }
+            "usermove" => {
+                if parts.len() > 1 {
+                    self.usermove(parts[1]);
+                }
+            }
+            "setboard" => self.setboard(&parts[1..].join(" ")),
+            "time" => self.time_left = parts.get(1).and_then(|t| t.parse().ok()),
+            "level" => self.level(&parts[1..]),
+            // Only our own clock goes into the allocation
+            "otim" => {}
+            "st" => self.move_time = parts.get(1).and_then(|t| t.parse::<u64>().ok()).map(|s| s * 1000),
+            "sd" => self.max_depth = parts.get(1).and_then(|d| d.parse().ok()).unwrap_or(64),
+            "ping" => println!("pong {}", parts.get(1).unwrap_or(&"")),
+            // The search runs to completion before the next command is read,
+            // so by the time `?` arrives the move is already out. Searches are
+            // always bounded, by the clock, `sd` or DEFAULT_MOVE_TIME_MS.
+            "?" => {}
+            // Both arrive in force mode, so neither starts a search
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "result" => self.force = true,
+            "quit" => return false,
+            // Bare moves, for GUIs that ignore usermove=1
+            _ if looks_like_move(parts[0]) => self.usermove(parts[0]),
+            _ => println!("Error (unknown command): {}", parts[0]),
+        }
+
+        true
+    }
+
+    fn protover(&self) {
+        println!(
+            "feature myname=\"RustChessEngine\" setboard=1 usermove=1 ping=1 playother=1 \
+             colors=0 sigint=0 sigterm=0 analyze=0 done=1"
+        );
+    }
+
+    fn new_game(&mut self) {
+        self.board = BoardState::default();
+        self.history.clear();
+        self.search_engine.new_game();
+        self.force = false;
+        self.engine_side = Color::Black;
+        self.move_time = None;
+        self.max_depth = 64;
+    }
+
+    fn setboard(&mut self, fen: &str) {
+        match BoardState::from_fen(fen) {
+            Ok(board) => {
+                self.board = board;
+                self.history.clear();
+            }
+            Err(e) => println!("tellusererror Illegal position: {}", e),
+        }
+    }
+
+    // `level MPS BASE INC`: BASE is minutes or minutes:seconds, INC seconds.
+    // The clock starts at BASE until the first `time` command.
+    fn level(&mut self, args: &[&str]) {
+        let [mps, base, inc] = args else {
+            println!("Error (bad level): {}", args.join(" "));
+            return;
+        };
+
+        let base_seconds = match base.split_once(':') {
+            Some((minutes, seconds)) => minutes.parse::<f64>().ok().zip(seconds.parse::<f64>().ok()).map(|(m, s)| m * 60.0 + s),
+            None => base.parse::<f64>().ok().map(|m| m * 60.0),
+        };
+        let (Ok(mps), Some(base_seconds), Ok(inc)) = (mps.parse::<u64>(), base_seconds, inc.parse::<f64>()) else {
+            println!("Error (bad level): {}", args.join(" "));
+            return;
+        };
+
+        self.moves_per_session = mps;
+        self.increment_ms = (inc * 1000.0) as u64;
+        self.time_left = Some((base_seconds * 100.0) as u64);
+        self.move_time = None;
+    }
+
+    // Takes back `plies` moves, or as many as were played
+    fn take_back(&mut self, plies: usize) {
+        for _ in 0..plies {
+            match self.history.pop() {
+                Some(board) => self.board = board,
+                None => break,
+            }
+        }
+    }
+
+    fn play(&mut self, mv: &Move) {
+        self.history.push(self.board.clone());
+        self.board.make_move(mv);
+    }
+
+    // Plays the opponent's move and replies if it is now our turn
+    fn usermove(&mut self, text: &str) {
+        let before = self.board.clone();
+        match self.board.make_move_uci(text) {
+            Ok(true) => {
+                self.history.push(before);
+                if !self.force && self.board.side_to_move == self.engine_side {
+                    self.think();
+                }
+            }
+            _ => println!("Illegal move: {}", text),
+        }
+    }
+
+    fn think(&mut self) {
+        if self.report_result() {
+            return;
+        }
+
+        let result = self.search_engine.search(self.board.clone(), self.max_depth, self.allocate_time());
+        if let Some(mv) = result.best_move {
+            self.play(&mv);
+            println!("move {}", mv.to_uci());
+            self.report_result();
+        }
+    }
+
+    // A fixed `st` wins; otherwise a slice of what's left on our clock, an
+    // even share of it when the time control ends after a set number of
+    // moves, plus most of the increment. With no clock at all, only an `sd`
+    // limit goes without a time budget.
+    fn allocate_time(&self) -> Option<u64> {
+        if self.move_time.is_some() {
+            return self.move_time;
+        }
+        let moves_to_go = match self.moves_per_session {
+            0 => 30,
+            mps => mps - (self.board.fullmove_number as u64).saturating_sub(1) % mps,
+        };
+        match self.time_left {
+            Some(centis) => {
+                let left_ms = centis * 10;
+                Some((left_ms / moves_to_go + self.increment_ms * 3 / 4).min(left_ms / 2).max(50))
+            }
+            None if self.max_depth < 64 => None,
+            None => Some(DEFAULT_MOVE_TIME_MS),
+        }
+    }
+
+    // Announces a finished game; true if there was one
+    fn report_result(&self) -> bool {
+        let announcement = match self.board.result() {
+            GameResult::Ongoing => return false,
+            GameResult::Checkmate(Color::White) => "1-0 {White mates}",
+            GameResult::Checkmate(Color::Black) => "0-1 {Black mates}",
+            GameResult::Stalemate => "1/2-1/2 {Stalemate}",
+            GameResult::DrawSeventyFiveMove => "1/2-1/2 {75-move rule}",
+            GameResult::DrawRepetition => "1/2-1/2 {Threefold repetition}",
+            GameResult::DrawInsufficient => "1/2-1/2 {Insufficient material}",
+        };
+        println!("{}", announcement);
+        true
+    }
+}
+
+// Coordinate notation: from and to square, then an optional promotion piece
+fn looks_like_move(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let square = |file: u8, rank: u8| (b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank);
+    match bytes.len() {
+        4 => square(bytes[0], bytes[1]) && square(bytes[2], bytes[3]),
+        5 => square(bytes[0], bytes[1]) && square(bytes[2], bytes[3]) && b"qrbn".contains(&bytes[4]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usermove_in_force_mode() {
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("new");
+        engine.handle_command("force");
+        engine.handle_command("usermove e2e4");
+
+        let mut expected = BoardState::default();
+        assert!(expected.make_move_uci("e2e4").unwrap());
+        assert_eq!(engine.board.to_fen(), expected.to_fen());
+
+        // Illegal moves leave the board alone
+        engine.handle_command("usermove e2e4");
+        assert_eq!(engine.board.to_fen(), expected.to_fen());
+    }
+
+    #[test]
+    fn test_setboard() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut engine = XBoardEngine::new();
+        engine.handle_command(&format!("setboard {}", fen));
+        assert_eq!(engine.board.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_engine_replies_to_usermove() {
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("new");
+        engine.handle_command("sd 2");
+        engine.handle_command("setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        engine.handle_command("usermove e2e4");
+
+        // Black, the engine's side after `new`, has answered
+        assert_eq!(engine.board.side_to_move, Color::White);
+    }
+
+    #[test]
+    fn test_level_sets_the_clock() {
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("level 40 5 0");
+        assert_eq!(engine.time_left, Some(30_000));
+        // 40 moves to go on move one
+        assert_eq!(engine.allocate_time(), Some(7_500));
+
+        engine.handle_command("level 0 0:30 2");
+        assert_eq!(engine.time_left, Some(3_000));
+        assert_eq!(engine.increment_ms, 2_000);
+        assert_eq!(engine.allocate_time(), Some(30_000 / 30 + 1_500));
+
+        // A malformed level keeps the old one
+        engine.handle_command("level 40 x 0");
+        assert_eq!(engine.time_left, Some(3_000));
+
+        // Some GUIs send move number 0, which counts as move one
+        engine.handle_command("level 40 5 0");
+        engine.handle_command("setboard rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0");
+        assert_eq!(engine.board.fullmove_number, 0);
+        assert_eq!(engine.allocate_time(), Some(7_500));
+    }
+
+    #[test]
+    fn test_go_without_clock_is_bounded() {
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("new");
+        assert_eq!(engine.allocate_time(), Some(DEFAULT_MOVE_TIME_MS));
+
+        // A depth limit bounds it already
+        engine.handle_command("sd 6");
+        assert_eq!(engine.allocate_time(), None);
+
+        engine.handle_command("st 2");
+        assert_eq!(engine.allocate_time(), Some(2_000));
+    }
+
+    #[test]
+    fn test_undo_and_remove() {
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("new");
+        engine.handle_command("force");
+        for mv in ["e2e4", "e7e5", "g1f3"] {
+            engine.handle_command(&format!("usermove {}", mv));
+        }
+
+        let mut after_e4 = BoardState::default();
+        assert!(after_e4.make_move_uci("e2e4").unwrap());
+        engine.handle_command("remove");
+        assert_eq!(engine.board.to_fen(), after_e4.to_fen());
+
+        engine.handle_command("undo");
+        assert_eq!(engine.board.to_fen(), BoardState::default().to_fen());
+
+        // Nothing left to take back
+        engine.handle_command("undo");
+        assert_eq!(engine.board.to_fen(), BoardState::default().to_fen());
+    }
+
+    #[test]
+    fn test_unknown_commands_are_not_moves() {
+        assert!(looks_like_move("e2e4"));
+        assert!(looks_like_move("a7a8q"));
+        assert!(!looks_like_move("level"));
+        assert!(!looks_like_move("e2e9"));
+        assert!(!looks_like_move("e7e8k"));
+
+        let mut engine = XBoardEngine::new();
+        engine.handle_command("force");
+        engine.handle_command("hint");
+        engine.handle_command("e2e4");
+        let mut expected = BoardState::default();
+        assert!(expected.make_move_uci("e2e4").unwrap());
+        assert_eq!(engine.board.to_fen(), expected.to_fen());
+    }
+}
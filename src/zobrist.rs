@@ -1,6 +1,12 @@
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 
+// Fixed so keys, and with them hash-dependent behaviour, are identical from
+// run to run. Changing it changes every key.
+const ZOBRIST_SEED: u64 = 42;
+
+/// Random keys for the engine's internal position hash. Only meaningful
+/// inside this engine: opening books use the Polyglot keys instead.
 pub struct Zobrist {
     pub piece_keys: [[[u64; 64]; 7]; 2], // [color][piece][square]
     pub castle_keys: [u64; 16],           // 16 possible castling states
@@ -10,7 +16,7 @@ pub struct Zobrist {
 
 impl Zobrist {
     pub fn new() -> Self {
-        let mut rng = StdRng::seed_from_u64(42);
+        let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
         
         let mut piece_keys = [[[0u64; 64]; 7]; 2];
         for color in 0..2 {
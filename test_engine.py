@@ -2,6 +2,7 @@
 """Quick test to verify the engine is responding"""
 
 import chess_engine
+import threading
 import time
 
 print("Testing engine...")
@@ -26,4 +27,137 @@ print(f"Elapsed: {elapsed:.2f}s")
 if result.get('move'):
     print(f"✓ Engine is working! Best move: {result['move']}")
 else:
-    print("✗ Engine returned no move!")
\ No newline at end of file
+    print("✗ Engine returned no move!")
+
+# stop() from another thread: search releases the GIL, so this runs mid-search
+print("\nStopping an unbounded search from a second thread...")
+
+stopper = threading.Timer(0.5, engine.stop)
+start = time.time()
+stopper.start()
+result = engine.search(fen, depth=64)
+elapsed = time.time() - start
+
+if elapsed < 3.0 and result.get('move'):
+    print(f"✓ Search stopped after {elapsed:.2f}s with {result['move']}")
+else:
+    print(f"✗ Search did not stop promptly ({elapsed:.2f}s)")
+
+# An infinite search ignores depth and time and waits for stop(), even
+# once a mate has been found
+print("\nRunning an infinite search until stop()...")
+
+stopper = threading.Timer(0.5, engine.stop)
+start = time.time()
+stopper.start()
+result = engine.search("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1", depth=1, infinite=True)
+elapsed = time.time() - start
+
+if 0.4 <= elapsed < 3.0 and result.get('move') == "d1d8":
+    print(f"✓ Infinite search returned {result['move']} after stop ({elapsed:.2f}s)")
+else:
+    print(f"✗ Infinite search returned {result.get('move')} after {elapsed:.2f}s")
+
+# analyse() reports each finished depth to the callback while it searches
+print("\nAnalysing with a callback...")
+
+reports = []
+analysed = engine.analyse("r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R2QKB1R w KQ - 0 8", 6, reports.append)
+depths = [info['depth'] for info in reports if info['multipv'] == 1]
+
+if depths == [1, 2, 3, 4, 5, 6] and reports[-1]['pv'][0] == analysed.get('move'):
+    print(f"✓ Callback saw depths {depths}, ending on {analysed['move']}")
+else:
+    print(f"✗ Callback saw depths {depths}, last pv {reports[-1]['pv'] if reports else None}, move {analysed.get('move')}")
+
+# A ponder search ignores its time until ponderhit(), then uses it
+print("\nPondering until ponderhit()...")
+
+hitter = threading.Timer(0.5, engine.ponderhit)
+start = time.time()
+hitter.start()
+result = engine.search(fen, time_ms=200, ponder=True)
+elapsed = time.time() - start
+
+if 0.5 <= elapsed < 2.0 and result.get('move'):
+    print(f"✓ Ponder search returned {result['move']} after ponderhit ({elapsed:.2f}s)")
+else:
+    print(f"✗ Ponder search returned {result.get('move')} after {elapsed:.2f}s")
+
+# A node budget ends the search and gives the same answer every time
+print("\nSearching with a node limit...")
+
+engine.set_deterministic(True)
+first = engine.search(fen, nodes=20000)
+second = engine.search(fen, nodes=20000)
+engine.set_deterministic(False)
+
+if first['nodes'] == 20000 and first.get('move') == second.get('move'):
+    print(f"✓ Stopped at {first['nodes']} nodes on {first['move']} both times")
+else:
+    print(f"✗ Node-limited searches gave {first['nodes']} nodes {first.get('move')}, then {second.get('move')}")
+
+# One search per engine: a second search() while the first runs is refused
+print("\nStarting a second search while one is running...")
+
+runner = threading.Thread(target=engine.search, args=(fen,), kwargs={"depth": 64, "time_ms": 1000})
+runner.start()
+time.sleep(0.2)
+try:
+    engine.search(fen, depth=5)
+    print("✗ Overlapping search was not refused")
+except RuntimeError as e:
+    print(f"✓ Overlapping search refused: {e}")
+runner.join()
+
+# Options by UCI name: with contempt a forced perpetual costs the side that
+# gives it, and the score reports that
+print("\nSetting Contempt through set_option...")
+
+perpetual = "8/6pk/8/8/8/8/rr6/3Q2K1 w - - 0 1"
+engine = chess_engine.PyChessEngine(threads=1)
+before = engine.search(perpetual, depth=6)['score']
+engine.set_option("Contempt", "50")
+after = engine.search(perpetual, depth=6)['score']
+
+if before == 0 and after == -50:
+    print(f"✓ Contempt applied: draw scored {before} then {after}")
+else:
+    print(f"✗ Contempt not reflected: draw scored {before} then {after}")
+
+# Take-backs on a board: push then pop returns to the same position
+print("\nPushing and popping moves on a board...")
+
+board = chess_engine.PyBoardState()
+start_fen = board.to_fen()
+board.push("e2e4")
+board.push("e7e5")
+moves = board.move_stack()
+board.pop()
+board.pop()
+
+if moves == ["e2e4", "e7e5"] and board.to_fen() == start_fen and board.move_stack() == []:
+    print("✓ push/pop restored the starting position")
+else:
+    print(f"✗ push/pop left {board.to_fen()} with stack {board.move_stack()}")
+
+try:
+    board.pop()
+    print("✗ pop on an empty stack did not raise")
+except IndexError as e:
+    print(f"✓ Empty pop refused: {e}")
+
+# Position keys: the internal key is fixed across runs, the Polyglot key
+# matches the published startpos value
+print("\nChecking position keys...")
+
+board = chess_engine.PyBoardState()
+key = board.zobrist()
+board.push("e2e4")
+moved = board.zobrist()
+board.pop()
+
+if key == 0x6299d710a4b45a29 and moved != key and board.zobrist() == key and board.polyglot_key() == 0x463b96181691fc9c:
+    print(f"✓ Keys stable: zobrist {key:#x}, polyglot {board.polyglot_key():#x}")
+else:
+    print(f"✗ Unexpected keys: zobrist {key:#x}, after e4 {moved:#x}, polyglot {board.polyglot_key():#x}")